@@ -0,0 +1,522 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! SPA device interface.
+//!
+//! This module allows implementing a device in Rust with the [`SpaDevice`] trait.
+//! A device announces the objects it provides, usually nodes, with [`DeviceEmitter::object_info`],
+//! and is configured through params, such as `EnumProfile`/`Profile` and `EnumRoute`/`Route`.
+//! Wrapped in a [`Device`], the implementation can be used anywhere a `struct spa_device`
+//! is expected, for example to export it to a PipeWire server.
+
+use std::{
+    ffi::CStr,
+    fmt, mem,
+    os::raw::{c_int, c_void},
+    pin::Pin,
+    ptr,
+};
+
+use nix::errno::Errno;
+
+use crate::{
+    param::{ParamInfo, ParamType},
+    pod::Pod,
+    utils::{dict::DictRef, hook::HookList, result::to_c},
+};
+
+/// Information about a device, emitted to its listeners with [`DeviceEmitter::info`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DeviceInfo<'a> {
+    /// Which of the following fields changed, a combination of `SPA_DEVICE_CHANGE_MASK_*`.
+    pub change_mask: u64,
+    pub flags: u64,
+    pub props: Option<&'a DictRef>,
+    pub params: &'a [ParamInfo],
+}
+
+impl DeviceInfo<'_> {
+    fn to_raw(self) -> spa_sys::spa_device_info {
+        spa_sys::spa_device_info {
+            version: spa_sys::SPA_VERSION_DEVICE_INFO,
+            change_mask: self.change_mask,
+            flags: self.flags,
+            props: self
+                .props
+                .map_or(ptr::null(), |props| props.as_raw_ptr().cast_const()),
+            params: self.params.as_ptr().cast_mut().cast(),
+            n_params: self.params.len() as u32,
+        }
+    }
+}
+
+/// Information about an object provided by a device, emitted with [`DeviceEmitter::object_info`].
+///
+/// The host creates the object with the factory `factory_name`, passing it `props`.
+#[derive(Debug, Clone, Copy)]
+pub struct ObjectInfo<'a> {
+    /// The type of the interface of the object, such as `Spa:Pointer:Interface:Node`.
+    pub type_: &'a CStr,
+    pub factory_name: &'a CStr,
+    /// Which of the following fields changed, a combination of `SPA_DEVICE_OBJECT_CHANGE_MASK_*`.
+    pub change_mask: u64,
+    pub flags: u64,
+    pub props: Option<&'a DictRef>,
+}
+
+impl ObjectInfo<'_> {
+    fn to_raw(self) -> spa_sys::spa_device_object_info {
+        spa_sys::spa_device_object_info {
+            version: spa_sys::SPA_VERSION_DEVICE_OBJECT_INFO,
+            type_: self.type_.as_ptr(),
+            factory_name: self.factory_name.as_ptr(),
+            change_mask: self.change_mask,
+            flags: self.flags,
+            props: self
+                .props
+                .map_or(ptr::null(), |props| props.as_raw_ptr().cast_const()),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Listeners<'a> {
+    All(&'a HookList),
+    One(*const spa_sys::spa_device_events, *mut c_void),
+}
+
+/// Emits events to the listeners of a device.
+///
+/// An emitter is passed to the [`SpaDevice`] methods.
+/// Events can also be emitted at any time with [`Device::emitter`].
+pub struct DeviceEmitter<'a> {
+    listeners: Listeners<'a>,
+}
+
+impl DeviceEmitter<'_> {
+    fn emit<F>(&self, f: F)
+    where
+        F: Fn(&spa_sys::spa_device_events, *mut c_void),
+    {
+        match self.listeners {
+            Listeners::All(hooks) => hooks.for_each(|funcs, data| unsafe {
+                if let Some(events) = funcs.cast::<spa_sys::spa_device_events>().as_ref() {
+                    f(events, data);
+                }
+            }),
+            Listeners::One(events, data) => {
+                if let Some(events) = unsafe { events.as_ref() } {
+                    f(events, data);
+                }
+            }
+        }
+    }
+
+    /// Emit the info of the device.
+    pub fn info(&self, info: &DeviceInfo) {
+        let info = info.to_raw();
+        self.emit(|events, data| {
+            if let Some(cb) = events.info {
+                unsafe { cb(data, &info) }
+            }
+        });
+    }
+
+    /// Emit the info of the object `id` of the device, or [`None`] when the object was removed.
+    pub fn object_info(&self, id: u32, info: Option<&ObjectInfo>) {
+        let info = info.map(|info| info.to_raw());
+        let info_ptr = info.as_ref().map_or(ptr::null(), |info| info as *const _);
+        self.emit(|events, data| {
+            if let Some(cb) = events.object_info {
+                unsafe { cb(data, id, info_ptr) }
+            }
+        });
+    }
+
+    /// Emit a raw result.
+    ///
+    /// # Safety
+    /// `result` must point to a valid result of type `type_`, or be null if `type_` is 0.
+    pub unsafe fn result(&self, seq: i32, res: i32, type_: u32, result: *const c_void) {
+        self.emit(|events, data| {
+            if let Some(cb) = events.result {
+                cb(data, seq, res, type_, result)
+            }
+        });
+    }
+
+    /// Emit a param enumerated by [`SpaDevice::enum_params`].
+    ///
+    /// `seq` is the sequence number the method was called with, `index` is the index of `param`
+    /// in the enumeration and `next` the index of the next one.
+    pub fn param(&self, seq: i32, id: ParamType, index: u32, next: u32, param: &Pod) {
+        let result = spa_sys::spa_result_device_params {
+            id: id.as_raw(),
+            index,
+            next,
+            param: param.as_raw_ptr(),
+        };
+
+        unsafe {
+            self.result(
+                seq,
+                0,
+                spa_sys::SPA_RESULT_TYPE_DEVICE_PARAMS,
+                ptr::addr_of!(result).cast(),
+            )
+        }
+    }
+
+    /// Emit an event, an object of type `SPA_TYPE_EVENT_Device`.
+    pub fn event(&self, event: &Pod) {
+        let event: *const spa_sys::spa_event = event.as_raw_ptr().cast_const().cast();
+        self.emit(|events, data| {
+            if let Some(cb) = events.event {
+                unsafe { cb(data, event) }
+            }
+        });
+    }
+}
+
+/// A device implemented in Rust.
+///
+/// All methods are called from the main thread.
+///
+/// Methods returning an error default to [`Errno::ENOTSUP`].
+pub trait SpaDevice {
+    /// A listener was added to the device.
+    ///
+    /// The implementation must emit the info of the device and the info of all its objects
+    /// to the new listener using `emitter`.
+    fn add_listener(&self, emitter: &DeviceEmitter);
+
+    /// Enumerate up to `max` params of type `id` of the device, starting at index `start`,
+    /// which are compatible with `filter`.
+    ///
+    /// The params are emitted with [`DeviceEmitter::param`], passing `seq`.
+    /// Devices usually provide the available profiles and routes as [`ParamType::EnumProfile`]
+    /// and [`ParamType::EnumRoute`], and the active ones as [`ParamType::Profile`]
+    /// and [`ParamType::Route`].
+    fn enum_params(
+        &self,
+        emitter: &DeviceEmitter,
+        seq: i32,
+        id: ParamType,
+        start: u32,
+        max: u32,
+        filter: Option<&Pod>,
+    ) -> Result<(), Errno> {
+        let _ = (emitter, seq, id, start, max, filter);
+        Err(Errno::ENOTSUP)
+    }
+
+    /// Set the param of type `id` of the device, such as [`ParamType::Profile`]
+    /// to switch the active profile.
+    fn set_param(
+        &self,
+        emitter: &DeviceEmitter,
+        id: ParamType,
+        flags: u32,
+        param: Option<&Pod>,
+    ) -> Result<(), Errno> {
+        let _ = (emitter, id, flags, param);
+        Err(Errno::ENOTSUP)
+    }
+}
+
+#[repr(C)]
+struct DeviceInner<T: SpaDevice> {
+    // Must be the first member, as the device is cast to and from a `spa_device` pointer.
+    device: spa_sys::spa_device,
+    methods: spa_sys::spa_device_methods,
+    hooks: HookList,
+    implementation: T,
+}
+
+/// A `spa_device` implemented by a [`SpaDevice`].
+pub struct Device<T: SpaDevice> {
+    inner: Pin<Box<DeviceInner<T>>>,
+}
+
+impl<T: SpaDevice> Device<T> {
+    pub fn new(implementation: T) -> Self {
+        let mut inner = Box::pin(DeviceInner {
+            device: unsafe { mem::zeroed() },
+            methods: spa_sys::spa_device_methods {
+                version: spa_sys::SPA_VERSION_DEVICE_METHODS,
+                add_listener: Some(device_add_listener::<T>),
+                sync: Some(device_sync::<T>),
+                enum_params: Some(device_enum_params::<T>),
+                set_param: Some(device_set_param::<T>),
+            },
+            hooks: HookList::new(),
+            implementation,
+        });
+
+        unsafe {
+            let inner = inner.as_mut().get_unchecked_mut();
+            let inner_ptr: *mut DeviceInner<T> = inner;
+            inner.device.iface = spa_sys::spa_interface {
+                type_: CStr::from_bytes_with_nul(spa_sys::SPA_TYPE_INTERFACE_Device)
+                    .unwrap()
+                    .as_ptr(),
+                version: spa_sys::SPA_VERSION_DEVICE,
+                cb: spa_sys::spa_callbacks {
+                    funcs: ptr::addr_of!(inner.methods).cast(),
+                    data: inner_ptr.cast(),
+                },
+            };
+        }
+
+        Self { inner }
+    }
+
+    pub fn as_raw_ptr(&self) -> *mut spa_sys::spa_device {
+        ptr::addr_of!(self.inner.device).cast_mut()
+    }
+
+    /// The implementation of the device.
+    pub fn implementation(&self) -> &T {
+        &self.inner.implementation
+    }
+
+    /// An emitter for all listeners of the device, for example to emit updated info
+    /// or announce new objects.
+    pub fn emitter(&self) -> DeviceEmitter<'_> {
+        DeviceEmitter {
+            listeners: Listeners::All(&self.inner.hooks),
+        }
+    }
+}
+
+impl<T: SpaDevice + fmt::Debug> fmt::Debug for Device<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Device")
+            .field("implementation", &self.inner.implementation)
+            .finish()
+    }
+}
+
+unsafe fn inner<'a, T: SpaDevice>(object: *mut c_void) -> &'a DeviceInner<T> {
+    &*(object as *const DeviceInner<T>)
+}
+
+unsafe fn pod_opt<'a>(pod: *const spa_sys::spa_pod) -> Option<&'a Pod> {
+    (!pod.is_null()).then(|| Pod::from_raw(pod))
+}
+
+unsafe extern "C" fn device_add_listener<T: SpaDevice>(
+    object: *mut c_void,
+    listener: *mut spa_sys::spa_hook,
+    events: *const spa_sys::spa_device_events,
+    data: *mut c_void,
+) -> c_int {
+    let inner = inner::<T>(object);
+    inner.hooks.append(listener, events.cast(), data);
+
+    let emitter = DeviceEmitter {
+        listeners: Listeners::One(events, data),
+    };
+    inner.implementation.add_listener(&emitter);
+
+    0
+}
+
+unsafe extern "C" fn device_sync<T: SpaDevice>(object: *mut c_void, seq: c_int) -> c_int {
+    let inner = inner::<T>(object);
+    DeviceEmitter {
+        listeners: Listeners::All(&inner.hooks),
+    }
+    .result(seq, 0, 0, ptr::null());
+
+    0
+}
+
+unsafe extern "C" fn device_enum_params<T: SpaDevice>(
+    object: *mut c_void,
+    seq: c_int,
+    id: u32,
+    start: u32,
+    max: u32,
+    filter: *const spa_sys::spa_pod,
+) -> c_int {
+    let inner = inner::<T>(object);
+    let emitter = DeviceEmitter {
+        listeners: Listeners::All(&inner.hooks),
+    };
+
+    to_c(inner.implementation.enum_params(
+        &emitter,
+        seq,
+        ParamType::from_raw(id),
+        start,
+        max,
+        pod_opt(filter),
+    ))
+}
+
+unsafe extern "C" fn device_set_param<T: SpaDevice>(
+    object: *mut c_void,
+    id: u32,
+    flags: u32,
+    param: *const spa_sys::spa_pod,
+) -> c_int {
+    let inner = inner::<T>(object);
+    let emitter = DeviceEmitter {
+        listeners: Listeners::All(&inner.hooks),
+    };
+
+    to_c(
+        inner
+            .implementation
+            .set_param(&emitter, ParamType::from_raw(id), flags, pod_opt(param)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct TestDevice {
+        enumerated: Cell<Option<(ParamType, u32, u32)>>,
+    }
+
+    impl SpaDevice for TestDevice {
+        fn add_listener(&self, emitter: &DeviceEmitter) {
+            emitter.info(&DeviceInfo {
+                change_mask: spa_sys::SPA_DEVICE_CHANGE_MASK_FLAGS as u64,
+                flags: 1,
+                ..Default::default()
+            });
+            emitter.object_info(
+                0,
+                Some(&ObjectInfo {
+                    type_: c"Spa:Pointer:Interface:Node",
+                    factory_name: c"test-node",
+                    change_mask: 0,
+                    flags: 0,
+                    props: None,
+                }),
+            );
+        }
+
+        fn enum_params(
+            &self,
+            _emitter: &DeviceEmitter,
+            _seq: i32,
+            id: ParamType,
+            start: u32,
+            max: u32,
+            _filter: Option<&Pod>,
+        ) -> Result<(), Errno> {
+            self.enumerated.set(Some((id, start, max)));
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct Received {
+        flags: u64,
+        objects: Vec<(u32, String)>,
+        results: Vec<i32>,
+    }
+
+    unsafe extern "C" fn on_info(data: *mut c_void, info: *const spa_sys::spa_device_info) {
+        let received = &mut *(data as *mut Received);
+        received.flags = (*info).flags;
+    }
+
+    unsafe extern "C" fn on_object_info(
+        data: *mut c_void,
+        id: u32,
+        info: *const spa_sys::spa_device_object_info,
+    ) {
+        let received = &mut *(data as *mut Received);
+        let factory_name = CStr::from_ptr((*info).factory_name);
+        received
+            .objects
+            .push((id, factory_name.to_string_lossy().into_owned()));
+    }
+
+    unsafe extern "C" fn on_result(
+        data: *mut c_void,
+        seq: c_int,
+        _res: c_int,
+        _type: u32,
+        _result: *const c_void,
+    ) {
+        let received = &mut *(data as *mut Received);
+        received.results.push(seq);
+    }
+
+    #[test]
+    fn methods() {
+        let device = Device::new(TestDevice::default());
+        let events = spa_sys::spa_device_events {
+            version: spa_sys::SPA_VERSION_DEVICE_EVENTS,
+            info: Some(on_info),
+            result: Some(on_result),
+            event: None,
+            object_info: Some(on_object_info),
+        };
+        let mut received = Received::default();
+        let mut hook: spa_sys::spa_hook = unsafe { mem::zeroed() };
+
+        unsafe {
+            let res = crate::spa_interface_call_method!(
+                device.as_raw_ptr(),
+                spa_sys::spa_device_methods,
+                add_listener,
+                &mut hook,
+                &events,
+                ptr::addr_of_mut!(received).cast()
+            );
+            assert_eq!(res, 0);
+        }
+        assert_eq!(received.flags, 1);
+        assert_eq!(received.objects, [(0, "test-node".to_owned())]);
+
+        unsafe {
+            let res = crate::spa_interface_call_method!(
+                device.as_raw_ptr(),
+                spa_sys::spa_device_methods,
+                sync,
+                42
+            );
+            assert_eq!(res, 0);
+
+            let res = crate::spa_interface_call_method!(
+                device.as_raw_ptr(),
+                spa_sys::spa_device_methods,
+                enum_params,
+                7,
+                ParamType::EnumProfile.as_raw(),
+                1,
+                u32::MAX,
+                ptr::null()
+            );
+            assert_eq!(res, 0);
+
+            let res = crate::spa_interface_call_method!(
+                device.as_raw_ptr(),
+                spa_sys::spa_device_methods,
+                set_param,
+                ParamType::Profile.as_raw(),
+                0,
+                ptr::null()
+            );
+            assert_eq!(res, -(Errno::ENOTSUP as c_int));
+        }
+        assert_eq!(received.results, [42]);
+        assert_eq!(
+            device.implementation().enumerated.get(),
+            Some((ParamType::EnumProfile, 1, u32::MAX))
+        );
+
+        crate::utils::hook::remove(hook);
+        assert!(device.inner.hooks.is_empty());
+    }
+}
@@ -7,6 +7,8 @@
 //! [libspa]: https://docs.pipewire.org/page_spa.html
 
 pub mod buffer;
+pub mod device;
+pub mod node;
 pub mod param;
 pub mod pod;
 pub mod support;
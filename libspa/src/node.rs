@@ -0,0 +1,960 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! SPA node interface.
+//!
+//! This module allows implementing a node in Rust with the [`SpaNode`] trait.
+//! Wrapped in a [`Node`], the implementation can be used anywhere a `struct spa_node` is expected,
+//! for example to export it to a PipeWire server.
+
+use std::{
+    ffi::CStr,
+    fmt, mem,
+    os::raw::{c_int, c_void},
+    pin::Pin,
+    ptr,
+    sync::Mutex,
+};
+
+use bitflags::bitflags;
+use nix::errno::Errno;
+
+use crate::{
+    param::{ParamInfo, ParamType},
+    pod::{
+        builder::{builder_add, Builder},
+        Pod,
+    },
+    utils::{dict::DictRef, hook::HookList, result::to_c, Direction, Fraction, SpaTypes},
+};
+
+bitflags! {
+    /// The status of a node, returned by [`SpaNode::process`].
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub struct Status: u32 {
+        const OK = spa_sys::SPA_STATUS_OK;
+        const NEED_DATA = spa_sys::SPA_STATUS_NEED_DATA;
+        const HAVE_DATA = spa_sys::SPA_STATUS_HAVE_DATA;
+        const STOPPED = spa_sys::SPA_STATUS_STOPPED;
+        const DRAINED = spa_sys::SPA_STATUS_DRAINED;
+    }
+}
+
+/// Information about a node, emitted to its listeners with [`NodeEmitter::info`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NodeInfo<'a> {
+    pub max_input_ports: u32,
+    pub max_output_ports: u32,
+    /// Which of the following fields changed, a combination of `SPA_NODE_CHANGE_MASK_*`.
+    pub change_mask: u64,
+    /// A combination of `SPA_NODE_FLAG_*`.
+    pub flags: u64,
+    pub props: Option<&'a DictRef>,
+    pub params: &'a [ParamInfo],
+}
+
+impl NodeInfo<'_> {
+    fn to_raw(self) -> spa_sys::spa_node_info {
+        spa_sys::spa_node_info {
+            max_input_ports: self.max_input_ports,
+            max_output_ports: self.max_output_ports,
+            change_mask: self.change_mask,
+            flags: self.flags,
+            props: self.props.map_or(ptr::null_mut(), DictRef::as_raw_ptr),
+            params: self.params.as_ptr().cast_mut().cast(),
+            n_params: self.params.len() as u32,
+        }
+    }
+}
+
+/// Information about a port of a node, emitted to its listeners with [`NodeEmitter::port_info`].
+#[derive(Debug, Clone, Copy)]
+pub struct PortInfo<'a> {
+    /// Which of the following fields changed, a combination of `SPA_PORT_CHANGE_MASK_*`.
+    pub change_mask: u64,
+    /// A combination of `SPA_PORT_FLAG_*`.
+    pub flags: u64,
+    pub rate: Fraction,
+    pub props: Option<&'a DictRef>,
+    pub params: &'a [ParamInfo],
+}
+
+impl Default for PortInfo<'_> {
+    fn default() -> Self {
+        Self {
+            change_mask: 0,
+            flags: 0,
+            rate: Fraction { num: 0, denom: 1 },
+            props: None,
+            params: &[],
+        }
+    }
+}
+
+impl PortInfo<'_> {
+    fn to_raw(self) -> spa_sys::spa_port_info {
+        spa_sys::spa_port_info {
+            change_mask: self.change_mask,
+            flags: self.flags,
+            rate: self.rate,
+            props: self
+                .props
+                .map_or(ptr::null(), |props| props.as_raw_ptr().cast_const()),
+            params: self.params.as_ptr().cast_mut().cast(),
+            n_params: self.params.len() as u32,
+        }
+    }
+}
+
+/// A command sent to a node, the id of an object of type `SPA_TYPE_COMMAND_Node`.
+///
+/// Commands are sent to nodes with [`SpaNode::send_command`], or to remote nodes
+/// with the `send_command` method of the PipeWire node proxy.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct NodeCommand(spa_sys::spa_node_command);
+
+#[allow(non_upper_case_globals)]
+impl NodeCommand {
+    /// Suspend the node, releasing its resources.
+    pub const Suspend: Self = Self(spa_sys::SPA_NODE_COMMAND_Suspend);
+    /// Pause the processing of the node.
+    pub const Pause: Self = Self(spa_sys::SPA_NODE_COMMAND_Pause);
+    /// Start the processing of the node.
+    pub const Start: Self = Self(spa_sys::SPA_NODE_COMMAND_Start);
+    pub const Enable: Self = Self(spa_sys::SPA_NODE_COMMAND_Enable);
+    pub const Disable: Self = Self(spa_sys::SPA_NODE_COMMAND_Disable);
+    pub const Flush: Self = Self(spa_sys::SPA_NODE_COMMAND_Flush);
+    pub const Drain: Self = Self(spa_sys::SPA_NODE_COMMAND_Drain);
+    pub const Marker: Self = Self(spa_sys::SPA_NODE_COMMAND_Marker);
+    pub const ParamBegin: Self = Self(spa_sys::SPA_NODE_COMMAND_ParamBegin);
+    pub const ParamEnd: Self = Self(spa_sys::SPA_NODE_COMMAND_ParamEnd);
+    /// Ask the node to start a processing cycle of its graph.
+    pub const RequestProcess: Self = Self(spa_sys::SPA_NODE_COMMAND_RequestProcess);
+
+    pub fn from_raw(raw: spa_sys::spa_node_command) -> Self {
+        Self(raw)
+    }
+
+    pub fn as_raw(&self) -> spa_sys::spa_node_command {
+        self.0
+    }
+
+    /// Add the command pod to `builder`.
+    pub fn build(&self, builder: &mut Builder) -> Result<(), Errno> {
+        builder_add!(builder, Object(SpaTypes::CommandNode.as_raw(), self.as_raw()) {})
+    }
+
+    /// Serialize the command into the bytes of a pod, see [`Pod::from_bytes`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut builder = Builder::new(&mut data);
+        self.build(&mut builder)
+            .expect("Builder grows its buffer as needed");
+        drop(builder);
+        data
+    }
+
+    /// Get the command of a pod received by [`SpaNode::send_command`].
+    ///
+    /// Returns [`None`] if `pod` is not a node command.
+    pub fn from_pod(pod: &Pod) -> Option<Self> {
+        let object = pod.as_object().ok()?;
+        (object.type_() == SpaTypes::CommandNode).then(|| Self(object.id().0))
+    }
+}
+
+impl fmt::Debug for NodeCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match *self {
+            Self::Suspend => "Suspend",
+            Self::Pause => "Pause",
+            Self::Start => "Start",
+            Self::Enable => "Enable",
+            Self::Disable => "Disable",
+            Self::Flush => "Flush",
+            Self::Drain => "Drain",
+            Self::Marker => "Marker",
+            Self::ParamBegin => "ParamBegin",
+            Self::ParamEnd => "ParamEnd",
+            Self::RequestProcess => "RequestProcess",
+            _ => return write!(f, "NodeCommand({})", self.0),
+        };
+        write!(f, "NodeCommand::{name}")
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Listeners<'a> {
+    All(&'a HookList),
+    One(*const spa_sys::spa_node_events, *mut c_void),
+}
+
+/// Emits events to the listeners of a node.
+///
+/// An emitter is passed to the [`SpaNode`] methods that may emit events.
+/// Events can also be emitted at any time from the main thread with [`Node::emitter`].
+pub struct NodeEmitter<'a> {
+    listeners: Listeners<'a>,
+}
+
+impl NodeEmitter<'_> {
+    fn emit<F>(&self, f: F)
+    where
+        F: Fn(&spa_sys::spa_node_events, *mut c_void),
+    {
+        match self.listeners {
+            Listeners::All(hooks) => hooks.for_each(|funcs, data| unsafe {
+                if let Some(events) = funcs.cast::<spa_sys::spa_node_events>().as_ref() {
+                    f(events, data);
+                }
+            }),
+            Listeners::One(events, data) => {
+                if let Some(events) = unsafe { events.as_ref() } {
+                    f(events, data);
+                }
+            }
+        }
+    }
+
+    /// Emit the info of the node.
+    pub fn info(&self, info: &NodeInfo) {
+        let info = info.to_raw();
+        self.emit(|events, data| {
+            if let Some(cb) = events.info {
+                unsafe { cb(data, &info) }
+            }
+        });
+    }
+
+    /// Emit the info of a port of the node, or [`None`] when the port was removed.
+    pub fn port_info(&self, direction: Direction, port_id: u32, info: Option<&PortInfo>) {
+        let info = info.map(|info| info.to_raw());
+        let info_ptr = info.as_ref().map_or(ptr::null(), |info| info as *const _);
+        self.emit(|events, data| {
+            if let Some(cb) = events.port_info {
+                unsafe { cb(data, direction.as_raw(), port_id, info_ptr) }
+            }
+        });
+    }
+
+    /// Emit a raw result.
+    ///
+    /// # Safety
+    /// `result` must point to a valid result of type `type_`, or be null if `type_` is 0.
+    pub unsafe fn result(&self, seq: i32, res: i32, type_: u32, result: *const c_void) {
+        self.emit(|events, data| {
+            if let Some(cb) = events.result {
+                cb(data, seq, res, type_, result)
+            }
+        });
+    }
+
+    /// Emit a param enumerated by [`SpaNode::enum_params`] or [`SpaNode::port_enum_params`].
+    ///
+    /// `seq` is the sequence number the method was called with, `index` is the index of `param`
+    /// in the enumeration and `next` the index of the next one.
+    pub fn param(&self, seq: i32, id: ParamType, index: u32, next: u32, param: &Pod) {
+        let result = spa_sys::spa_result_node_params {
+            id: id.as_raw(),
+            index,
+            next,
+            param: param.as_raw_ptr(),
+        };
+
+        unsafe {
+            self.result(
+                seq,
+                0,
+                spa_sys::SPA_RESULT_TYPE_NODE_PARAMS,
+                ptr::addr_of!(result).cast(),
+            )
+        }
+    }
+
+    /// Emit an event, such as an error or a request to the host.
+    ///
+    /// `event` needs to be an object of type `SPA_TYPE_EVENT_Node`.
+    pub fn event(&self, event: &Pod) {
+        let event: *const spa_sys::spa_event = event.as_raw_ptr().cast_const().cast();
+        self.emit(|events, data| {
+            if let Some(cb) = events.event {
+                unsafe { cb(data, event) }
+            }
+        });
+    }
+}
+
+/// A node implemented in Rust.
+///
+/// All methods besides [`process`](`Self::process`) are called from the main thread.
+/// `process` is called from the data thread, so it may run concurrently with the other methods.
+///
+/// Methods returning an error default to [`Errno::ENOTSUP`].
+pub trait SpaNode: Send + Sync {
+    /// A listener was added to the node.
+    ///
+    /// The implementation must emit the info of the node and the info of all its ports
+    /// to the new listener using `emitter`.
+    fn add_listener(&self, emitter: &NodeEmitter);
+
+    /// Enumerate up to `max` params of type `id` of the node, starting at index `start`,
+    /// which are compatible with `filter`.
+    ///
+    /// The params are emitted with [`NodeEmitter::param`], passing `seq`.
+    fn enum_params(
+        &self,
+        emitter: &NodeEmitter,
+        seq: i32,
+        id: ParamType,
+        start: u32,
+        max: u32,
+        filter: Option<&Pod>,
+    ) -> Result<(), Errno> {
+        let _ = (emitter, seq, id, start, max, filter);
+        Err(Errno::ENOTSUP)
+    }
+
+    /// Set the param of type `id` of the node, or reset it if `param` is [`None`].
+    fn set_param(
+        &self,
+        emitter: &NodeEmitter,
+        id: ParamType,
+        flags: u32,
+        param: Option<&Pod>,
+    ) -> Result<(), Errno> {
+        let _ = (emitter, id, flags, param);
+        Err(Errno::ENOTSUP)
+    }
+
+    /// Configure the io area of type `id` (a `SPA_IO_*` constant), shared with the host.
+    ///
+    /// `data` points to the area of `size` bytes, or is null to remove it.
+    /// The area stays valid until it is replaced.
+    fn set_io(&self, id: u32, data: *mut c_void, size: usize) -> Result<(), Errno> {
+        let _ = (id, data, size);
+        Err(Errno::ENOTSUP)
+    }
+
+    /// Handle a command, an object of type `SPA_TYPE_COMMAND_Node`.
+    fn send_command(&self, emitter: &NodeEmitter, command: &Pod) -> Result<(), Errno> {
+        let _ = (emitter, command);
+        Err(Errno::ENOTSUP)
+    }
+
+    /// Add a port with `port_id` in `direction`.
+    fn add_port(
+        &self,
+        emitter: &NodeEmitter,
+        direction: Direction,
+        port_id: u32,
+        props: Option<&DictRef>,
+    ) -> Result<(), Errno> {
+        let _ = (emitter, direction, port_id, props);
+        Err(Errno::ENOTSUP)
+    }
+
+    /// Remove the port with `port_id` in `direction`.
+    fn remove_port(
+        &self,
+        emitter: &NodeEmitter,
+        direction: Direction,
+        port_id: u32,
+    ) -> Result<(), Errno> {
+        let _ = (emitter, direction, port_id);
+        Err(Errno::ENOTSUP)
+    }
+
+    /// Enumerate the params of a port, like [`enum_params`](`Self::enum_params`).
+    ///
+    /// `port_id` is `SPA_ID_INVALID` to enumerate the params a new port in `direction` would have.
+    #[allow(clippy::too_many_arguments)]
+    fn port_enum_params(
+        &self,
+        emitter: &NodeEmitter,
+        seq: i32,
+        direction: Direction,
+        port_id: u32,
+        id: ParamType,
+        start: u32,
+        max: u32,
+        filter: Option<&Pod>,
+    ) -> Result<(), Errno> {
+        let _ = (emitter, seq, direction, port_id, id, start, max, filter);
+        Err(Errno::ENOTSUP)
+    }
+
+    /// Set the param of type `id` of a port, or reset it if `param` is [`None`].
+    fn port_set_param(
+        &self,
+        emitter: &NodeEmitter,
+        direction: Direction,
+        port_id: u32,
+        id: ParamType,
+        flags: u32,
+        param: Option<&Pod>,
+    ) -> Result<(), Errno> {
+        let _ = (emitter, direction, port_id, id, flags, param);
+        Err(Errno::ENOTSUP)
+    }
+
+    /// Use `buffers` on a port, or stop using buffers if it is empty.
+    ///
+    /// The buffers stay valid until this is called again for the port.
+    fn port_use_buffers(
+        &self,
+        direction: Direction,
+        port_id: u32,
+        flags: u32,
+        buffers: &[*mut spa_sys::spa_buffer],
+    ) -> Result<(), Errno> {
+        let _ = (direction, port_id, flags, buffers);
+        Err(Errno::ENOTSUP)
+    }
+
+    /// Configure the io area of type `id` of a port, like [`set_io`](`Self::set_io`).
+    fn port_set_io(
+        &self,
+        direction: Direction,
+        port_id: u32,
+        id: u32,
+        data: *mut c_void,
+        size: usize,
+    ) -> Result<(), Errno> {
+        let _ = (direction, port_id, id, data, size);
+        Err(Errno::ENOTSUP)
+    }
+
+    /// Make the buffer `buffer_id` of the output port `port_id` available again.
+    ///
+    /// Called from the data thread.
+    fn port_reuse_buffer(&self, port_id: u32, buffer_id: u32) -> Result<(), Errno> {
+        let _ = (port_id, buffer_id);
+        Err(Errno::ENOTSUP)
+    }
+
+    /// Process the data of the node.
+    ///
+    /// Called from the data thread.
+    fn process(&self) -> Result<Status, Errno>;
+}
+
+/// The callbacks set by the host of the node.
+struct Callbacks {
+    funcs: *const spa_sys::spa_node_callbacks,
+    data: *mut c_void,
+}
+
+// The callbacks are set by the host, and are meant to be called from the data thread.
+unsafe impl Send for Callbacks {}
+
+#[repr(C)]
+struct NodeInner<T: SpaNode> {
+    // Must be the first member, as the node is cast to and from a `spa_node` pointer.
+    node: spa_sys::spa_node,
+    methods: spa_sys::spa_node_methods,
+    hooks: HookList,
+    callbacks: Mutex<Callbacks>,
+    implementation: T,
+}
+
+/// A `spa_node` implemented by a [`SpaNode`].
+pub struct Node<T: SpaNode> {
+    inner: Pin<Box<NodeInner<T>>>,
+}
+
+impl<T: SpaNode> Node<T> {
+    pub fn new(implementation: T) -> Self {
+        let mut inner = Box::pin(NodeInner {
+            node: unsafe { mem::zeroed() },
+            methods: spa_sys::spa_node_methods {
+                version: spa_sys::SPA_VERSION_NODE_METHODS,
+                add_listener: Some(node_add_listener::<T>),
+                set_callbacks: Some(node_set_callbacks::<T>),
+                sync: Some(node_sync::<T>),
+                enum_params: Some(node_enum_params::<T>),
+                set_param: Some(node_set_param::<T>),
+                set_io: Some(node_set_io::<T>),
+                send_command: Some(node_send_command::<T>),
+                add_port: Some(node_add_port::<T>),
+                remove_port: Some(node_remove_port::<T>),
+                port_enum_params: Some(node_port_enum_params::<T>),
+                port_set_param: Some(node_port_set_param::<T>),
+                port_use_buffers: Some(node_port_use_buffers::<T>),
+                port_set_io: Some(node_port_set_io::<T>),
+                port_reuse_buffer: Some(node_port_reuse_buffer::<T>),
+                process: Some(node_process::<T>),
+            },
+            hooks: HookList::new(),
+            callbacks: Mutex::new(Callbacks {
+                funcs: ptr::null(),
+                data: ptr::null_mut(),
+            }),
+            implementation,
+        });
+
+        unsafe {
+            let inner = inner.as_mut().get_unchecked_mut();
+            let inner_ptr: *mut NodeInner<T> = inner;
+            inner.node.iface = spa_sys::spa_interface {
+                type_: CStr::from_bytes_with_nul(spa_sys::SPA_TYPE_INTERFACE_Node)
+                    .unwrap()
+                    .as_ptr(),
+                version: spa_sys::SPA_VERSION_NODE,
+                cb: spa_sys::spa_callbacks {
+                    funcs: ptr::addr_of!(inner.methods).cast(),
+                    data: inner_ptr.cast(),
+                },
+            };
+        }
+
+        Self { inner }
+    }
+
+    pub fn as_raw_ptr(&self) -> *mut spa_sys::spa_node {
+        ptr::addr_of!(self.inner.node).cast_mut()
+    }
+
+    /// The implementation of the node.
+    pub fn implementation(&self) -> &T {
+        &self.inner.implementation
+    }
+
+    /// An emitter for all listeners of the node, for example to emit updated info.
+    ///
+    /// This must only be used from the main thread.
+    pub fn emitter(&self) -> NodeEmitter<'_> {
+        NodeEmitter {
+            listeners: Listeners::All(&self.inner.hooks),
+        }
+    }
+
+    /// Notify the host that the node is ready to be processed, with `status`.
+    ///
+    /// This is meant for nodes driving the graph, and must only be called from the data thread.
+    pub fn ready(&self, status: Status) -> Result<(), Errno> {
+        let callbacks = self.inner.callbacks.lock().unwrap();
+        let res = unsafe {
+            match callbacks.funcs.as_ref().and_then(|funcs| funcs.ready) {
+                Some(ready) => ready(callbacks.data, status.bits() as c_int),
+                None => 0,
+            }
+        };
+
+        if res < 0 {
+            Err(Errno::from_raw(-res))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<T: SpaNode + fmt::Debug> fmt::Debug for Node<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Node")
+            .field("implementation", &self.inner.implementation)
+            .finish()
+    }
+}
+
+unsafe fn inner<'a, T: SpaNode>(object: *mut c_void) -> &'a NodeInner<T> {
+    &*(object as *const NodeInner<T>)
+}
+
+unsafe fn pod_opt<'a>(pod: *const spa_sys::spa_pod) -> Option<&'a Pod> {
+    (!pod.is_null()).then(|| Pod::from_raw(pod))
+}
+
+unsafe extern "C" fn node_add_listener<T: SpaNode>(
+    object: *mut c_void,
+    listener: *mut spa_sys::spa_hook,
+    events: *const spa_sys::spa_node_events,
+    data: *mut c_void,
+) -> c_int {
+    let inner = inner::<T>(object);
+    inner.hooks.append(listener, events.cast(), data);
+
+    let emitter = NodeEmitter {
+        listeners: Listeners::One(events, data),
+    };
+    inner.implementation.add_listener(&emitter);
+
+    0
+}
+
+unsafe extern "C" fn node_set_callbacks<T: SpaNode>(
+    object: *mut c_void,
+    callbacks: *const spa_sys::spa_node_callbacks,
+    data: *mut c_void,
+) -> c_int {
+    let inner = inner::<T>(object);
+    *inner.callbacks.lock().unwrap() = Callbacks {
+        funcs: callbacks,
+        data,
+    };
+
+    0
+}
+
+unsafe extern "C" fn node_sync<T: SpaNode>(object: *mut c_void, seq: c_int) -> c_int {
+    let inner = inner::<T>(object);
+    NodeEmitter {
+        listeners: Listeners::All(&inner.hooks),
+    }
+    .result(seq, 0, 0, ptr::null());
+
+    0
+}
+
+unsafe extern "C" fn node_enum_params<T: SpaNode>(
+    object: *mut c_void,
+    seq: c_int,
+    id: u32,
+    start: u32,
+    max: u32,
+    filter: *const spa_sys::spa_pod,
+) -> c_int {
+    let inner = inner::<T>(object);
+    let emitter = NodeEmitter {
+        listeners: Listeners::All(&inner.hooks),
+    };
+
+    to_c(inner.implementation.enum_params(
+        &emitter,
+        seq,
+        ParamType::from_raw(id),
+        start,
+        max,
+        pod_opt(filter),
+    ))
+}
+
+unsafe extern "C" fn node_set_param<T: SpaNode>(
+    object: *mut c_void,
+    id: u32,
+    flags: u32,
+    param: *const spa_sys::spa_pod,
+) -> c_int {
+    let inner = inner::<T>(object);
+    let emitter = NodeEmitter {
+        listeners: Listeners::All(&inner.hooks),
+    };
+
+    to_c(
+        inner
+            .implementation
+            .set_param(&emitter, ParamType::from_raw(id), flags, pod_opt(param)),
+    )
+}
+
+unsafe extern "C" fn node_set_io<T: SpaNode>(
+    object: *mut c_void,
+    id: u32,
+    data: *mut c_void,
+    size: usize,
+) -> c_int {
+    to_c(inner::<T>(object).implementation.set_io(id, data, size))
+}
+
+unsafe extern "C" fn node_send_command<T: SpaNode>(
+    object: *mut c_void,
+    command: *const spa_sys::spa_command,
+) -> c_int {
+    let inner = inner::<T>(object);
+    let emitter = NodeEmitter {
+        listeners: Listeners::All(&inner.hooks),
+    };
+
+    match pod_opt(command.cast()) {
+        Some(command) => to_c(inner.implementation.send_command(&emitter, command)),
+        None => -(Errno::EINVAL as c_int),
+    }
+}
+
+unsafe extern "C" fn node_add_port<T: SpaNode>(
+    object: *mut c_void,
+    direction: spa_sys::spa_direction,
+    port_id: u32,
+    props: *const spa_sys::spa_dict,
+) -> c_int {
+    let inner = inner::<T>(object);
+    let emitter = NodeEmitter {
+        listeners: Listeners::All(&inner.hooks),
+    };
+    let props = props.cast::<DictRef>().as_ref();
+
+    to_c(
+        inner
+            .implementation
+            .add_port(&emitter, Direction::from_raw(direction), port_id, props),
+    )
+}
+
+unsafe extern "C" fn node_remove_port<T: SpaNode>(
+    object: *mut c_void,
+    direction: spa_sys::spa_direction,
+    port_id: u32,
+) -> c_int {
+    let inner = inner::<T>(object);
+    let emitter = NodeEmitter {
+        listeners: Listeners::All(&inner.hooks),
+    };
+
+    to_c(
+        inner
+            .implementation
+            .remove_port(&emitter, Direction::from_raw(direction), port_id),
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+unsafe extern "C" fn node_port_enum_params<T: SpaNode>(
+    object: *mut c_void,
+    seq: c_int,
+    direction: spa_sys::spa_direction,
+    port_id: u32,
+    id: u32,
+    start: u32,
+    max: u32,
+    filter: *const spa_sys::spa_pod,
+) -> c_int {
+    let inner = inner::<T>(object);
+    let emitter = NodeEmitter {
+        listeners: Listeners::All(&inner.hooks),
+    };
+
+    to_c(inner.implementation.port_enum_params(
+        &emitter,
+        seq,
+        Direction::from_raw(direction),
+        port_id,
+        ParamType::from_raw(id),
+        start,
+        max,
+        pod_opt(filter),
+    ))
+}
+
+unsafe extern "C" fn node_port_set_param<T: SpaNode>(
+    object: *mut c_void,
+    direction: spa_sys::spa_direction,
+    port_id: u32,
+    id: u32,
+    flags: u32,
+    param: *const spa_sys::spa_pod,
+) -> c_int {
+    let inner = inner::<T>(object);
+    let emitter = NodeEmitter {
+        listeners: Listeners::All(&inner.hooks),
+    };
+
+    to_c(inner.implementation.port_set_param(
+        &emitter,
+        Direction::from_raw(direction),
+        port_id,
+        ParamType::from_raw(id),
+        flags,
+        pod_opt(param),
+    ))
+}
+
+unsafe extern "C" fn node_port_use_buffers<T: SpaNode>(
+    object: *mut c_void,
+    direction: spa_sys::spa_direction,
+    port_id: u32,
+    flags: u32,
+    buffers: *mut *mut spa_sys::spa_buffer,
+    n_buffers: u32,
+) -> c_int {
+    let buffers = if buffers.is_null() || n_buffers == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(buffers.cast_const(), n_buffers as usize)
+    };
+
+    to_c(inner::<T>(object).implementation.port_use_buffers(
+        Direction::from_raw(direction),
+        port_id,
+        flags,
+        buffers,
+    ))
+}
+
+unsafe extern "C" fn node_port_set_io<T: SpaNode>(
+    object: *mut c_void,
+    direction: spa_sys::spa_direction,
+    port_id: u32,
+    id: u32,
+    data: *mut c_void,
+    size: usize,
+) -> c_int {
+    to_c(inner::<T>(object).implementation.port_set_io(
+        Direction::from_raw(direction),
+        port_id,
+        id,
+        data,
+        size,
+    ))
+}
+
+unsafe extern "C" fn node_port_reuse_buffer<T: SpaNode>(
+    object: *mut c_void,
+    port_id: u32,
+    buffer_id: u32,
+) -> c_int {
+    to_c(
+        inner::<T>(object)
+            .implementation
+            .port_reuse_buffer(port_id, buffer_id),
+    )
+}
+
+unsafe extern "C" fn node_process<T: SpaNode>(object: *mut c_void) -> c_int {
+    match inner::<T>(object).implementation.process() {
+        Ok(status) => status.bits() as c_int,
+        Err(errno) => -(errno as c_int),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    #[derive(Default)]
+    struct TestNode {
+        processed: AtomicU32,
+    }
+
+    impl SpaNode for TestNode {
+        fn add_listener(&self, emitter: &NodeEmitter) {
+            emitter.info(&NodeInfo {
+                max_output_ports: 1,
+                change_mask: spa_sys::SPA_NODE_CHANGE_MASK_FLAGS as u64,
+                ..Default::default()
+            });
+            emitter.port_info(Direction::Output, 0, Some(&PortInfo::default()));
+        }
+
+        fn process(&self) -> Result<Status, Errno> {
+            self.processed.fetch_add(1, Ordering::Relaxed);
+            Ok(Status::HAVE_DATA)
+        }
+    }
+
+    #[derive(Default)]
+    struct Received {
+        max_output_ports: u32,
+        ports: u32,
+        results: Vec<i32>,
+    }
+
+    unsafe extern "C" fn on_info(data: *mut c_void, info: *const spa_sys::spa_node_info) {
+        let received = &mut *(data as *mut Received);
+        received.max_output_ports = (*info).max_output_ports;
+    }
+
+    unsafe extern "C" fn on_port_info(
+        data: *mut c_void,
+        _direction: spa_sys::spa_direction,
+        _port: u32,
+        _info: *const spa_sys::spa_port_info,
+    ) {
+        let received = &mut *(data as *mut Received);
+        received.ports += 1;
+    }
+
+    unsafe extern "C" fn on_result(
+        data: *mut c_void,
+        seq: c_int,
+        _res: c_int,
+        _type: u32,
+        _result: *const c_void,
+    ) {
+        let received = &mut *(data as *mut Received);
+        received.results.push(seq);
+    }
+
+    #[test]
+    fn methods() {
+        let node = Node::new(TestNode::default());
+        let events = spa_sys::spa_node_events {
+            version: spa_sys::SPA_VERSION_NODE_EVENTS,
+            info: Some(on_info),
+            port_info: Some(on_port_info),
+            result: Some(on_result),
+            event: None,
+        };
+        let mut received = Received::default();
+        let mut hook: spa_sys::spa_hook = unsafe { mem::zeroed() };
+
+        unsafe {
+            let res = crate::spa_interface_call_method!(
+                node.as_raw_ptr(),
+                spa_sys::spa_node_methods,
+                add_listener,
+                &mut hook,
+                &events,
+                ptr::addr_of_mut!(received).cast()
+            );
+            assert_eq!(res, 0);
+        }
+        assert_eq!(received.max_output_ports, 1);
+        assert_eq!(received.ports, 1);
+
+        unsafe {
+            let res = crate::spa_interface_call_method!(
+                node.as_raw_ptr(),
+                spa_sys::spa_node_methods,
+                sync,
+                42
+            );
+            assert_eq!(res, 0);
+
+            let res = crate::spa_interface_call_method!(
+                node.as_raw_ptr(),
+                spa_sys::spa_node_methods,
+                set_io,
+                0,
+                ptr::null_mut(),
+                0
+            );
+            assert_eq!(res, -(Errno::ENOTSUP as c_int));
+
+            let res = crate::spa_interface_call_method!(
+                node.as_raw_ptr(),
+                spa_sys::spa_node_methods,
+                process,
+            );
+            assert_eq!(res, spa_sys::SPA_STATUS_HAVE_DATA as c_int);
+        }
+        assert_eq!(received.results, [42]);
+        assert_eq!(node.implementation().processed.load(Ordering::Relaxed), 1);
+
+        crate::utils::hook::remove(hook);
+        assert!(node.inner.hooks.is_empty());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn command() {
+        let bytes = NodeCommand::RequestProcess.to_bytes();
+        let pod = Pod::from_bytes(&bytes).unwrap();
+        assert!(pod.is_object());
+        assert_eq!(
+            NodeCommand::from_pod(pod),
+            Some(NodeCommand::RequestProcess)
+        );
+        assert_eq!(
+            format!("{:?}", NodeCommand::Suspend),
+            "NodeCommand::Suspend"
+        );
+
+        let mut bytes = Vec::new();
+        let mut builder = Builder::new(&mut bytes);
+        builder.add_int(3).unwrap();
+        drop(builder);
+        let pod = Pod::from_bytes(&bytes).unwrap();
+        assert_eq!(NodeCommand::from_pod(pod), None);
+    }
+}
@@ -0,0 +1,646 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Typed [`Route`] and [`Profile`] params, as enumerated and set on devices.
+//!
+//! The structs convert from the [`Object`] of a deserialized `Route`, `EnumRoute`,
+//! `Profile` or `EnumProfile` param, and to the [`Object`] to pass when setting
+//! the active route or profile of a device.
+
+use crate::{
+    param::ParamType,
+    pod::{Object, Property, PropertyFlags, Value, ValueArray},
+    utils::{
+        result::{Errno, Error},
+        Direction, Id, SpaTypes,
+    },
+};
+
+/// The availability of a [`Route`] or [`Profile`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ParamAvailability(pub spa_sys::spa_param_availability);
+
+#[allow(non_upper_case_globals)]
+impl ParamAvailability {
+    pub const Unknown: Self = Self(spa_sys::SPA_PARAM_AVAILABILITY_unknown);
+    pub const No: Self = Self(spa_sys::SPA_PARAM_AVAILABILITY_no);
+    pub const Yes: Self = Self(spa_sys::SPA_PARAM_AVAILABILITY_yes);
+
+    /// Obtain a [`ParamAvailability`] from a raw `spa_param_availability` variant.
+    pub fn from_raw(raw: spa_sys::spa_param_availability) -> Self {
+        Self(raw)
+    }
+
+    /// Get the raw [`spa_sys::spa_param_availability`] representing this `ParamAvailability`.
+    pub fn as_raw(&self) -> spa_sys::spa_param_availability {
+        self.0
+    }
+}
+
+impl Default for ParamAvailability {
+    fn default() -> Self {
+        Self::Unknown
+    }
+}
+
+/// The props of a [`Route`], holding the volumes of the device.
+///
+/// Nodes hold their volumes in a `Props` param of the same layout.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RouteProps {
+    /// Whether the route is muted.
+    pub mute: Option<bool>,
+    /// The overall volume, as a linear factor.
+    pub volume: Option<f32>,
+    /// The volume of each channel, as linear factors.
+    pub channel_volumes: Option<Vec<f32>>,
+    /// The channel positions of [`channel_volumes`](Self::channel_volumes), as `spa_audio_channel` ids.
+    pub channel_map: Option<Vec<u32>>,
+    /// The other properties, kept as is.
+    pub other: Vec<Property>,
+}
+
+impl RouteProps {
+    /// Parse the props from a `Props` object.
+    pub fn from_object(object: &Object) -> Result<Self, Error> {
+        let mut props = Self::default();
+        for property in &object.properties {
+            match (property.key, &property.value) {
+                (spa_sys::SPA_PROP_mute, Value::Bool(mute)) => props.mute = Some(*mute),
+                (spa_sys::SPA_PROP_volume, Value::Float(volume)) => props.volume = Some(*volume),
+                (
+                    spa_sys::SPA_PROP_channelVolumes,
+                    Value::ValueArray(ValueArray::Float(volumes)),
+                ) => props.channel_volumes = Some(volumes.clone()),
+                (spa_sys::SPA_PROP_channelMap, Value::ValueArray(ValueArray::Id(map))) => {
+                    props.channel_map = Some(map.iter().map(|id| id.0).collect())
+                }
+                (spa_sys::SPA_PROP_mute, _)
+                | (spa_sys::SPA_PROP_volume, _)
+                | (spa_sys::SPA_PROP_channelVolumes, _)
+                | (spa_sys::SPA_PROP_channelMap, _) => return Err(invalid()),
+                _ => props.other.push(property.clone()),
+            }
+        }
+
+        Ok(props)
+    }
+
+    /// Build a `Props` object with the id `id`.
+    pub fn to_object(&self, id: ParamType) -> Object {
+        let mut properties = Vec::new();
+        if let Some(mute) = self.mute {
+            properties.push(Property::new(spa_sys::SPA_PROP_mute, Value::Bool(mute)));
+        }
+        if let Some(volume) = self.volume {
+            properties.push(Property::new(
+                spa_sys::SPA_PROP_volume,
+                Value::Float(volume),
+            ));
+        }
+        if let Some(volumes) = &self.channel_volumes {
+            properties.push(Property::new(
+                spa_sys::SPA_PROP_channelVolumes,
+                Value::ValueArray(ValueArray::Float(volumes.clone())),
+            ));
+        }
+        if let Some(map) = &self.channel_map {
+            properties.push(Property::new(
+                spa_sys::SPA_PROP_channelMap,
+                Value::ValueArray(ValueArray::Id(map.iter().copied().map(Id).collect())),
+            ));
+        }
+        properties.extend(self.other.iter().cloned());
+
+        Object {
+            type_: SpaTypes::ObjectParamProps.as_raw(),
+            id: id.as_raw(),
+            properties,
+        }
+    }
+}
+
+/// A route of a device, such as the speakers or the headphones jack of a sound card.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    /// The index of the route, unique for its direction.
+    pub index: u32,
+    /// The direction of the route.
+    pub direction: Direction,
+    /// The device of the active profile the route is active on, only set for active routes.
+    pub device: Option<u32>,
+    pub name: String,
+    pub description: String,
+    pub priority: u32,
+    pub available: ParamAvailability,
+    /// Extra information, as key/value pairs.
+    pub info: Vec<(String, String)>,
+    /// The profiles the route is available in.
+    pub profiles: Vec<u32>,
+    /// The props of the route, only set for active routes.
+    pub props: Option<RouteProps>,
+    /// The devices of the profile the route is available on.
+    pub devices: Vec<u32>,
+    /// The profile the route is active in, only set for active routes.
+    pub profile: Option<u32>,
+    /// Whether the session manager should save the route when set.
+    pub save: bool,
+}
+
+impl Route {
+    /// Create a route with the mandatory fields, as needed to select
+    /// the route `index` on the `device` of the active profile.
+    pub fn new(index: u32, direction: Direction, device: u32) -> Self {
+        Self {
+            index,
+            direction,
+            device: Some(device),
+            name: String::new(),
+            description: String::new(),
+            priority: 0,
+            available: ParamAvailability::Unknown,
+            info: Vec::new(),
+            profiles: Vec::new(),
+            props: None,
+            devices: Vec::new(),
+            profile: None,
+            save: false,
+        }
+    }
+}
+
+impl TryFrom<&Object> for Route {
+    type Error = Error;
+
+    fn try_from(object: &Object) -> Result<Self, Self::Error> {
+        if object.type_ != SpaTypes::ObjectParamRoute.as_raw() {
+            return Err(invalid());
+        }
+
+        let mut index = None;
+        let mut direction = None;
+        let mut route = Self::new(0, Direction::Input, 0);
+        route.device = None;
+
+        for property in &object.properties {
+            let value = &property.value;
+            match property.key {
+                spa_sys::SPA_PARAM_ROUTE_index => index = Some(int(value)?),
+                spa_sys::SPA_PARAM_ROUTE_direction => {
+                    direction = Some(Direction::from_raw(id(value)?))
+                }
+                spa_sys::SPA_PARAM_ROUTE_device => route.device = Some(int(value)?),
+                spa_sys::SPA_PARAM_ROUTE_name => route.name = string(value)?,
+                spa_sys::SPA_PARAM_ROUTE_description => route.description = string(value)?,
+                spa_sys::SPA_PARAM_ROUTE_priority => route.priority = int(value)?,
+                spa_sys::SPA_PARAM_ROUTE_available => {
+                    route.available = ParamAvailability::from_raw(id(value)?)
+                }
+                spa_sys::SPA_PARAM_ROUTE_info => route.info = info(value)?,
+                spa_sys::SPA_PARAM_ROUTE_profiles => route.profiles = int_array(value)?,
+                spa_sys::SPA_PARAM_ROUTE_props => match value {
+                    Value::Object(props) => route.props = Some(RouteProps::from_object(props)?),
+                    _ => return Err(invalid()),
+                },
+                spa_sys::SPA_PARAM_ROUTE_devices => route.devices = int_array(value)?,
+                spa_sys::SPA_PARAM_ROUTE_profile => route.profile = Some(int(value)?),
+                spa_sys::SPA_PARAM_ROUTE_save => route.save = bool(value)?,
+                _ => (),
+            }
+        }
+
+        route.index = index.ok_or_else(invalid)?;
+        route.direction = direction.ok_or_else(invalid)?;
+        Ok(route)
+    }
+}
+
+impl From<&Route> for Object {
+    fn from(route: &Route) -> Self {
+        let mut properties = vec![
+            Property::new(
+                spa_sys::SPA_PARAM_ROUTE_index,
+                Value::Int(route.index as i32),
+            ),
+            Property::new(
+                spa_sys::SPA_PARAM_ROUTE_direction,
+                Value::Id(Id(route.direction.as_raw())),
+            ),
+        ];
+        if let Some(device) = route.device {
+            properties.push(Property::new(
+                spa_sys::SPA_PARAM_ROUTE_device,
+                Value::Int(device as i32),
+            ));
+        }
+        properties.extend([
+            Property::new(
+                spa_sys::SPA_PARAM_ROUTE_name,
+                Value::String(route.name.clone()),
+            ),
+            Property::new(
+                spa_sys::SPA_PARAM_ROUTE_description,
+                Value::String(route.description.clone()),
+            ),
+            Property::new(
+                spa_sys::SPA_PARAM_ROUTE_priority,
+                Value::Int(route.priority as i32),
+            ),
+            Property::new(
+                spa_sys::SPA_PARAM_ROUTE_available,
+                Value::Id(Id(route.available.as_raw())),
+            ),
+        ]);
+        if !route.info.is_empty() {
+            properties.push(Property {
+                key: spa_sys::SPA_PARAM_ROUTE_info,
+                flags: PropertyFlags::HINT_DICT,
+                value: info_value(&route.info),
+            });
+        }
+        if !route.profiles.is_empty() {
+            properties.push(Property::new(
+                spa_sys::SPA_PARAM_ROUTE_profiles,
+                int_array_value(&route.profiles),
+            ));
+        }
+        if let Some(props) = &route.props {
+            properties.push(Property::new(
+                spa_sys::SPA_PARAM_ROUTE_props,
+                Value::Object(props.to_object(ParamType::Route)),
+            ));
+        }
+        if !route.devices.is_empty() {
+            properties.push(Property::new(
+                spa_sys::SPA_PARAM_ROUTE_devices,
+                int_array_value(&route.devices),
+            ));
+        }
+        if let Some(profile) = route.profile {
+            properties.push(Property::new(
+                spa_sys::SPA_PARAM_ROUTE_profile,
+                Value::Int(profile as i32),
+            ));
+        }
+        properties.push(Property::new(
+            spa_sys::SPA_PARAM_ROUTE_save,
+            Value::Bool(route.save),
+        ));
+
+        Object {
+            type_: SpaTypes::ObjectParamRoute.as_raw(),
+            id: ParamType::Route.as_raw(),
+            properties,
+        }
+    }
+}
+
+/// The nodes of a media class created by a [`Profile`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProfileClass {
+    /// The media class of the nodes, e.g. `Audio/Sink`.
+    pub media_class: String,
+    /// The number of nodes of the class.
+    pub count: u32,
+    /// The devices of the nodes.
+    pub devices: Vec<u32>,
+}
+
+const PROFILE_DEVICES_KEY: &str = "card.profile.devices";
+
+/// A profile of a device, such as the duplex or the output only configuration of a sound card.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Profile {
+    pub index: u32,
+    pub name: String,
+    pub description: String,
+    pub priority: u32,
+    pub available: ParamAvailability,
+    /// Extra information, as key/value pairs.
+    pub info: Vec<(String, String)>,
+    /// The nodes created by the profile.
+    pub classes: Vec<ProfileClass>,
+    /// Whether the session manager should save the profile when set.
+    pub save: bool,
+}
+
+impl Profile {
+    /// Create a profile with the mandatory fields, as needed to select the profile `index`.
+    pub fn new(index: u32) -> Self {
+        Self {
+            index,
+            ..Default::default()
+        }
+    }
+}
+
+impl TryFrom<&Object> for Profile {
+    type Error = Error;
+
+    fn try_from(object: &Object) -> Result<Self, Self::Error> {
+        if object.type_ != SpaTypes::ObjectParamProfile.as_raw() {
+            return Err(invalid());
+        }
+
+        let mut index = None;
+        let mut profile = Self::default();
+
+        for property in &object.properties {
+            let value = &property.value;
+            match property.key {
+                spa_sys::SPA_PARAM_PROFILE_index => index = Some(int(value)?),
+                spa_sys::SPA_PARAM_PROFILE_name => profile.name = string(value)?,
+                spa_sys::SPA_PARAM_PROFILE_description => profile.description = string(value)?,
+                spa_sys::SPA_PARAM_PROFILE_priority => profile.priority = int(value)?,
+                spa_sys::SPA_PARAM_PROFILE_available => {
+                    profile.available = ParamAvailability::from_raw(id(value)?)
+                }
+                spa_sys::SPA_PARAM_PROFILE_info => profile.info = info(value)?,
+                spa_sys::SPA_PARAM_PROFILE_classes => profile.classes = classes(value)?,
+                spa_sys::SPA_PARAM_PROFILE_save => profile.save = bool(value)?,
+                _ => (),
+            }
+        }
+
+        profile.index = index.ok_or_else(invalid)?;
+        Ok(profile)
+    }
+}
+
+impl From<&Profile> for Object {
+    fn from(profile: &Profile) -> Self {
+        let mut properties = vec![Property::new(
+            spa_sys::SPA_PARAM_PROFILE_index,
+            Value::Int(profile.index as i32),
+        )];
+        if !profile.name.is_empty() {
+            properties.push(Property::new(
+                spa_sys::SPA_PARAM_PROFILE_name,
+                Value::String(profile.name.clone()),
+            ));
+        }
+        if !profile.description.is_empty() {
+            properties.push(Property::new(
+                spa_sys::SPA_PARAM_PROFILE_description,
+                Value::String(profile.description.clone()),
+            ));
+        }
+        properties.extend([
+            Property::new(
+                spa_sys::SPA_PARAM_PROFILE_priority,
+                Value::Int(profile.priority as i32),
+            ),
+            Property::new(
+                spa_sys::SPA_PARAM_PROFILE_available,
+                Value::Id(Id(profile.available.as_raw())),
+            ),
+        ]);
+        if !profile.info.is_empty() {
+            properties.push(Property {
+                key: spa_sys::SPA_PARAM_PROFILE_info,
+                flags: PropertyFlags::HINT_DICT,
+                value: info_value(&profile.info),
+            });
+        }
+        if !profile.classes.is_empty() {
+            properties.push(Property {
+                key: spa_sys::SPA_PARAM_PROFILE_classes,
+                flags: PropertyFlags::HINT_DICT,
+                value: classes_value(&profile.classes),
+            });
+        }
+        properties.push(Property::new(
+            spa_sys::SPA_PARAM_PROFILE_save,
+            Value::Bool(profile.save),
+        ));
+
+        Object {
+            type_: SpaTypes::ObjectParamProfile.as_raw(),
+            id: ParamType::Profile.as_raw(),
+            properties,
+        }
+    }
+}
+
+fn invalid() -> Error {
+    Error::new(Errno::EINVAL as i32)
+}
+
+fn int(value: &Value) -> Result<u32, Error> {
+    match value {
+        Value::Int(value) => Ok(*value as u32),
+        _ => Err(invalid()),
+    }
+}
+
+fn id(value: &Value) -> Result<u32, Error> {
+    match value {
+        Value::Id(id) => Ok(id.0),
+        _ => Err(invalid()),
+    }
+}
+
+fn bool(value: &Value) -> Result<bool, Error> {
+    match value {
+        Value::Bool(value) => Ok(*value),
+        _ => Err(invalid()),
+    }
+}
+
+fn string(value: &Value) -> Result<String, Error> {
+    match value {
+        Value::String(value) => Ok(value.clone()),
+        _ => Err(invalid()),
+    }
+}
+
+fn int_array(value: &Value) -> Result<Vec<u32>, Error> {
+    match value {
+        Value::ValueArray(ValueArray::Int(values)) => {
+            Ok(values.iter().map(|v| *v as u32).collect())
+        }
+        _ => Err(invalid()),
+    }
+}
+
+fn int_array_value(values: &[u32]) -> Value {
+    Value::ValueArray(ValueArray::Int(values.iter().map(|v| *v as i32).collect()))
+}
+
+/// Parse a dict, serialized as a struct of the number of items followed by the keys and values.
+fn info(value: &Value) -> Result<Vec<(String, String)>, Error> {
+    let Value::Struct(fields) = value else {
+        return Err(invalid());
+    };
+    let Some((n_items, items)) = fields.split_first() else {
+        return Err(invalid());
+    };
+    let n_items = int(n_items)? as usize;
+    if items.len() != n_items * 2 {
+        return Err(invalid());
+    }
+
+    items
+        .chunks_exact(2)
+        .map(|item| Ok((string(&item[0])?, string(&item[1])?)))
+        .collect()
+}
+
+fn info_value(info: &[(String, String)]) -> Value {
+    let mut fields = Vec::with_capacity(1 + info.len() * 2);
+    fields.push(Value::Int(info.len() as i32));
+    for (key, value) in info {
+        fields.push(Value::String(key.clone()));
+        fields.push(Value::String(value.clone()));
+    }
+
+    Value::Struct(fields)
+}
+
+/// Parse the profile classes, serialized as a struct of the number of classes followed by
+/// a struct per class, holding the media class, the number of nodes and optional key/values.
+fn classes(value: &Value) -> Result<Vec<ProfileClass>, Error> {
+    let Value::Struct(fields) = value else {
+        return Err(invalid());
+    };
+    let Some((n_classes, classes)) = fields.split_first() else {
+        return Err(invalid());
+    };
+    if int(n_classes)? as usize != classes.len() {
+        return Err(invalid());
+    }
+
+    classes
+        .iter()
+        .map(|class| {
+            let Value::Struct(fields) = class else {
+                return Err(invalid());
+            };
+            let [media_class, count, extra @ ..] = fields.as_slice() else {
+                return Err(invalid());
+            };
+
+            let mut class = ProfileClass {
+                media_class: string(media_class)?,
+                count: int(count)?,
+                devices: Vec::new(),
+            };
+            for item in extra.chunks_exact(2) {
+                if string(&item[0])? == PROFILE_DEVICES_KEY {
+                    class.devices = int_array(&item[1])?;
+                }
+            }
+
+            Ok(class)
+        })
+        .collect()
+}
+
+fn classes_value(classes: &[ProfileClass]) -> Value {
+    let mut fields = Vec::with_capacity(1 + classes.len());
+    fields.push(Value::Int(classes.len() as i32));
+    for class in classes {
+        let mut class_fields = vec![
+            Value::String(class.media_class.clone()),
+            Value::Int(class.count as i32),
+        ];
+        if !class.devices.is_empty() {
+            class_fields.push(Value::String(PROFILE_DEVICES_KEY.to_owned()));
+            class_fields.push(int_array_value(&class.devices));
+        }
+        fields.push(Value::Struct(class_fields));
+    }
+
+    Value::Struct(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pod::{deserialize::PodDeserializer, serialize::PodSerializer};
+    use std::io::Cursor;
+
+    #[test]
+    fn route_object() {
+        let route = Route {
+            name: "analog-output-headphones".to_owned(),
+            description: "Headphones".to_owned(),
+            priority: 9900,
+            available: ParamAvailability::Yes,
+            info: vec![("port.type".to_owned(), "headphones".to_owned())],
+            profiles: vec![1, 3],
+            props: Some(RouteProps {
+                mute: Some(false),
+                volume: Some(1.0),
+                channel_volumes: Some(vec![0.5, 0.25]),
+                channel_map: Some(vec![
+                    spa_sys::SPA_AUDIO_CHANNEL_FL,
+                    spa_sys::SPA_AUDIO_CHANNEL_FR,
+                ]),
+                other: vec![Property::new(
+                    spa_sys::SPA_PROP_latencyOffsetNsec,
+                    Value::Long(0),
+                )],
+            }),
+            devices: vec![1],
+            profile: Some(1),
+            save: true,
+            ..Route::new(2, Direction::Output, 1)
+        };
+
+        let object = Object::from(&route);
+        assert_eq!(object.type_, SpaTypes::ObjectParamRoute.as_raw());
+        assert_eq!(object.id, ParamType::Route.as_raw());
+        assert_eq!(Route::try_from(&object).as_ref(), Ok(&route));
+
+        let bytes = PodSerializer::serialize(Cursor::new(Vec::new()), &Value::Object(object))
+            .unwrap()
+            .0
+            .into_inner();
+        let (_, value) = PodDeserializer::deserialize_any_from(&bytes).unwrap();
+        let Value::Object(object) = value else {
+            panic!("Expected an object, got {:?}", value);
+        };
+        assert_eq!(Route::try_from(&object), Ok(route));
+    }
+
+    #[test]
+    fn route_missing_index() {
+        let object = Object {
+            type_: SpaTypes::ObjectParamRoute.as_raw(),
+            id: ParamType::EnumRoute.as_raw(),
+            properties: vec![Property::new(
+                spa_sys::SPA_PARAM_ROUTE_direction,
+                Value::Id(Id(Direction::Input.as_raw())),
+            )],
+        };
+        assert!(Route::try_from(&object).is_err());
+    }
+
+    #[test]
+    fn profile_object() {
+        let profile = Profile {
+            name: "output:analog-stereo+input:analog-stereo".to_owned(),
+            description: "Analog Stereo Duplex".to_owned(),
+            priority: 6565,
+            available: ParamAvailability::Yes,
+            classes: vec![
+                ProfileClass {
+                    media_class: "Audio/Sink".to_owned(),
+                    count: 1,
+                    devices: vec![1],
+                },
+                ProfileClass {
+                    media_class: "Audio/Source".to_owned(),
+                    count: 1,
+                    devices: vec![],
+                },
+            ],
+            ..Profile::new(3)
+        };
+
+        let object = Object::from(&profile);
+        assert_eq!(object.type_, SpaTypes::ObjectParamProfile.as_raw());
+        assert_eq!(Profile::try_from(&object), Ok(profile));
+    }
+}
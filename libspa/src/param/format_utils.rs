@@ -4,9 +4,16 @@
 use std::mem::MaybeUninit;
 
 use crate::{
-    param::format::{MediaSubtype, MediaType},
-    pod::Pod,
-    utils::result::{Error, SpaResult},
+    param::{
+        audio::AudioFormat,
+        format::{MediaSubtype, MediaType},
+        video::VideoFormat,
+    },
+    pod::{deserialize::PodDeserializer, ChoiceValue, Object, Pod, Value},
+    utils::{
+        result::{Errno, Error, SpaResult},
+        Choice, ChoiceEnum, Fraction, Id, Rectangle, SpaTypes,
+    },
 };
 
 /// helper function to parse format properties type
@@ -32,3 +39,295 @@ pub fn parse_format(format: &Pod) -> Result<(MediaType, MediaSubtype), Error> {
         }),
     }
 }
+
+/// The capabilities described by an `EnumFormat` param.
+///
+/// Each field holds the values the format accepts for a format property,
+/// or [`None`]/empty if the param does not constrain it.
+/// Ranges and enumerations are kept as the [`ChoiceEnum`] found in the param,
+/// with fixed values being [`ChoiceEnum::None`].
+///
+/// Encoded formats, such as `MediaSubtype::Mp3` or `MediaSubtype::H264`,
+/// only differ from raw formats by their [`media_subtype`](Self::media_subtype),
+/// and have no sample formats.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatCaps {
+    pub media_type: MediaType,
+    pub media_subtype: MediaSubtype,
+    /// The sample formats of raw audio.
+    pub audio_formats: Vec<AudioFormat>,
+    /// The audio sample rates.
+    pub rate: Option<ChoiceEnum<i32>>,
+    /// The number of audio channels.
+    pub channels: Option<ChoiceEnum<i32>>,
+    /// The pixel formats of raw video.
+    pub video_formats: Vec<VideoFormat>,
+    /// The video frame sizes.
+    pub size: Option<ChoiceEnum<Rectangle>>,
+    /// The video frame rates.
+    pub framerate: Option<ChoiceEnum<Fraction>>,
+}
+
+impl FormatCaps {
+    /// Parse the capabilities of an `EnumFormat` or `Format` param.
+    pub fn from_pod(pod: &Pod) -> Result<Self, Error> {
+        match PodDeserializer::deserialize_any_from(pod.as_bytes()) {
+            Ok((_, Value::Object(object))) => Self::try_from(&object),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Whether the format is raw audio or video, as opposed to an encoded format.
+    pub fn is_raw(&self) -> bool {
+        self.media_subtype == MediaSubtype::Raw
+    }
+}
+
+impl TryFrom<&Object> for FormatCaps {
+    type Error = Error;
+
+    fn try_from(object: &Object) -> Result<Self, Self::Error> {
+        if object.type_ != SpaTypes::ObjectParamFormat.as_raw() {
+            return Err(invalid());
+        }
+
+        let mut media_type = None;
+        let mut media_subtype = None;
+        let mut caps = Self {
+            media_type: MediaType::Unknown,
+            media_subtype: MediaSubtype::Unknown,
+            audio_formats: Vec::new(),
+            rate: None,
+            channels: None,
+            video_formats: Vec::new(),
+            size: None,
+            framerate: None,
+        };
+
+        for property in &object.properties {
+            let value = &property.value;
+            match property.key {
+                spa_sys::SPA_FORMAT_mediaType => {
+                    media_type = ids(value)?.first().copied().map(MediaType::from_raw)
+                }
+                spa_sys::SPA_FORMAT_mediaSubtype => {
+                    media_subtype = ids(value)?.first().copied().map(MediaSubtype::from_raw)
+                }
+                spa_sys::SPA_FORMAT_AUDIO_format => {
+                    caps.audio_formats =
+                        ids(value)?.into_iter().map(AudioFormat::from_raw).collect()
+                }
+                spa_sys::SPA_FORMAT_AUDIO_rate => caps.rate = Some(int_choice(value)?),
+                spa_sys::SPA_FORMAT_AUDIO_channels => caps.channels = Some(int_choice(value)?),
+                spa_sys::SPA_FORMAT_VIDEO_format => {
+                    caps.video_formats =
+                        ids(value)?.into_iter().map(VideoFormat::from_raw).collect()
+                }
+                spa_sys::SPA_FORMAT_VIDEO_size => caps.size = Some(rectangle_choice(value)?),
+                spa_sys::SPA_FORMAT_VIDEO_framerate => {
+                    caps.framerate = Some(fraction_choice(value)?)
+                }
+                _ => (),
+            }
+        }
+
+        caps.media_type = media_type.ok_or_else(invalid)?;
+        caps.media_subtype = media_subtype.ok_or_else(invalid)?;
+        Ok(caps)
+    }
+}
+
+fn invalid() -> Error {
+    Error::new(Errno::EINVAL as i32)
+}
+
+/// The ids an id property accepts, the default first.
+fn ids(value: &Value) -> Result<Vec<u32>, Error> {
+    let choice = match value {
+        Value::Id(id) => return Ok(vec![id.0]),
+        Value::Choice(ChoiceValue::Id(Choice(_, choice))) => choice,
+        _ => return Err(invalid()),
+    };
+
+    let (default, others): (&Id, &[Id]) = match choice {
+        ChoiceEnum::None(default)
+        | ChoiceEnum::Range { default, .. }
+        | ChoiceEnum::Step { default, .. } => (default, &[]),
+        ChoiceEnum::Enum {
+            default,
+            alternatives,
+        } => (default, alternatives),
+        ChoiceEnum::Flags { default, flags } => (default, flags),
+    };
+
+    let mut ids = vec![default.0];
+    for id in others {
+        if !ids.contains(&id.0) {
+            ids.push(id.0);
+        }
+    }
+
+    Ok(ids)
+}
+
+fn int_choice(value: &Value) -> Result<ChoiceEnum<i32>, Error> {
+    match value {
+        Value::Int(value) => Ok(ChoiceEnum::None(*value)),
+        Value::Choice(ChoiceValue::Int(Choice(_, choice))) => Ok(choice.clone()),
+        _ => Err(invalid()),
+    }
+}
+
+fn rectangle_choice(value: &Value) -> Result<ChoiceEnum<Rectangle>, Error> {
+    match value {
+        Value::Rectangle(value) => Ok(ChoiceEnum::None(*value)),
+        Value::Choice(ChoiceValue::Rectangle(Choice(_, choice))) => Ok(choice.clone()),
+        _ => Err(invalid()),
+    }
+}
+
+fn fraction_choice(value: &Value) -> Result<ChoiceEnum<Fraction>, Error> {
+    match value {
+        Value::Fraction(value) => Ok(ChoiceEnum::None(*value)),
+        Value::Choice(ChoiceValue::Fraction(Choice(_, choice))) => Ok(choice.clone()),
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        param::ParamType,
+        pod::{serialize::PodSerializer, Property},
+        utils::ChoiceFlags,
+    };
+
+    fn serialize(object: Object) -> Vec<u8> {
+        PodSerializer::serialize(std::io::Cursor::new(Vec::new()), &Value::Object(object))
+            .unwrap()
+            .0
+            .into_inner()
+    }
+
+    fn enum_format(properties: Vec<Property>) -> Object {
+        Object {
+            type_: SpaTypes::ObjectParamFormat.as_raw(),
+            id: ParamType::EnumFormat.as_raw(),
+            properties,
+        }
+    }
+
+    #[test]
+    fn audio_caps() {
+        let bytes = serialize(enum_format(vec![
+            Property::new(
+                spa_sys::SPA_FORMAT_mediaType,
+                Value::Id(Id(spa_sys::SPA_MEDIA_TYPE_audio)),
+            ),
+            Property::new(
+                spa_sys::SPA_FORMAT_mediaSubtype,
+                Value::Id(Id(spa_sys::SPA_MEDIA_SUBTYPE_raw)),
+            ),
+            Property::new(
+                spa_sys::SPA_FORMAT_AUDIO_format,
+                Value::Choice(ChoiceValue::Id(Choice(
+                    ChoiceFlags::empty(),
+                    ChoiceEnum::Enum {
+                        default: Id(AudioFormat::F32LE.as_raw()),
+                        alternatives: vec![
+                            Id(AudioFormat::F32LE.as_raw()),
+                            Id(AudioFormat::S16LE.as_raw()),
+                        ],
+                    },
+                ))),
+            ),
+            Property::new(
+                spa_sys::SPA_FORMAT_AUDIO_rate,
+                Value::Choice(ChoiceValue::Int(Choice(
+                    ChoiceFlags::empty(),
+                    ChoiceEnum::Range {
+                        default: 48000,
+                        min: 1,
+                        max: 384000,
+                    },
+                ))),
+            ),
+            Property::new(spa_sys::SPA_FORMAT_AUDIO_channels, Value::Int(2)),
+        ]));
+
+        let caps = FormatCaps::from_pod(Pod::from_bytes(&bytes).unwrap()).unwrap();
+        assert_eq!(caps.media_type, MediaType::Audio);
+        assert!(caps.is_raw());
+        assert_eq!(
+            caps.audio_formats,
+            vec![AudioFormat::F32LE, AudioFormat::S16LE]
+        );
+        assert_eq!(
+            caps.rate,
+            Some(ChoiceEnum::Range {
+                default: 48000,
+                min: 1,
+                max: 384000
+            })
+        );
+        assert_eq!(caps.channels, Some(ChoiceEnum::None(2)));
+        assert!(caps.video_formats.is_empty());
+        assert_eq!(caps.size, None);
+    }
+
+    #[test]
+    fn encoded_video_caps() {
+        let bytes = serialize(enum_format(vec![
+            Property::new(
+                spa_sys::SPA_FORMAT_mediaType,
+                Value::Id(Id(spa_sys::SPA_MEDIA_TYPE_video)),
+            ),
+            Property::new(
+                spa_sys::SPA_FORMAT_mediaSubtype,
+                Value::Id(Id(spa_sys::SPA_MEDIA_SUBTYPE_mjpg)),
+            ),
+            Property::new(
+                spa_sys::SPA_FORMAT_VIDEO_size,
+                Value::Rectangle(Rectangle {
+                    width: 1280,
+                    height: 720,
+                }),
+            ),
+            Property::new(
+                spa_sys::SPA_FORMAT_VIDEO_framerate,
+                Value::Choice(ChoiceValue::Fraction(Choice(
+                    ChoiceFlags::empty(),
+                    ChoiceEnum::Enum {
+                        default: Fraction { num: 30, denom: 1 },
+                        alternatives: vec![
+                            Fraction { num: 30, denom: 1 },
+                            Fraction { num: 15, denom: 1 },
+                        ],
+                    },
+                ))),
+            ),
+        ]));
+
+        let caps = FormatCaps::from_pod(Pod::from_bytes(&bytes).unwrap()).unwrap();
+        assert_eq!(caps.media_type, MediaType::Video);
+        assert_eq!(caps.media_subtype, MediaSubtype::Mjpg);
+        assert!(!caps.is_raw());
+        assert_eq!(
+            caps.size,
+            Some(ChoiceEnum::None(Rectangle {
+                width: 1280,
+                height: 720
+            }))
+        );
+        assert!(matches!(
+            caps.framerate,
+            Some(ChoiceEnum::Enum { ref alternatives, .. }) if alternatives.len() == 2
+        ));
+    }
+
+    #[test]
+    fn missing_media_type() {
+        assert!(FormatCaps::try_from(&enum_format(vec![])).is_err());
+    }
+}
@@ -2,13 +2,14 @@
 // SPDX-License-Identifier: MIT
 
 pub mod audio;
+pub mod device;
 pub mod format;
 pub mod format_utils;
 pub mod video;
 
+use pipewire_sys::pw_buffer;
 use std::ffi::CStr;
 use std::fmt::Debug;
-use pipewire_sys::pw_buffer;
 /// A wrapper around spa_param_type
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub struct ParamType(pub spa_sys::spa_param_type);
@@ -71,10 +72,22 @@ bitflags::bitflags! {
 }
 
 /// A transparent wrapper around a spa_param_info.
+#[derive(Clone, Copy)]
 #[repr(transparent)]
 pub struct ParamInfo(pub(crate) spa_sys::spa_param_info);
 
 impl ParamInfo {
+    /// Create a new param info, for example to describe the params of a node implemented in Rust.
+    pub fn new(id: ParamType, flags: ParamInfoFlags) -> Self {
+        Self(spa_sys::spa_param_info {
+            id: id.as_raw(),
+            flags: flags.bits(),
+            user: 0,
+            seq: 0,
+            padding: [0; 4],
+        })
+    }
+
     /// Get the param id
     pub fn id(&self) -> ParamType {
         ParamType::from_raw(self.0.id)
@@ -142,7 +155,6 @@ impl Debug for MetaType {
     }
 }
 
-
 pub trait TimelineManager {
     async fn set_acquire_point(&self, point: u64) -> Result<(), anyhow::Error>;
     async fn signal(&self, point: u64) -> Result<(), anyhow::Error>;
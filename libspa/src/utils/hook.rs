@@ -59,3 +59,121 @@ macro_rules! spa_interface_call_method {
         f((*iface).cb.data, $($arg),*)
     }};
 }
+
+/// A list of hooks, used by objects implemented in Rust to keep track of
+/// the listeners added to them.
+///
+/// This is the equivalent of `struct spa_hook_list`. Hooks still in the list when it is dropped
+/// are removed from it, calling their `removed` callback.
+pub struct HookList {
+    // Boxed as the hooks in the list point to its head.
+    list: Box<std::cell::UnsafeCell<spa_sys::spa_hook_list>>,
+}
+
+impl HookList {
+    pub fn new() -> Self {
+        let list = Box::new(std::cell::UnsafeCell::new(spa_sys::spa_hook_list {
+            list: spa_sys::spa_list {
+                next: std::ptr::null_mut(),
+                prev: std::ptr::null_mut(),
+            },
+        }));
+
+        unsafe {
+            let head = std::ptr::addr_of_mut!((*list.get()).list);
+            (*head).next = head;
+            (*head).prev = head;
+        }
+
+        Self { list }
+    }
+
+    fn head(&self) -> *mut spa_sys::spa_list {
+        unsafe { std::ptr::addr_of_mut!((*self.list.get()).list) }
+    }
+
+    /// Append `hook` to the list, setting its callbacks to `funcs` and `data`.
+    ///
+    /// # Safety
+    /// `hook` must point to a valid hook that is not part of any list, and must stay valid until it is
+    /// removed from the list, either with [`remove`] or by dropping the list.
+    pub unsafe fn append(
+        &self,
+        hook: *mut spa_sys::spa_hook,
+        funcs: *const std::os::raw::c_void,
+        data: *mut std::os::raw::c_void,
+    ) {
+        (*hook).cb = spa_sys::spa_callbacks { funcs, data };
+
+        let head = self.head();
+        let link = std::ptr::addr_of_mut!((*hook).link);
+        (*link).prev = (*head).prev;
+        (*link).next = head;
+        (*(*head).prev).next = link;
+        (*head).prev = link;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        let head = self.head();
+        unsafe { (*head).next == head }
+    }
+
+    /// Call `f` with the callbacks of every hook in the list, in order.
+    ///
+    /// Hooks may be removed from the list by `f`: the callbacks are collected before any call.
+    pub fn for_each<F>(&self, mut f: F)
+    where
+        F: FnMut(*const std::os::raw::c_void, *mut std::os::raw::c_void),
+    {
+        let mut callbacks = Vec::new();
+
+        let head = self.head();
+        unsafe {
+            let mut link = (*head).next;
+            while link != head {
+                // The link is the first member of a hook.
+                let hook: *mut spa_sys::spa_hook = link.cast();
+                callbacks.push((*hook).cb);
+                link = (*link).next;
+            }
+        }
+
+        for cb in callbacks {
+            f(cb.funcs, cb.data);
+        }
+    }
+}
+
+impl Default for HookList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for HookList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HookList")
+            .field("is_empty", &self.is_empty())
+            .finish()
+    }
+}
+
+impl Drop for HookList {
+    fn drop(&mut self) {
+        let head = self.head();
+        unsafe {
+            loop {
+                let link = (*head).next;
+                if link == head {
+                    break;
+                }
+
+                let hook: *mut spa_sys::spa_hook = link.cast();
+                list::remove(&(*hook).link);
+                if let Some(removed) = (*hook).removed {
+                    removed(hook);
+                }
+            }
+        }
+    }
+}
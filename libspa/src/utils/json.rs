@@ -0,0 +1,671 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! SPA-JSON parsing and serialization.
+//!
+//! SPA-JSON is the relaxed JSON dialect used by PipeWire configuration files and properties.
+//! Compared to regular JSON:
+//! - `:`, `,` and `=` are treated as whitespace, so they are optional,
+//! - object keys and simple values do not need to be quoted,
+//! - `#` starts a comment that runs until the end of the line.
+//!
+//! Unquoted values are interpreted as `null`, booleans or numbers when they have that form,
+//! and as strings otherwise.
+
+use std::{fmt, str::FromStr};
+
+/// A parsed SPA-JSON value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    /// A number, stored as written to preserve its exact representation.
+    Number(String),
+    String(String),
+    Array(Vec<Value>),
+    /// The members of an object, in the order they appear in.
+    ///
+    /// Keys may appear more than once, in which case the last member takes precedence.
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Parse a single SPA-JSON value.
+    ///
+    /// Arrays and objects nested more than 128 levels deep are rejected.
+    pub fn parse(input: &str) -> Result<Self, ParseError> {
+        let mut parser = Parser::new(input);
+        let value = parser.value()?;
+        parser.end()?;
+
+        Ok(value)
+    }
+
+    /// Parse the members of a SPA-JSON object.
+    ///
+    /// The enclosing braces may be omitted, so that both `{ a = 1 b = 2 }` and `a = 1 b = 2`
+    /// are accepted, like PipeWire does for properties and configuration files.
+    pub fn parse_object(input: &str) -> Result<Vec<(String, Self)>, ParseError> {
+        let mut parser = Parser::new(input);
+
+        let members = if parser.peek() == Some(b'{') {
+            parser.pos += 1;
+            parser.members(Some(b'}'))?
+        } else {
+            parser.members(None)?
+        };
+        parser.end()?;
+
+        Ok(members)
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::Number(n) => n.parse().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Number(n) => n.parse().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Self::Array(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&[(String, Value)]> {
+        match self {
+            Self::Object(o) => Some(o),
+            _ => None,
+        }
+    }
+
+    /// Get the value of the member `key` if this is an object.
+    ///
+    /// If the key appears multiple times, the last value is returned.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.as_object()?
+            .iter()
+            .rev()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v)
+    }
+
+    /// The value as it would be stored in a property.
+    ///
+    /// Strings are returned without quotes, other values as their serialized JSON form,
+    /// and [`Value::Null`] as [`None`], as it removes the property.
+    pub fn to_property_value(&self) -> Option<String> {
+        match self {
+            Self::Null => None,
+            Self::String(s) => Some(s.clone()),
+            other => Some(other.to_string()),
+        }
+    }
+}
+
+impl FromStr for Value {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Self::Bool(b)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(n: i64) -> Self {
+        Self::Number(n.to_string())
+    }
+}
+
+/// Non-finite numbers can't be represented in JSON and are converted to [`Value::Null`].
+impl From<f64> for Value {
+    fn from(n: f64) -> Self {
+        if n.is_finite() {
+            Self::Number(n.to_string())
+        } else {
+            Self::Null
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Self::String(s.to_owned())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Self::String(s)
+    }
+}
+
+/// Serializes the value as standard JSON.
+///
+/// The alternate form `{:#}` spreads arrays and objects over indented lines,
+/// keeping arrays of simple values on one line.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return self.write_pretty(f, 0);
+        }
+
+        match self {
+            Self::Null => f.write_str("null"),
+            Self::Bool(b) => write!(f, "{}", b),
+            Self::Number(n) => f.write_str(n),
+            Self::String(s) => write_string(f, s),
+            Self::Array(values) => {
+                f.write_str("[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                f.write_str("]")
+            }
+            Self::Object(members) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ": {}", value)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+impl Value {
+    fn is_container(&self) -> bool {
+        matches!(self, Self::Array(_) | Self::Object(_))
+    }
+
+    fn write_pretty(&self, f: &mut fmt::Formatter<'_>, indent: usize) -> fmt::Result {
+        const INDENT: usize = 2;
+
+        match self {
+            Self::Array(values) if values.is_empty() => f.write_str("[]"),
+            Self::Object(members) if members.is_empty() => f.write_str("{}"),
+            Self::Array(values) if !values.iter().any(Self::is_container) => {
+                f.write_str("[ ")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                f.write_str(" ]")
+            }
+            Self::Array(values) => {
+                f.write_str("[\n")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",\n")?;
+                    }
+                    write!(f, "{:1$}", "", indent + INDENT)?;
+                    value.write_pretty(f, indent + INDENT)?;
+                }
+                write!(f, "\n{:1$}]", "", indent)
+            }
+            Self::Object(members) => {
+                f.write_str("{\n")?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",\n")?;
+                    }
+                    write!(f, "{:1$}", "", indent + INDENT)?;
+                    write_string(f, key)?;
+                    f.write_str(": ")?;
+                    value.write_pretty(f, indent + INDENT)?;
+                }
+                write!(f, "\n{:1$}}}", "", indent)
+            }
+            value => write!(f, "{}", value),
+        }
+    }
+}
+
+/// Write `s` as a quoted and escaped JSON string.
+pub fn write_string(f: &mut impl fmt::Write, s: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            '\u{8}' => f.write_str("\\b")?,
+            '\u{c}' => f.write_str("\\f")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+/// Error returned when parsing invalid SPA-JSON.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    offset: usize,
+    message: &'static str,
+}
+
+impl ParseError {
+    /// The byte offset in the input at which the error occurred.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid SPA-JSON at offset {}: {}",
+            self.offset, self.message
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// How deeply arrays and objects can be nested, to bound the recursion of the parser.
+const MAX_DEPTH: usize = 128;
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            input,
+            pos: 0,
+            depth: 0,
+        }
+    }
+
+    fn error(&self, message: &'static str) -> ParseError {
+        ParseError {
+            offset: self.pos,
+            message,
+        }
+    }
+
+    /// Skip whitespace, separators and comments, and return the next byte.
+    fn peek(&mut self) -> Option<u8> {
+        let bytes = self.input.as_bytes();
+
+        while let Some(&b) = bytes.get(self.pos) {
+            match b {
+                b'\0' | b' ' | b'\t' | b'\r' | b'\n' | b':' | b',' | b'=' => self.pos += 1,
+                b'#' => {
+                    while bytes.get(self.pos).is_some_and(|&b| b != b'\n') {
+                        self.pos += 1;
+                    }
+                }
+                b => return Some(b),
+            }
+        }
+
+        None
+    }
+
+    fn end(&mut self) -> Result<(), ParseError> {
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => Err(self.error("trailing characters")),
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, ParseError> {
+        match self.peek() {
+            None => Err(self.error("unexpected end of input")),
+            Some(b'{') => self.nested(|parser| Ok(Value::Object(parser.members(Some(b'}'))?))),
+            Some(b'[') => self.nested(|parser| {
+                let mut values = Vec::new();
+                loop {
+                    match parser.peek() {
+                        Some(b']') => {
+                            parser.pos += 1;
+                            break;
+                        }
+                        None => return Err(parser.error("unterminated array")),
+                        Some(_) => values.push(parser.value()?),
+                    }
+                }
+                Ok(Value::Array(values))
+            }),
+            Some(b'"') => Ok(Value::String(self.string()?)),
+            Some(b'}') | Some(b']') => Err(self.error("unexpected closing bracket")),
+            Some(_) => Ok(bare_value(self.bare())),
+        }
+    }
+
+    /// Parse the array or object opened at the current position with `f`.
+    fn nested(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<Value, ParseError>,
+    ) -> Result<Value, ParseError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error("too deeply nested"));
+        }
+
+        self.pos += 1;
+        self.depth += 1;
+        let value = f(self);
+        self.depth -= 1;
+
+        value
+    }
+
+    /// Parse object members until `close`, or until the end of input if `close` is [`None`].
+    fn members(&mut self, close: Option<u8>) -> Result<Vec<(String, Value)>, ParseError> {
+        let mut members = Vec::new();
+
+        loop {
+            let key = match self.peek() {
+                None if close.is_none() => break,
+                None => return Err(self.error("unterminated object")),
+                Some(b) if Some(b) == close => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(b'"') => self.string()?,
+                Some(b'{') | Some(b'[') | Some(b'}') | Some(b']') => {
+                    return Err(self.error("expected object key"))
+                }
+                Some(_) => self.bare().to_owned(),
+            };
+            let value = self.value()?;
+
+            members.push((key, value));
+        }
+
+        Ok(members)
+    }
+
+    fn bare(&mut self) -> &'a str {
+        let start = self.pos;
+        let bytes = self.input.as_bytes();
+
+        while let Some(&b) = bytes.get(self.pos) {
+            match b {
+                b'\0' | b' ' | b'\t' | b'\r' | b'\n' | b'"' | b'#' | b':' | b',' | b'=' | b']'
+                | b'}' => break,
+                _ => self.pos += 1,
+            }
+        }
+
+        &self.input[start..self.pos]
+    }
+
+    /// Parse the 4 hex digits of a `\u` escape at `start`.
+    fn hex4(&self, start: usize) -> Result<u32, ParseError> {
+        self.input
+            .get(start..start + 4)
+            .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+            .ok_or_else(|| self.error("invalid unicode escape"))
+    }
+
+    fn string(&mut self) -> Result<String, ParseError> {
+        // Skip the opening quote
+        self.pos += 1;
+
+        let mut s = String::new();
+        let mut chars = self.input[self.pos..].char_indices();
+
+        loop {
+            let (i, c) = chars
+                .next()
+                .ok_or_else(|| self.error("unterminated string"))?;
+            match c {
+                '"' => {
+                    self.pos += i + 1;
+                    return Ok(s);
+                }
+                '\\' => {
+                    let (i, c) = chars
+                        .next()
+                        .ok_or_else(|| self.error("unterminated string"))?;
+                    match c {
+                        'n' => s.push('\n'),
+                        'r' => s.push('\r'),
+                        't' => s.push('\t'),
+                        'b' => s.push('\u{8}'),
+                        'f' => s.push('\u{c}'),
+                        'u' => {
+                            let start = self.pos + i + 1;
+                            let mut code = self.hex4(start)?;
+                            let mut len = 4;
+                            // Characters outside the BMP are escaped as a UTF-16 surrogate pair.
+                            if (0xd800..0xdc00).contains(&code)
+                                && self.input[start + 4..].starts_with("\\u")
+                            {
+                                if let Ok(low @ 0xdc00..=0xdfff) = self.hex4(start + 6) {
+                                    code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                                    len += 6;
+                                }
+                            }
+                            for _ in 0..len {
+                                chars.next();
+                            }
+                            s.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                        }
+                        // `\"`, `\\`, `\/` and unknown escapes are replaced by the escaped character
+                        c => s.push(c),
+                    }
+                }
+                c => s.push(c),
+            }
+        }
+    }
+}
+
+fn bare_value(token: &str) -> Value {
+    match token {
+        "null" => Value::Null,
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ if is_number(token) => Value::Number(token.to_owned()),
+        _ => Value::String(token.to_owned()),
+    }
+}
+
+fn is_number(token: &str) -> bool {
+    let digits = token.strip_prefix('-').unwrap_or(token);
+
+    digits.starts_with(|c: char| c.is_ascii_digit() || c == '.') && token.parse::<f64>().is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_scalars() {
+        assert_eq!(Value::parse("null"), Ok(Value::Null));
+        assert_eq!(Value::parse("true"), Ok(Value::Bool(true)));
+        assert_eq!(Value::parse(" 48000 "), Ok(Value::Number("48000".into())));
+        assert_eq!(Value::parse("-0.5").unwrap().as_f64(), Some(-0.5));
+        assert_eq!(Value::parse("inf"), Ok(Value::String("inf".into())));
+        assert_eq!(Value::parse("+5"), Ok(Value::String("+5".into())));
+        assert_eq!(
+            Value::parse("1024/48000"),
+            Ok(Value::String("1024/48000".into()))
+        );
+        assert_eq!(
+            Value::parse(r#""a \"b\"\né""#),
+            Ok(Value::String("a \"b\"\né".into()))
+        );
+    }
+
+    #[test]
+    fn parse_relaxed() {
+        let value = Value::parse(
+            r#"
+            # a comment
+            {
+                node.name = "test"
+                "audio.rate": 48000,
+                rules = [ { matches = [ { media.class = "~Audio/.*" } ] } ]
+                empty = { }
+            }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(value.get("node.name").and_then(Value::as_str), Some("test"));
+        assert_eq!(value.get("audio.rate").and_then(Value::as_i64), Some(48000));
+        assert_eq!(
+            value.get("rules").and_then(Value::as_array).map(<[_]>::len),
+            Some(1)
+        );
+        assert_eq!(value.get("empty"), Some(&Value::Object(vec![])));
+    }
+
+    #[test]
+    fn parse_object() {
+        let expected = vec![
+            ("a".to_string(), Value::Number("1".into())),
+            ("b".to_string(), Value::String("c".into())),
+        ];
+
+        assert_eq!(Value::parse_object("a = 1 b = c"), Ok(expected.clone()));
+        assert_eq!(Value::parse_object("{ a: 1, b: c }"), Ok(expected));
+        assert_eq!(Value::parse_object(""), Ok(vec![]));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(Value::parse("").is_err());
+        assert!(Value::parse("[ 1 2").is_err());
+        assert!(Value::parse("{ a = 1").is_err());
+        assert!(Value::parse("\"abc").is_err());
+        assert!(Value::parse("1 2").is_err());
+        assert!(Value::parse("}").is_err());
+    }
+
+    #[test]
+    fn parse_depth() {
+        let nested = |depth| "[".repeat(depth) + &"]".repeat(depth);
+        assert!(Value::parse(&nested(MAX_DEPTH)).is_ok());
+
+        let err = Value::parse(&nested(MAX_DEPTH + 1)).unwrap_err();
+        assert_eq!(err.offset(), MAX_DEPTH);
+        assert!(Value::parse(&"{ a = ".repeat(100_000)).is_err());
+    }
+
+    #[test]
+    fn parse_unicode_escapes() {
+        assert_eq!(
+            Value::parse(r#""\u00e9\ud83d\ude00!""#),
+            Ok(Value::String("é😀!".into()))
+        );
+        // Unpaired surrogates are replaced.
+        assert_eq!(
+            Value::parse(r#""\ud83d \ude00""#),
+            Ok(Value::String("\u{fffd} \u{fffd}".into()))
+        );
+        assert_eq!(
+            Value::parse(r#""\ud83d\u0041""#),
+            Ok(Value::String("\u{fffd}A".into()))
+        );
+        assert!(Value::parse(r#""\u+041""#).is_err());
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        let value = Value::parse("{ a = 1 a = 2 }").unwrap();
+        assert_eq!(value.get("a").and_then(Value::as_i64), Some(2));
+    }
+
+    #[test]
+    fn serialize() {
+        let value = Value::parse(r#"{ a = [ 1 true null ] "b c" = "d\"e" }"#).unwrap();
+        assert_eq!(
+            value.to_string(),
+            r#"{"a": [1, true, null], "b c": "d\"e"}"#
+        );
+        assert_eq!(Value::parse(&value.to_string()), Ok(value));
+    }
+
+    #[test]
+    fn serialize_pretty() {
+        let value = Value::parse(r#"{ a = [ 1 true null ] b = [ { c = {} } [] ] }"#).unwrap();
+        assert_eq!(
+            format!("{:#}", value),
+            r#"{
+  "a": [ 1, true, null ],
+  "b": [
+    {
+      "c": {}
+    },
+    []
+  ]
+}"#
+        );
+        assert_eq!(Value::parse(&format!("{:#}", value)), Ok(value));
+    }
+
+    #[test]
+    fn from_f64() {
+        assert_eq!(Value::from(0.5), Value::Number("0.5".into()));
+        assert_eq!(Value::from(-48000.0), Value::Number("-48000".into()));
+        assert_eq!(Value::from(f64::NAN), Value::Null);
+        assert_eq!(Value::from(f64::INFINITY), Value::Null);
+        assert_eq!(Value::from(f64::NEG_INFINITY), Value::Null);
+    }
+
+    #[test]
+    fn property_value() {
+        assert_eq!(Value::from("abc").to_property_value(), Some("abc".into()));
+        assert_eq!(Value::from(5i64).to_property_value(), Some("5".into()));
+        assert_eq!(Value::Null.to_property_value(), None);
+        assert_eq!(
+            Value::parse("[ a b ]").unwrap().to_property_value(),
+            Some(r#"["a", "b"]"#.into())
+        );
+    }
+}
@@ -4,6 +4,7 @@ pub mod dict;
 mod direction;
 pub use direction::*;
 pub mod hook;
+pub mod json;
 pub mod list;
 pub mod result;
 
@@ -3,9 +3,11 @@
 
 //! SPA results and errors.
 
-use std::{convert::TryInto, fmt};
+use std::{convert::TryInto, ffi::c_int, fmt};
 
-use nix::errno::Errno;
+/// The errno type used in this crate, re-exported so implementations of SPA interfaces such as
+/// [`SpaNode`](`crate::node::SpaNode`) can return errors without depending on `nix` themselves.
+pub use nix::errno::Errno;
 
 /// A result returned by a SPA method, usually to be converted to
 /// a Rust result using [`SpaResult::into_result`] or [`SpaResult::into_async_result`].
@@ -61,6 +63,11 @@ impl AsyncSeq {
         debug_assert!(is_async(val));
         Self(val)
     }
+
+    /// Create a new [`AsyncSeq`] from a raw value, returning [`None`] if the `SPA_ASYNC_BIT` bit is not set
+    pub fn try_from_raw(val: i32) -> Option<Self> {
+        is_async(val).then_some(Self(val))
+    }
 }
 
 impl fmt::Debug for AsyncSeq {
@@ -130,11 +137,16 @@ impl SpaResult {
 pub struct Error(Errno);
 
 impl Error {
-    fn new(e: i32) -> Self {
+    pub(crate) fn new(e: i32) -> Self {
         assert!(e > 0);
 
         Self(Errno::from_raw(e))
     }
+
+    /// The errno describing the error
+    pub fn errno(&self) -> Errno {
+        self.0
+    }
 }
 
 impl std::error::Error for Error {}
@@ -145,6 +157,18 @@ impl fmt::Display for Error {
     }
 }
 
+/// Convert the result of a method implemented in Rust into the value returned to C,
+/// `0` on success or a negative errno.
+///
+/// Used by the C trampolines of the interfaces implemented in Rust, in this crate and in `pipewire`.
+#[doc(hidden)]
+pub fn to_c(res: Result<(), Errno>) -> c_int {
+    match res {
+        Ok(()) => 0,
+        Err(errno) => -(errno as c_int),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -176,10 +200,21 @@ mod tests {
         assert!(res.is_err());
     }
 
+    #[test]
+    fn to_c_result() {
+        assert_eq!(to_c(Ok(())), 0);
+        assert_eq!(to_c(Err(Errno::ENOENT)), -libc::ENOENT);
+    }
+
     #[test]
     fn async_seq() {
         assert_eq!(AsyncSeq::from_seq(0).seq(), 0);
         assert_eq!(AsyncSeq::from_seq(1).seq(), 1);
+        assert_eq!(
+            AsyncSeq::try_from_raw(AsyncSeq::from_seq(2).raw()),
+            Some(AsyncSeq::from_seq(2))
+        );
+        assert_eq!(AsyncSeq::try_from_raw(2), None);
     }
 
     #[should_panic]
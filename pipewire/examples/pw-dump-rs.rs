@@ -0,0 +1,98 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Dump the objects of the graph as JSON, like `pw-dump`.
+
+use std::cell::Cell;
+
+use anyhow::Result;
+use clap::Parser;
+use pipewire as pw;
+use pw::{dump::Dump, loop_::Signal, properties::properties};
+
+fn dump(remote: Option<String>, monitor: bool, compact: bool) -> Result<()> {
+    let main_loop = pw::main_loop::MainLoop::new(None)?;
+
+    let main_loop_weak = main_loop.downgrade();
+    let _sig_int = main_loop.loop_().add_signal_local(Signal::SIGINT, move || {
+        if let Some(main_loop) = main_loop_weak.upgrade() {
+            main_loop.quit();
+        }
+    });
+
+    let context = pw::context::Context::new(&main_loop)?;
+    let props = remote.map(|remote| {
+        properties! {
+            *pw::keys::REMOTE_NAME => remote
+        }
+    });
+    let core = context.connect(props)?;
+
+    let main_loop_weak = main_loop.downgrade();
+    let _core_listener = core
+        .add_listener_local()
+        .error(move |error| {
+            eprintln!("{}", error);
+
+            if error.id == pw::core::PW_ID_CORE {
+                if let Some(main_loop) = main_loop_weak.upgrade() {
+                    main_loop.quit();
+                }
+            }
+        })
+        .register();
+
+    let dump = Dump::new(&core)?;
+
+    // The first report contains all the objects, later ones only those that changed.
+    let main_loop_weak = main_loop.downgrade();
+    let first = Cell::new(true);
+    let _listener = dump
+        .add_listener_local()
+        .changed(move |objects| {
+            if !first.replace(false) && !monitor {
+                return;
+            }
+
+            if compact {
+                println!("{}", objects);
+            } else {
+                println!("{:#}", objects);
+            }
+
+            if !monitor {
+                if let Some(main_loop) = main_loop_weak.upgrade() {
+                    main_loop.quit();
+                }
+            }
+        })
+        .register();
+
+    main_loop.run();
+
+    Ok(())
+}
+
+#[derive(Parser)]
+#[clap(name = "pw-dump-rs", about = "Dump PipeWire objects as JSON")]
+struct Opt {
+    #[clap(short, long, help = "The name of the remote to connect to")]
+    remote: Option<String>,
+    #[clap(short, long, help = "Keep running and print the objects that changed")]
+    monitor: bool,
+    #[clap(long, help = "Print each dump on a single line")]
+    compact: bool,
+}
+
+fn main() -> Result<()> {
+    pw::init();
+
+    let opt = Opt::parse();
+    dump(opt.remote, opt.monitor, opt.compact)?;
+
+    unsafe {
+        pw::deinit();
+    }
+
+    Ok(())
+}
@@ -90,10 +90,10 @@ fn monitor(remote: Option<String>) -> Result<()> {
         .done(|_id, _seq| {
             // TODO
         })
-        .error(move |id, seq, res, message| {
-            eprintln!("error id:{} seq:{} res:{}: {}", id, seq, res, message);
+        .error(move |error| {
+            eprintln!("{}", error);
 
-            if id == 0 {
+            if error.id == pw::core::PW_ID_CORE {
                 if let Some(main_loop) = main_loop_weak.upgrade() {
                     main_loop.quit();
                 }
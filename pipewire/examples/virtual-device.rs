@@ -0,0 +1,217 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Export a virtual audio device implemented in Rust.
+//!
+//! The device has two profiles: "off", and "output" which provides a sink node.
+//! Switch between them with, for example, `pw-cli set-param <device id> Profile '{ index: 1 }'`
+//! or the profile selector of your desktop.
+
+use std::{cell::Cell, ffi::CStr};
+
+use pipewire as pw;
+use pw::{properties::properties, spa};
+use spa::{
+    device::{Device, DeviceEmitter, DeviceInfo, ObjectInfo, SpaDevice},
+    param::{ParamInfo, ParamInfoFlags, ParamType},
+    pod::{deserialize::PodDeserializer, serialize::PodSerializer, Object, Pod, Property, Value},
+    sys,
+    utils::{result::Errno, Direction, Id, SpaTypes},
+};
+
+const PROFILES: [(&str, &str); 2] = [("off", "Off"), ("output", "Analog Stereo Output")];
+
+struct VirtualDevice {
+    profile: Cell<u32>,
+    node_props: pw::properties::Properties,
+}
+
+fn serialize(object: Object) -> Vec<u8> {
+    PodSerializer::serialize(std::io::Cursor::new(Vec::new()), &Value::Object(object))
+        .unwrap()
+        .0
+        .into_inner()
+}
+
+fn profile(id: ParamType, index: u32) -> Vec<u8> {
+    let (name, description) = PROFILES[index as usize];
+    serialize(Object {
+        type_: SpaTypes::ObjectParamProfile.as_raw(),
+        id: id.as_raw(),
+        properties: vec![
+            Property::new(sys::SPA_PARAM_PROFILE_index, Value::Int(index as i32)),
+            Property::new(sys::SPA_PARAM_PROFILE_name, Value::String(name.into())),
+            Property::new(
+                sys::SPA_PARAM_PROFILE_description,
+                Value::String(description.into()),
+            ),
+            Property::new(
+                sys::SPA_PARAM_PROFILE_available,
+                Value::Id(Id(sys::SPA_PARAM_AVAILABILITY_yes)),
+            ),
+        ],
+    })
+}
+
+fn route(id: ParamType) -> Vec<u8> {
+    serialize(Object {
+        type_: SpaTypes::ObjectParamRoute.as_raw(),
+        id: id.as_raw(),
+        properties: vec![
+            Property::new(sys::SPA_PARAM_ROUTE_index, Value::Int(0)),
+            Property::new(
+                sys::SPA_PARAM_ROUTE_direction,
+                Value::Id(Id(Direction::Output.as_raw())),
+            ),
+            Property::new(sys::SPA_PARAM_ROUTE_device, Value::Int(0)),
+            Property::new(
+                sys::SPA_PARAM_ROUTE_name,
+                Value::String("analog-output-speaker".into()),
+            ),
+            Property::new(
+                sys::SPA_PARAM_ROUTE_description,
+                Value::String("Speakers".into()),
+            ),
+            Property::new(
+                sys::SPA_PARAM_ROUTE_available,
+                Value::Id(Id(sys::SPA_PARAM_AVAILABILITY_yes)),
+            ),
+        ],
+    })
+}
+
+impl VirtualDevice {
+    fn emit_info(&self, emitter: &DeviceEmitter) {
+        let params = [
+            ParamInfo::new(ParamType::EnumProfile, ParamInfoFlags::READ),
+            ParamInfo::new(ParamType::Profile, ParamInfoFlags::READWRITE),
+            ParamInfo::new(ParamType::EnumRoute, ParamInfoFlags::READ),
+            ParamInfo::new(ParamType::Route, ParamInfoFlags::READ),
+        ];
+
+        emitter.info(&DeviceInfo {
+            change_mask: sys::SPA_DEVICE_CHANGE_MASK_PARAMS as u64,
+            params: &params,
+            ..Default::default()
+        });
+    }
+
+    fn emit_objects(&self, emitter: &DeviceEmitter) {
+        if self.profile.get() == 0 {
+            emitter.object_info(0, None);
+            return;
+        }
+
+        emitter.object_info(
+            0,
+            Some(&ObjectInfo {
+                type_: CStr::from_bytes_with_nul(sys::SPA_TYPE_INTERFACE_Node).unwrap(),
+                factory_name: c"support.null-audio-sink",
+                change_mask: sys::SPA_DEVICE_OBJECT_CHANGE_MASK_PROPS as u64,
+                flags: 0,
+                props: Some(self.node_props.dict()),
+            }),
+        );
+    }
+}
+
+impl SpaDevice for VirtualDevice {
+    fn add_listener(&self, emitter: &DeviceEmitter) {
+        self.emit_info(emitter);
+        self.emit_objects(emitter);
+    }
+
+    fn enum_params(
+        &self,
+        emitter: &DeviceEmitter,
+        seq: i32,
+        id: ParamType,
+        start: u32,
+        max: u32,
+        _filter: Option<&Pod>,
+    ) -> Result<(), Errno> {
+        let params = match id {
+            ParamType::EnumProfile => (0..PROFILES.len() as u32)
+                .map(|index| profile(id, index))
+                .collect(),
+            ParamType::Profile => vec![profile(id, self.profile.get())],
+            ParamType::EnumRoute => vec![route(id)],
+            ParamType::Route if self.profile.get() != 0 => vec![route(id)],
+            ParamType::Route => vec![],
+            _ => return Err(Errno::ENOENT),
+        };
+
+        let end = params.len().min(start.saturating_add(max) as usize) as u32;
+        for index in start..end {
+            let pod = Pod::from_bytes(&params[index as usize]).unwrap();
+            emitter.param(seq, id, index, index + 1, pod);
+        }
+
+        Ok(())
+    }
+
+    fn set_param(
+        &self,
+        emitter: &DeviceEmitter,
+        id: ParamType,
+        _flags: u32,
+        param: Option<&Pod>,
+    ) -> Result<(), Errno> {
+        if id != ParamType::Profile {
+            return Err(Errno::ENOTSUP);
+        }
+
+        let (_, value) = param
+            .and_then(|param| PodDeserializer::deserialize_any_from(param.as_bytes()).ok())
+            .ok_or(Errno::EINVAL)?;
+        let Value::Object(object) = value else {
+            return Err(Errno::EINVAL);
+        };
+        let index = object
+            .properties
+            .iter()
+            .find(|prop| prop.key == sys::SPA_PARAM_PROFILE_index)
+            .and_then(|prop| match prop.value {
+                Value::Int(index) => Some(index as u32),
+                _ => None,
+            })
+            .filter(|index| (*index as usize) < PROFILES.len())
+            .ok_or(Errno::EINVAL)?;
+
+        println!("Switching to profile {}", PROFILES[index as usize].0);
+        self.profile.set(index);
+        self.emit_info(emitter);
+        self.emit_objects(emitter);
+
+        Ok(())
+    }
+}
+
+fn main() -> Result<(), pw::Error> {
+    pw::init();
+
+    let mainloop = pw::main_loop::MainLoop::new(None)?;
+    let context = pw::context::Context::new(&mainloop)?;
+    let core = context.connect(None)?;
+
+    let device = Device::new(VirtualDevice {
+        profile: Cell::new(1),
+        node_props: properties! {
+            *pw::keys::NODE_NAME => "virtual-sink",
+            *pw::keys::NODE_DESCRIPTION => "Virtual Sink",
+            *pw::keys::MEDIA_CLASS => "Audio/Sink",
+            "audio.position" => "FL,FR",
+        },
+    });
+
+    let props = properties! {
+        *pw::keys::DEVICE_NAME => "virtual-device",
+        *pw::keys::DEVICE_DESCRIPTION => "Virtual Device",
+        *pw::keys::MEDIA_CLASS => "Audio/Device",
+    };
+    let _exported = core.export(device, Some(props.dict()))?;
+
+    mainloop.run();
+
+    Ok(())
+}
@@ -14,9 +14,11 @@ use std::{fmt, mem};
 use crate::{
     permissions::Permission,
     proxy::{Listener, Proxy, ProxyT},
+    reply::{self, Reply},
     types::ObjectType,
+    Error,
 };
-use spa::spa_interface_call_method;
+use spa::{spa_interface_call_method, utils::result::SpaResult};
 
 #[derive(Debug)]
 pub struct Client {
@@ -96,6 +98,43 @@ impl Client {
         }
     }
 
+    /// Query the permissions of the client and collect the replies
+    ///
+    /// Same as [`get_permissions()`](`Self::get_permissions()`), but returns a [`Reply`] resolving
+    /// to all the permissions emitted in reply.
+    pub fn get_permissions_async(
+        &self,
+        index: u32,
+        num: u32,
+    ) -> Result<Reply<Vec<Permission>>, Error> {
+        let res = unsafe {
+            spa_interface_call_method!(
+                self.proxy.as_ptr(),
+                pw_sys::pw_client_methods,
+                get_permissions,
+                index,
+                num
+            )
+        };
+        let seq = SpaResult::from_c(res).into_async_result()?;
+
+        // `permissions` events do not carry a sequence number, so collect all of them
+        // until the sync issued after the call is answered.
+        let mut reply = reply::track(&self.proxy, seq, Vec::new())?;
+        let state = reply.state();
+        let listener = self
+            .add_listener_local()
+            .permissions(move |_index, permissions| {
+                state
+                    .borrow_mut()
+                    .update(|collected| collected.extend_from_slice(permissions));
+            })
+            .register();
+        reply.hold(listener);
+
+        Ok(reply)
+    }
+
     pub fn update_permissions(&self, permissions: &[Permission]) {
         unsafe {
             spa_interface_call_method!(
@@ -200,6 +239,7 @@ impl AsRef<ClientInfoRef> for ClientInfo {
 
 bitflags! {
     #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct ClientChangeMask: u64 {
         const PROPS = pw_sys::PW_CLIENT_CHANGE_MASK_PROPS as u64;
     }
@@ -0,0 +1,300 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Configuration helpers.
+//!
+//! Configuration sections can be read from a [`Context`](`crate::context::Context`)
+//! with [`ContextRef::conf_section`](`crate::context::ContextRef::conf_section`).
+//! This module provides [`MatchRules`], an implementation of the rules found in sections such as
+//! `stream.rules` or `node.rules`, following the semantics of `pw_conf_match_rules()`:
+//!
+//! ```text
+//! stream.rules = [
+//!     {
+//!         matches = [
+//!             # all keys of an object must match, any object of the array may match
+//!             { application.name = "pw-cat" media.role = "~Music|Movie" }
+//!         ]
+//!         actions = {
+//!             update-props = { node.latency = 1024/48000 }
+//!         }
+//!     }
+//! ]
+//! ```
+//!
+//! Match values are compared to the property of the same key. A string value starting
+//! with `~` is a POSIX extended regular expression, one starting with `!` negates the match,
+//! and `null` matches properties that are not set.
+
+use std::{ffi::CString, fmt, mem::MaybeUninit};
+
+use spa::utils::{dict::DictRef, json::Value};
+
+use crate::properties::PropertiesRef;
+
+/// The name of the action updating the properties of the matched object.
+pub const ACTION_UPDATE_PROPS: &str = "update-props";
+
+/// A list of rules, each applying actions to the objects whose properties match.
+#[derive(Debug, Default)]
+pub struct MatchRules {
+    rules: Vec<MatchRule>,
+}
+
+#[derive(Debug)]
+struct MatchRule {
+    /// Any of these must match, all members of one must match.
+    matches: Vec<Vec<(String, Matcher)>>,
+    actions: Vec<(String, Value)>,
+}
+
+impl MatchRules {
+    /// Parse rules from the value of a configuration section.
+    ///
+    /// Like PipeWire, rules that are not objects or have no `matches` or `actions` are ignored.
+    pub fn from_value(value: &Value) -> Self {
+        let mut rules = Self::default();
+        rules.extend_from_value(value);
+        rules
+    }
+
+    /// Append the rules of another section value to these rules.
+    pub fn extend_from_value(&mut self, value: &Value) {
+        let Some(rules) = value.as_array() else {
+            return;
+        };
+
+        for rule in rules {
+            let (Some(matches), Some(actions)) = (
+                rule.get("matches").and_then(Value::as_array),
+                rule.get("actions").and_then(Value::as_object),
+            ) else {
+                continue;
+            };
+
+            let matches = matches
+                .iter()
+                .filter_map(Value::as_object)
+                .map(|members| {
+                    members
+                        .iter()
+                        .map(|(key, value)| (key.clone(), Matcher::new(value)))
+                        .collect()
+                })
+                .collect();
+
+            self.rules.push(MatchRule {
+                matches,
+                actions: actions.to_vec(),
+            });
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Call `f` with the name and value of every action of every rule matching `props`, in order.
+    pub fn for_each_action<F>(&self, props: &DictRef, mut f: F)
+    where
+        F: FnMut(&str, &Value),
+    {
+        for rule in self.rules.iter().filter(|rule| rule.matches(props)) {
+            for (action, value) in &rule.actions {
+                f(action, value);
+            }
+        }
+    }
+
+    /// Apply the `update-props` actions of all rules matching `props` to `props`.
+    ///
+    /// Returns the number of properties that were updated.
+    pub fn update_props(&self, props: &mut PropertiesRef) -> usize {
+        let mut updates = Vec::new();
+        self.for_each_action(props.dict(), |action, value| {
+            if action == ACTION_UPDATE_PROPS {
+                if let Some(members) = value.as_object() {
+                    updates.extend(members.iter().cloned());
+                }
+            }
+        });
+
+        let count = updates.len();
+        for (key, value) in updates {
+            match value.to_property_value() {
+                Some(value) => props.insert(key, value),
+                None => props.remove(key),
+            }
+        }
+
+        count
+    }
+}
+
+impl MatchRule {
+    fn matches(&self, props: &DictRef) -> bool {
+        self.matches.iter().any(|members| {
+            !members.is_empty()
+                && members
+                    .iter()
+                    .all(|(key, matcher)| matcher.matches(props.get(key)))
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Matcher {
+    negate: bool,
+    kind: MatcherKind,
+}
+
+#[derive(Debug)]
+enum MatcherKind {
+    Unset,
+    Exact(String),
+    /// [`None`] if the expression failed to compile, in which case it never matches.
+    Regex(Option<Regex>),
+}
+
+impl Matcher {
+    fn new(value: &Value) -> Self {
+        let (negate, kind) = match value {
+            Value::Null => (false, MatcherKind::Unset),
+            Value::String(s) => {
+                let (negate, s) = match s.strip_prefix('!') {
+                    Some(s) => (true, s),
+                    None => (false, s.as_str()),
+                };
+                match s.strip_prefix('~') {
+                    Some(pattern) => (negate, MatcherKind::Regex(Regex::new(pattern))),
+                    None => (negate, MatcherKind::Exact(s.to_owned())),
+                }
+            }
+            other => (false, MatcherKind::Exact(other.to_string())),
+        };
+
+        Self { negate, kind }
+    }
+
+    fn matches(&self, value: Option<&str>) -> bool {
+        let matched = match (&self.kind, value) {
+            (MatcherKind::Unset, value) => value.is_none(),
+            (MatcherKind::Exact(expected), Some(value)) => expected == value,
+            (MatcherKind::Regex(Some(regex)), Some(value)) => regex.is_match(value),
+            _ => false,
+        };
+
+        matched != self.negate
+    }
+}
+
+/// A POSIX extended regular expression, as used by the C implementation.
+struct Regex {
+    pattern: String,
+    // Boxed as the compiled expression must not move.
+    raw: Box<libc::regex_t>,
+}
+
+impl Regex {
+    fn new(pattern: &str) -> Option<Self> {
+        let c_pattern = CString::new(pattern).ok()?;
+        let mut raw = Box::new(MaybeUninit::<libc::regex_t>::uninit());
+
+        let res = unsafe {
+            libc::regcomp(
+                raw.as_mut_ptr(),
+                c_pattern.as_ptr(),
+                libc::REG_EXTENDED | libc::REG_NOSUB,
+            )
+        };
+        if res != 0 {
+            return None;
+        }
+
+        Some(Self {
+            pattern: pattern.to_owned(),
+            // SAFETY: regcomp() succeeded, so the expression is initialized.
+            raw: unsafe { Box::from_raw(Box::into_raw(raw).cast()) },
+        })
+    }
+
+    fn is_match(&self, s: &str) -> bool {
+        let Ok(s) = CString::new(s) else {
+            return false;
+        };
+
+        unsafe { libc::regexec(&*self.raw, s.as_ptr(), 0, std::ptr::null_mut(), 0) == 0 }
+    }
+}
+
+impl fmt::Debug for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Regex").field(&self.pattern).finish()
+    }
+}
+
+impl Drop for Regex {
+    fn drop(&mut self) {
+        unsafe { libc::regfree(&mut *self.raw) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::properties::properties;
+
+    fn rules() -> MatchRules {
+        let value = Value::parse(
+            r#"[
+                {
+                    matches = [
+                        { application.name = "pw-cat" media.role = "~^(Music|Movie)$" }
+                        { node.name = "!~^alsa_" node.description = null }
+                    ]
+                    actions = {
+                        update-props = { node.latency = 1024/48000 media.role = null }
+                    }
+                }
+                { matches = [ { application.name = "pw-cat" } ] }
+            ]"#,
+        )
+        .unwrap();
+
+        MatchRules::from_value(&value)
+    }
+
+    #[test]
+    fn parse() {
+        let rules = rules();
+        assert_eq!(rules.rules.len(), 1);
+        assert_eq!(rules.rules[0].matches.len(), 2);
+    }
+
+    #[test]
+    fn update_props() {
+        let rules = rules();
+
+        let mut props = properties! {
+            "application.name" => "pw-cat",
+            "media.role" => "Music"
+        };
+        assert_eq!(rules.update_props(&mut props), 2);
+        assert_eq!(props.get("node.latency"), Some("1024/48000"));
+        assert_eq!(props.get("media.role"), None);
+
+        let mut props = properties! {
+            "application.name" => "pw-cat",
+            "media.role" => "Communication",
+            "node.name" => "alsa_output"
+        };
+        assert_eq!(rules.update_props(&mut props), 0);
+        assert_eq!(props.get("node.latency"), None);
+
+        let mut props = properties! {
+            "node.name" => "bluez_output"
+        };
+        assert_eq!(rules.update_props(&mut props), 2);
+        assert_eq!(props.get("node.latency"), Some("1024/48000"));
+    }
+}
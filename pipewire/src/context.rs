@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: MIT
 
 use std::{
+    ffi::{CStr, CString},
     fmt,
     ops::Deref,
     os::unix::prelude::{IntoRawFd, OwnedFd},
@@ -9,9 +10,20 @@ use std::{
     rc::Rc,
 };
 
+use nix::errno::Errno;
+use spa::{
+    pod::Pod,
+    utils::{json::Value, result::SpaResult},
+};
+
+use crate::conf::MatchRules;
 use crate::core::Core;
 use crate::error::Error;
+use crate::impl_::{
+    ImplCoreRef, ImplFactoryRef, ImplLink, ImplModule, ImplNode, ImplPortRef, SpaHandle,
+};
 use crate::loop_::{IsLoopRc, LoopRef};
+use crate::mem::MemPoolRef;
 use crate::properties::{Properties, PropertiesRef};
 
 #[repr(transparent)]
@@ -38,6 +50,144 @@ impl ContextRef {
             pw_sys::pw_context_update_properties(self.as_raw_ptr(), properties.as_raw_ptr());
         }
     }
+
+    /// Get the raw SPA-JSON text of a section of the context configuration.
+    ///
+    /// Only the section of the main configuration file is returned, see
+    /// [`conf_section_for_each()`](`Self::conf_section_for_each()`) to also get the
+    /// sections of configuration fragments.
+    ///
+    /// # Panics
+    /// If `section` contains a null byte.
+    pub fn conf_section_str(&self, section: &str) -> Option<&str> {
+        let section = CString::new(section).expect("Null byte in section parameter");
+
+        unsafe {
+            let res = pw_sys::pw_context_get_conf_section(self.as_raw_ptr(), section.as_ptr());
+            if res.is_null() {
+                None
+            } else {
+                CStr::from_ptr(res).to_str().ok()
+            }
+        }
+    }
+
+    /// Get a section of the context configuration, parsed as SPA-JSON.
+    ///
+    /// Returns `Ok(None)` if the section does not exist.
+    ///
+    /// # Panics
+    /// If `section` contains a null byte.
+    pub fn conf_section(&self, section: &str) -> Result<Option<Value>, Error> {
+        self.conf_section_str(section)
+            .map(Value::parse)
+            .transpose()
+            .map_err(Error::from)
+    }
+
+    /// Call `callback` for the section `section` of the main configuration file and
+    /// of every configuration fragment defining it.
+    ///
+    /// The callback receives the location of the file the section was found in, the name
+    /// of the section and its raw SPA-JSON text.
+    ///
+    /// # Panics
+    /// If `section` contains a null byte.
+    #[cfg(feature = "v0_3_45")]
+    pub fn conf_section_for_each<F>(&self, section: &str, callback: F)
+    where
+        F: FnMut(&str, &str, &str),
+    {
+        use libc::{c_char, c_int, c_void};
+
+        unsafe extern "C" fn section_cb<F: FnMut(&str, &str, &str)>(
+            data: *mut c_void,
+            location: *const c_char,
+            section: *const c_char,
+            str_: *const c_char,
+            len: usize,
+        ) -> c_int {
+            let callback = (data as *mut F).as_mut().unwrap();
+            let location = CStr::from_ptr(location).to_string_lossy();
+            let section = CStr::from_ptr(section).to_string_lossy();
+            let value = std::slice::from_raw_parts(str_.cast::<u8>(), len);
+            callback(&location, &section, &String::from_utf8_lossy(value));
+            0
+        }
+
+        let section = CString::new(section).expect("Null byte in section parameter");
+        let mut callback = callback;
+
+        unsafe {
+            pw_sys::pw_context_conf_section_for_each(
+                self.as_raw_ptr(),
+                section.as_ptr(),
+                Some(section_cb::<F>),
+                std::ptr::addr_of_mut!(callback).cast(),
+            );
+        }
+    }
+
+    /// Parse the [`MatchRules`] of a section of the context configuration,
+    /// such as `stream.rules` or `node.rules`.
+    ///
+    /// Rules from configuration fragments are included when supported by the library version.
+    ///
+    /// # Panics
+    /// If `section` contains a null byte.
+    pub fn conf_section_match_rules(&self, section: &str) -> Result<MatchRules, Error> {
+        let mut rules = MatchRules::default();
+
+        #[cfg(feature = "v0_3_45")]
+        {
+            let mut res = Ok(());
+            self.conf_section_for_each(section, |_location, _section, value| {
+                match Value::parse(value) {
+                    Ok(value) => rules.extend_from_value(&value),
+                    Err(e) => res = Err(e),
+                }
+            });
+            res?;
+        }
+        #[cfg(not(feature = "v0_3_45"))]
+        if let Some(value) = self.conf_section(section)? {
+            rules.extend_from_value(&value);
+        }
+
+        Ok(rules)
+    }
+
+    /// The core object exposing this context to clients, if any.
+    pub fn default_core(&self) -> Option<&ImplCoreRef> {
+        unsafe {
+            let core = pw_sys::pw_context_get_default_core(self.as_raw_ptr());
+            ptr::NonNull::new(core).map(|core| core.cast().as_ref())
+        }
+    }
+
+    /// Find the factory named `name` registered in this context.
+    ///
+    /// # Panics
+    /// If `name` contains a null byte.
+    pub fn find_factory(&self, name: &str) -> Option<&ImplFactoryRef> {
+        let name = CString::new(name).expect("Null byte in name parameter");
+
+        unsafe {
+            let factory = pw_sys::pw_context_find_factory(self.as_raw_ptr(), name.as_ptr());
+            ptr::NonNull::new(factory).map(|factory| factory.cast().as_ref())
+        }
+    }
+
+    /// The memory pool of the context, used by the objects it implements.
+    pub fn mempool(&self) -> &MemPoolRef {
+        unsafe {
+            let pool = pw_sys::pw_context_get_mempool(self.as_raw_ptr());
+            ptr::NonNull::new(pool)
+                .expect("context mempool is NULL")
+                .cast()
+                .as_ref()
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -90,7 +240,7 @@ impl Context {
 
         unsafe {
             let core = pw_sys::pw_context_connect(self.as_raw_ptr(), properties, 0);
-            let ptr = ptr::NonNull::new(core).ok_or(Error::CreationFailed)?;
+            let ptr = ptr::NonNull::new(core).ok_or_else(|| Error::Connect(Errno::last()))?;
 
             Ok(Core::from_ptr(ptr, self.clone()))
         }
@@ -102,13 +252,132 @@ impl Context {
         unsafe {
             let raw_fd = fd.into_raw_fd();
             let core = pw_sys::pw_context_connect_fd(self.as_raw_ptr(), raw_fd, properties, 0);
-            let ptr = ptr::NonNull::new(core).ok_or(Error::CreationFailed)?;
+            let ptr = ptr::NonNull::new(core).ok_or_else(|| Error::Connect(Errno::last()))?;
 
             Ok(Core::from_ptr(ptr, self.clone()))
         }
     }
 }
 
+impl Context {
+    /// Load a module into the local process.
+    ///
+    /// # Parameters
+    /// - `name`: the name of the module to load, such as `libpipewire-module-loopback`
+    /// - `args`: the arguments of the module, usually a SPA-JSON object
+    /// - `properties`: extra properties for the module
+    ///
+    /// # Panics
+    /// If `name` or `args` contain a null byte.
+    ///
+    /// # Returns
+    /// The loaded module, which is unloaded when dropped, or `Err(Error::LoadModule { .. })`
+    /// if the module could not be loaded.
+    pub fn load_module(
+        &self,
+        name: &str,
+        args: Option<&str>,
+        properties: Option<Properties>,
+    ) -> Result<ImplModule, Error> {
+        let name_c = CString::new(name).expect("Null byte in name parameter");
+        let args = args.map(|args| CString::new(args).expect("Null byte in args parameter"));
+        let properties = properties.map_or(ptr::null_mut(), |p| p.into_raw());
+
+        let module = unsafe {
+            pw_sys::pw_context_load_module(
+                self.as_raw_ptr(),
+                name_c.as_ptr(),
+                args.as_ref().map_or(ptr::null(), |args| args.as_ptr()),
+                properties,
+            )
+        };
+        let module = ptr::NonNull::new(module).ok_or_else(|| Error::LoadModule {
+            name: name.to_owned(),
+            errno: Errno::last(),
+        })?;
+
+        Ok(ImplModule::from_ptr(module, self.clone()))
+    }
+
+    /// Create a node in the local process, implemented by a SPA plugin.
+    ///
+    /// The plugin is loaded with the factory `factory_name`, such as `support.null-audio-sink`
+    /// or `api.alsa.pcm.sink`, and is configured with `properties`, which are also used
+    /// as the properties of the node.
+    ///
+    /// The node needs to be [registered](`crate::impl_::ImplNodeRef::register`) to be visible
+    /// to clients, and [activated](`crate::impl_::ImplNodeRef::set_active`) to process data.
+    ///
+    /// # Panics
+    /// If `factory_name` contains a null byte.
+    pub fn create_spa_node(
+        &self,
+        factory_name: &str,
+        properties: Properties,
+    ) -> Result<ImplNode, Error> {
+        let factory_name_c =
+            CString::new(factory_name).expect("Null byte in factory_name parameter");
+
+        let handle = unsafe {
+            pw_sys::pw_context_load_spa_handle(
+                self.as_raw_ptr(),
+                factory_name_c.as_ptr(),
+                properties.dict().as_raw_ptr(),
+            )
+        };
+        let handle = ptr::NonNull::new(handle).ok_or_else(|| Error::LoadSpaHandle {
+            factory: factory_name.to_owned(),
+            errno: Errno::last(),
+        })?;
+        let handle = SpaHandle::from_ptr(handle);
+
+        let iface_type = CStr::from_bytes_with_nul(spa_sys::SPA_TYPE_INTERFACE_Node).unwrap();
+        let spa_node = handle.interface(iface_type)?;
+
+        let node =
+            unsafe { pw_sys::pw_context_create_node(self.as_raw_ptr(), properties.into_raw(), 0) };
+        let ptr = ptr::NonNull::new(node).ok_or(Error::CreationFailed)?;
+        let node = ImplNode::from_ptr(ptr, Some(handle), self.clone());
+
+        let res = unsafe {
+            pw_sys::pw_impl_node_set_implementation(ptr.as_ptr(), spa_node.cast().as_ptr())
+        };
+        SpaResult::from_c(res).into_sync_result()?;
+
+        Ok(node)
+    }
+
+    /// Create a link between two ports of nodes implemented in the local process.
+    ///
+    /// The format negotiated on the link can be restricted with `format_filter`.
+    /// The link needs to be [registered](`crate::impl_::ImplLinkRef::register`) to be visible
+    /// to clients.
+    pub fn create_link(
+        &self,
+        output: &ImplPortRef,
+        input: &ImplPortRef,
+        format_filter: Option<&Pod>,
+        properties: Option<Properties>,
+    ) -> Result<ImplLink, Error> {
+        let format_filter = format_filter.map_or(ptr::null_mut(), |filter| filter.as_raw_ptr());
+        let properties = properties.map_or(ptr::null_mut(), |p| p.into_raw());
+
+        let link = unsafe {
+            pw_sys::pw_context_create_link(
+                self.as_raw_ptr(),
+                output.as_raw_ptr(),
+                input.as_raw_ptr(),
+                format_filter,
+                properties,
+                0,
+            )
+        };
+        let link = ptr::NonNull::new(link).ok_or(Error::CreationFailed)?;
+
+        Ok(ImplLink::from_ptr(link, self.clone()))
+    }
+}
+
 impl std::convert::AsRef<ContextRef> for Context {
     fn as_ref(&self) -> &ContextRef {
         self.deref()
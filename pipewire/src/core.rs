@@ -5,17 +5,22 @@ use bitflags::bitflags;
 use libc::{c_char, c_void};
 use std::{
     ffi::{CStr, CString},
+    os::fd::BorrowedFd,
     rc::Rc,
 };
 use std::{fmt, mem, ptr};
 use std::{ops::Deref, pin::Pin};
 
 use crate::{
+    loop_::LoopRef,
+    mem::{MemBlockFlags, MemPoolRef},
     proxy::{Proxy, ProxyT},
     registry::Registry,
-    Error,
+    reply::{self, Reply},
+    Error, RemoteError,
 };
 use spa::{
+    buffer::DataType,
     spa_interface_call_method,
     utils::result::{AsyncSeq, SpaResult},
 };
@@ -43,6 +48,18 @@ impl CoreRef {
         }
     }
 
+    /// The memory pool of the core, holding the memory shared by the server
+    /// with `add_mem` events.
+    pub fn mempool(&self) -> &MemPoolRef {
+        unsafe {
+            let pool = pw_sys::pw_core_get_mempool(self.as_raw_ptr());
+            ptr::NonNull::new(pool)
+                .expect("core mempool is NULL")
+                .cast()
+                .as_ref()
+        }
+    }
+
     pub fn get_registry(&self) -> Result<Registry, Error> {
         let registry = unsafe {
             spa_interface_call_method!(
@@ -73,6 +90,56 @@ impl CoreRef {
         Ok(res)
     }
 
+    /// Awaitable version of [`sync()`](`Self::sync()`).
+    ///
+    /// The returned [`Reply`] resolves once the server processed all previous method calls,
+    /// or fails if the server reports an error for the sync or the connection is lost.
+    pub fn sync_async(&self) -> Result<Reply<()>, Error> {
+        let pending = self.sync(0)?;
+        let mut reply = Reply::new(pending, ());
+
+        let done_state = reply.state();
+        let error_state = reply.state();
+        let listener = self
+            .add_listener_local()
+            .done(move |id, seq| {
+                if id == PW_ID_CORE && seq == pending {
+                    done_state.borrow_mut().complete();
+                }
+            })
+            .error(move |error| {
+                if reply::fails_pending(error) {
+                    error_state.borrow_mut().fail(Error::Remote(error.clone()));
+                }
+            })
+            .register();
+        reply.hold(listener);
+
+        Ok(reply)
+    }
+
+    /// Block until the server processed all previous method calls.
+    ///
+    /// This iterates the main loop of the context the core belongs to until the reply to a
+    /// [`sync()`](`Self::sync()`) is received, dispatching all events received in the meantime.
+    /// It must not be called from a callback running on that loop, nor while the loop is run
+    /// by a [`ThreadLoop`](`crate::thread_loop::ThreadLoop`).
+    pub fn roundtrip(&self) -> Result<(), Error> {
+        let reply = self.sync_async()?;
+        reply.wait(self)
+    }
+
+    pub(crate) fn main_loop(&self) -> &LoopRef {
+        unsafe {
+            let context = pw_sys::pw_core_get_context(self.as_raw_ptr());
+            let loop_ = pw_sys::pw_context_get_main_loop(context);
+            ptr::NonNull::new(loop_)
+                .expect("context main loop is NULL")
+                .cast::<LoopRef>()
+                .as_ref()
+        }
+    }
+
     /// Create a new object on the PipeWire server from a factory.
     ///
     /// You will need specify what type you are expecting to be constructed by either using type inference or the
@@ -88,7 +155,7 @@ impl CoreRef {
     /// # Returns
     /// One of:
     /// - `Ok(P)` on success, where `P` is the newly created object
-    /// - `Err(Error::CreationFailed)` if the object could not be created
+    /// - `Err(Error::CreateObject { .. })` if the object could not be created
     /// - `Err(Error::WrongProxyType)` if the created type does not match the type `P` that the user is trying to create
     ///
     /// # Examples
@@ -153,7 +220,10 @@ impl CoreRef {
             )
         };
 
-        let ptr = ptr::NonNull::new(res.cast()).ok_or(Error::CreationFailed)?;
+        let ptr = ptr::NonNull::new(res.cast()).ok_or_else(|| Error::CreateObject {
+            factory: factory_name.to_string_lossy().into_owned(),
+            errno: nix::errno::Errno::last(),
+        })?;
 
         Proxy::new(ptr).downcast().map_err(|(_, e)| e)
     }
@@ -174,6 +244,108 @@ impl CoreRef {
         let res = SpaResult::from_c(res).into_async_result()?;
         Ok(res)
     }
+
+    /// Export an object implemented in the local process, such as a [`spa::node::Node`]
+    /// or a [`spa::device::Device`],
+    /// making it available to the server and its other clients.
+    ///
+    /// The object stays exported until the returned [`Exported`] is dropped.
+    pub fn export<T: Export>(
+        &self,
+        object: T,
+        properties: Option<&spa::utils::dict::DictRef>,
+    ) -> Result<Exported<T>, Error> {
+        let type_ = object.type_();
+        let properties = properties.map_or(ptr::null(), |props| props.as_raw_ptr().cast_const());
+
+        let proxy = unsafe {
+            pw_sys::pw_core_export(
+                self.as_raw_ptr(),
+                type_.as_ptr(),
+                properties,
+                object.as_object_ptr(),
+                0,
+            )
+        };
+        let proxy = ptr::NonNull::new(proxy).ok_or_else(|| Error::Export {
+            type_: type_.to_string_lossy().into_owned(),
+            errno: nix::errno::Errno::last(),
+        })?;
+
+        Ok(Exported {
+            proxy: Proxy::new(proxy),
+            object,
+        })
+    }
+}
+
+/// An object implemented in the local process that can be [exported](`CoreRef::export`).
+///
+/// # Safety
+/// [`as_object_ptr()`](`Self::as_object_ptr`) must return a pointer to a valid object
+/// implementing the interface named by [`type_()`](`Self::type_`), such as a `struct spa_node`
+/// for `PipeWire:Interface:Node`.
+/// The object must stay valid and must not move for as long as the value implementing this trait
+/// is alive, as the server calls its methods until the export is dropped.
+pub unsafe trait Export {
+    /// The type of the interface the object is exported as, such as `PipeWire:Interface:Node`.
+    fn type_(&self) -> &'static CStr;
+
+    /// A pointer to the object implementing the interface, such as a `struct spa_node`.
+    fn as_object_ptr(&self) -> *mut c_void;
+}
+
+// SAFETY: the `spa_node` is pinned in a box owned by the `Node`, so it does not move.
+unsafe impl<T: spa::node::SpaNode> Export for spa::node::Node<T> {
+    fn type_(&self) -> &'static CStr {
+        CStr::from_bytes_with_nul(pw_sys::PW_TYPE_INTERFACE_Node).unwrap()
+    }
+
+    fn as_object_ptr(&self) -> *mut c_void {
+        self.as_raw_ptr().cast()
+    }
+}
+
+// SAFETY: the `spa_device` is pinned in a box owned by the `Device`, so it does not move.
+unsafe impl<T: spa::device::SpaDevice> Export for spa::device::Device<T> {
+    fn type_(&self) -> &'static CStr {
+        CStr::from_bytes_with_nul(pw_sys::PW_TYPE_INTERFACE_Device).unwrap()
+    }
+
+    fn as_object_ptr(&self) -> *mut c_void {
+        self.as_raw_ptr().cast()
+    }
+}
+
+/// An object exported with [`CoreRef::export`].
+///
+/// The object is removed from the server when this is dropped.
+pub struct Exported<T: Export> {
+    // Dropped first, as the object must outlive the proxy exporting it.
+    proxy: Proxy,
+    object: T,
+}
+
+impl<T: Export> Exported<T> {
+    /// The proxy representing the exported object on the server.
+    ///
+    /// It can be used to get the id of the object, or to add a listener to it.
+    pub fn proxy(&self) -> &Proxy {
+        &self.proxy
+    }
+
+    pub fn object(&self) -> &T {
+        &self.object
+    }
+}
+
+impl<T: Export + fmt::Debug> fmt::Debug for Exported<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Exported")
+            .field("proxy", &self.proxy)
+            .field("object", &self.object)
+            .finish()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -225,8 +397,11 @@ struct ListenerLocalCallbacks {
     info: Option<Box<dyn Fn(&Info)>>,
     done: Option<Box<dyn Fn(u32, AsyncSeq)>>,
     #[allow(clippy::type_complexity)]
-    error: Option<Box<dyn Fn(u32, i32, i32, &str)>>, // TODO: return a proper Error enum?
-                                                     // TODO: ping, remove_id, bound_id, add_mem, remove_mem
+    error: Option<Box<dyn Fn(&RemoteError)>>,
+    #[allow(clippy::type_complexity)]
+    add_mem: Option<Box<dyn Fn(u32, DataType, BorrowedFd, MemBlockFlags)>>,
+    remove_mem: Option<Box<dyn Fn(u32)>>,
+    // TODO: ping, remove_id, bound_id
 }
 
 pub struct ListenerLocalBuilder<'a> {
@@ -243,6 +418,8 @@ pub struct Listener {
     data: Box<ListenerLocalCallbacks>,
 }
 
+impl crate::proxy::Listener for Listener {}
+
 impl Listener {
     pub fn unregister(self) {
         // Consuming the listener will call drop()
@@ -274,15 +451,43 @@ impl<'a> ListenerLocalBuilder<'a> {
         self
     }
 
+    /// Set the callback called when the remote reports an error.
+    ///
+    /// Use [`RemoteError::is_reply_to`] to match the error against the [`AsyncSeq`]
+    /// returned by the method call that caused it.
     #[must_use]
     pub fn error<F>(mut self, error: F) -> Self
     where
-        F: Fn(u32, i32, i32, &str) + 'static,
+        F: Fn(&RemoteError) + 'static,
     {
         self.cbs.error = Some(Box::new(error));
         self
     }
 
+    /// Set the callback called when the server shares memory with the client,
+    /// passing the id, type, fd and flags of the memory.
+    ///
+    /// The memory is imported into the [`mempool`](`CoreRef::mempool`) of the core before
+    /// the callback is called, so it can be mapped with [`MemPoolRef::map_id`].
+    #[must_use]
+    pub fn add_mem<F>(mut self, add_mem: F) -> Self
+    where
+        F: Fn(u32, DataType, BorrowedFd, MemBlockFlags) + 'static,
+    {
+        self.cbs.add_mem = Some(Box::new(add_mem));
+        self
+    }
+
+    /// Set the callback called when the server removes the memory with the given id.
+    #[must_use]
+    pub fn remove_mem<F>(mut self, remove_mem: F) -> Self
+    where
+        F: Fn(u32) + 'static,
+    {
+        self.cbs.remove_mem = Some(Box::new(remove_mem));
+        self
+    }
+
     #[must_use]
     pub fn register(self) -> Listener {
         unsafe extern "C" fn core_events_info(
@@ -307,8 +512,30 @@ impl<'a> ListenerLocalBuilder<'a> {
             message: *const c_char,
         ) {
             let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
-            let message = CStr::from_ptr(message).to_str().unwrap();
-            callbacks.error.as_ref().unwrap()(id, seq, res, message);
+            let message = CStr::from_ptr(message).to_string_lossy();
+            let error = RemoteError::from_raw(id, seq, res, &message);
+            callbacks.error.as_ref().unwrap()(&error);
+        }
+
+        unsafe extern "C" fn core_events_add_mem(
+            data: *mut c_void,
+            id: u32,
+            type_: u32,
+            fd: i32,
+            flags: u32,
+        ) {
+            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
+            callbacks.add_mem.as_ref().unwrap()(
+                id,
+                DataType::from_raw(type_),
+                BorrowedFd::borrow_raw(fd),
+                MemBlockFlags::from_bits_retain(flags),
+            );
+        }
+
+        unsafe extern "C" fn core_events_remove_mem(data: *mut c_void, id: u32) {
+            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
+            callbacks.remove_mem.as_ref().unwrap()(id);
         }
 
         let e = unsafe {
@@ -324,6 +551,12 @@ impl<'a> ListenerLocalBuilder<'a> {
             if self.cbs.error.is_some() {
                 e.error = Some(core_events_error);
             }
+            if self.cbs.add_mem.is_some() {
+                e.add_mem = Some(core_events_add_mem);
+            }
+            if self.cbs.remove_mem.is_some() {
+                e.remove_mem = Some(core_events_remove_mem);
+            }
 
             e
         };
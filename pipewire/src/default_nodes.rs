@@ -0,0 +1,420 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Tracking of the default nodes.
+//!
+//! The session manager stores the default nodes in the [`Metadata`] object named `default`,
+//! as JSON values such as `{ "name": "alsa_output.pci-0000_00_1f.3.analog-stereo" }`
+//! on the [`PW_ID_CORE`] subject.
+//! The `default.configured.*` keys hold the defaults chosen by the user, while the other keys
+//! hold the defaults actually in use, which the session manager picks among the available nodes.
+//!
+//! [`DefaultNodes`] watches that metadata object and resolves the default node names
+//! to the ids of the nodes announced by the registry.
+//!
+//! ```no_run
+//! use pipewire::{context::Context, default_nodes::DefaultNodes, main_loop::MainLoop};
+//!
+//! let mainloop = MainLoop::new(None).expect("Failed to create main loop");
+//! let context = Context::new(&mainloop).expect("Failed to create context");
+//! let core = context.connect(None).expect("Failed to connect to remote");
+//!
+//! let defaults = DefaultNodes::new(&core).expect("Failed to track default nodes");
+//! let _listener = defaults
+//!     .add_listener_local()
+//!     .changed(|key, name, id| println!("{}: {:?} ({:?})", key, name, id))
+//!     .register();
+//!
+//! mainloop.run();
+//! ```
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    fmt,
+    rc::Rc,
+};
+
+use spa::utils::{dict::DictRef, json::Value, result::AsyncSeq};
+
+use crate::{
+    core::{CoreRef, PW_ID_CORE},
+    keys,
+    metadata::{Metadata, MetadataListener, MetadataValue},
+    registry::{self, GlobalObject, Registry},
+    types::ObjectType,
+    utils::LocalListeners,
+    Error,
+};
+
+/// The name of the metadata object holding the default nodes.
+pub const METADATA_NAME: &str = "default";
+
+/// A default node key of the `default` metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DefaultKey {
+    AudioSink,
+    AudioSource,
+    VideoSource,
+    ConfiguredAudioSink,
+    ConfiguredAudioSource,
+    ConfiguredVideoSource,
+}
+
+impl DefaultKey {
+    pub const ALL: [Self; 6] = [
+        Self::AudioSink,
+        Self::AudioSource,
+        Self::VideoSource,
+        Self::ConfiguredAudioSink,
+        Self::ConfiguredAudioSource,
+        Self::ConfiguredVideoSource,
+    ];
+
+    /// The metadata key, e.g. `default.audio.sink`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::AudioSink => "default.audio.sink",
+            Self::AudioSource => "default.audio.source",
+            Self::VideoSource => "default.video.source",
+            Self::ConfiguredAudioSink => "default.configured.audio.sink",
+            Self::ConfiguredAudioSource => "default.configured.audio.source",
+            Self::ConfiguredVideoSource => "default.configured.video.source",
+        }
+    }
+
+    /// The default matching the metadata key `key`, if it is one.
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| k.as_str() == key)
+    }
+
+    /// The key holding the default configured by the user for the same kind of node.
+    pub fn configured(&self) -> Self {
+        match self {
+            Self::AudioSink | Self::ConfiguredAudioSink => Self::ConfiguredAudioSink,
+            Self::AudioSource | Self::ConfiguredAudioSource => Self::ConfiguredAudioSource,
+            Self::VideoSource | Self::ConfiguredVideoSource => Self::ConfiguredVideoSource,
+        }
+    }
+}
+
+impl fmt::Display for DefaultKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Parse a default node value, returning the node name.
+fn parse_value(value: &MetadataValue) -> Option<String> {
+    value.as_json()?.get("name")?.as_str().map(str::to_owned)
+}
+
+/// Format a default node value for the node `name`.
+fn format_value(name: &str) -> MetadataValue {
+    MetadataValue::Json(Value::Object(vec![("name".to_owned(), Value::from(name))]))
+}
+
+type Resolved = (Option<String>, Option<u32>);
+
+type ChangedCallback = dyn Fn(DefaultKey, Option<&str>, Option<u32>);
+
+#[derive(Default)]
+struct ListenerLocalCallbacks {
+    changed: Option<Box<ChangedCallback>>,
+}
+
+struct State {
+    // Needs to be dropped before the metadata it is registered on.
+    listener: Option<MetadataListener>,
+    metadata: Option<(u32, Metadata)>,
+    names: BTreeMap<DefaultKey, String>,
+    nodes: HashMap<u32, String>,
+    resolved: BTreeMap<DefaultKey, Resolved>,
+}
+
+impl State {
+    fn node_id(&self, name: &str) -> Option<u32> {
+        self.nodes
+            .iter()
+            .filter(|(_, node)| *node == name)
+            .map(|(id, _)| *id)
+            .min()
+    }
+
+    fn resolve(&self, key: DefaultKey) -> Resolved {
+        let name = self.names.get(&key).cloned();
+        let id = name.as_deref().and_then(|name| self.node_id(name));
+        (name, id)
+    }
+}
+
+struct Inner {
+    registry: Registry,
+    state: RefCell<State>,
+    listeners: LocalListeners<ListenerLocalCallbacks>,
+}
+
+impl Inner {
+    fn global(self: &Rc<Self>, global: &GlobalObject<&DictRef>) {
+        let props = global.props;
+        match global.type_ {
+            ObjectType::Node => {
+                if let Some(name) = props.and_then(|props| props.get(*keys::NODE_NAME)) {
+                    self.state
+                        .borrow_mut()
+                        .nodes
+                        .insert(global.id, name.to_owned());
+                }
+            }
+            ObjectType::Metadata => {
+                if props.and_then(|props| props.get(*keys::METADATA_NAME)) != Some(METADATA_NAME)
+                    || self.state.borrow().metadata.is_some()
+                {
+                    return;
+                }
+
+                let Ok(metadata) = self.registry.bind::<Metadata, _>(global) else {
+                    return;
+                };
+                let this = Rc::downgrade(self);
+                let listener = metadata
+                    .add_listener_local()
+                    .property_value(move |subject, key, value| {
+                        if let Some(this) = this.upgrade() {
+                            this.property(subject, key, value);
+                        }
+                    })
+                    .register();
+
+                let mut state = self.state.borrow_mut();
+                state.listener = Some(listener);
+                state.metadata = Some((global.id, metadata));
+            }
+            _ => return,
+        }
+
+        self.emit_changes();
+    }
+
+    fn global_remove(&self, id: u32) {
+        {
+            let mut state = self.state.borrow_mut();
+            if state.nodes.remove(&id).is_none() {
+                if !matches!(state.metadata, Some((metadata_id, _)) if metadata_id == id) {
+                    return;
+                }
+                state.listener = None;
+                state.metadata = None;
+                state.names.clear();
+            }
+        }
+
+        self.emit_changes();
+    }
+
+    fn property(&self, subject: u32, key: Option<&str>, value: Option<&MetadataValue>) {
+        if subject != PW_ID_CORE {
+            return;
+        }
+
+        {
+            let mut state = self.state.borrow_mut();
+            match key {
+                None => state.names.clear(),
+                Some(key) => {
+                    let Some(key) = DefaultKey::from_key(key) else {
+                        return;
+                    };
+                    match value.and_then(parse_value) {
+                        Some(name) => state.names.insert(key, name),
+                        None => state.names.remove(&key),
+                    };
+                }
+            }
+        }
+
+        self.emit_changes();
+    }
+
+    /// Notify the listeners of the defaults whose name or node changed.
+    fn emit_changes(&self) {
+        let changes: Vec<_> = {
+            let mut state = self.state.borrow_mut();
+            DefaultKey::ALL
+                .into_iter()
+                .filter_map(|key| {
+                    let resolved = state.resolve(key);
+                    let previous = state.resolved.insert(key, resolved.clone());
+                    (previous.unwrap_or_default() != resolved).then_some((key, resolved))
+                })
+                .collect()
+        };
+        if changes.is_empty() {
+            return;
+        }
+
+        for (key, (name, id)) in changes {
+            self.listeners.emit(|cbs| {
+                if let Some(changed) = &cbs.changed {
+                    changed(key, name.as_deref(), id);
+                }
+            });
+        }
+    }
+}
+
+/// Tracks the default nodes stored in the `default` metadata object.
+///
+/// See the [module documentation](`self`) for details.
+pub struct DefaultNodes {
+    // Needs to be dropped before the registry it is registered on.
+    _listener: registry::Listener,
+    inner: Rc<Inner>,
+}
+
+impl DefaultNodes {
+    /// Start tracking the default nodes of the remote of `core`.
+    pub fn new(core: &CoreRef) -> Result<Self, Error> {
+        let inner = Rc::new(Inner {
+            registry: core.get_registry()?,
+            state: RefCell::new(State {
+                listener: None,
+                metadata: None,
+                names: BTreeMap::new(),
+                nodes: HashMap::new(),
+                resolved: BTreeMap::new(),
+            }),
+            listeners: LocalListeners::default(),
+        });
+
+        let listener = inner
+            .registry
+            .add_listener_local()
+            .global({
+                let inner = Rc::downgrade(&inner);
+                move |global| {
+                    if let Some(inner) = inner.upgrade() {
+                        inner.global(global);
+                    }
+                }
+            })
+            .global_remove({
+                let inner = Rc::downgrade(&inner);
+                move |id| {
+                    if let Some(inner) = inner.upgrade() {
+                        inner.global_remove(id);
+                    }
+                }
+            })
+            .register();
+
+        Ok(Self {
+            _listener: listener,
+            inner,
+        })
+    }
+
+    #[must_use]
+    pub fn add_listener_local(&self) -> DefaultNodesListenerLocalBuilder {
+        DefaultNodesListenerLocalBuilder {
+            default_nodes: self,
+            cbs: ListenerLocalCallbacks::default(),
+        }
+    }
+
+    /// The name of the default node for `key`.
+    pub fn name(&self, key: DefaultKey) -> Option<String> {
+        self.inner.state.borrow().names.get(&key).cloned()
+    }
+
+    /// The id of the default node for `key`, if a node with its name exists.
+    pub fn node_id(&self, key: DefaultKey) -> Option<u32> {
+        self.inner.state.borrow().resolve(key).1
+    }
+
+    /// Set the default node for `key` to the node named `name`.
+    ///
+    /// Applications choosing a default for the user should set the
+    /// [configured](`DefaultKey::configured`) key, from which the session manager
+    /// picks the default in use.
+    pub fn set(&self, key: DefaultKey, name: &str) -> Result<AsyncSeq, Error> {
+        self.metadata(|metadata| metadata.set(PW_ID_CORE, key.as_str(), &format_value(name)))
+    }
+
+    /// Remove the default node for `key`.
+    pub fn unset(&self, key: DefaultKey) -> Result<AsyncSeq, Error> {
+        self.metadata(|metadata| metadata.remove(PW_ID_CORE, key.as_str()))
+    }
+
+    fn metadata<T>(&self, f: impl FnOnce(&Metadata) -> Result<T, Error>) -> Result<T, Error> {
+        let state = self.inner.state.borrow();
+        let (_, metadata) = state
+            .metadata
+            .as_ref()
+            .ok_or_else(|| Error::MetadataNotFound(METADATA_NAME.to_owned()))?;
+
+        f(metadata)
+    }
+}
+
+pub struct DefaultNodesListenerLocalBuilder<'a> {
+    default_nodes: &'a DefaultNodes,
+    cbs: ListenerLocalCallbacks,
+}
+
+/// A listener of the changes of [`DefaultNodes`], removed when dropped.
+pub struct DefaultNodesListener {
+    _cbs: Rc<ListenerLocalCallbacks>,
+}
+
+impl<'a> DefaultNodesListenerLocalBuilder<'a> {
+    /// Called with the new node name and id when the default for a key changed,
+    /// or when the node it names appeared or was removed.
+    #[must_use]
+    pub fn changed<F>(mut self, changed: F) -> Self
+    where
+        F: Fn(DefaultKey, Option<&str>, Option<u32>) + 'static,
+    {
+        self.cbs.changed = Some(Box::new(changed));
+        self
+    }
+
+    #[must_use]
+    pub fn register(self) -> DefaultNodesListener {
+        DefaultNodesListener {
+            _cbs: self.default_nodes.inner.listeners.register(self.cbs),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys() {
+        for key in DefaultKey::ALL {
+            assert_eq!(DefaultKey::from_key(key.as_str()), Some(key));
+        }
+        assert_eq!(DefaultKey::from_key("default.audio"), None);
+        assert_eq!(
+            DefaultKey::AudioSink.configured(),
+            DefaultKey::ConfiguredAudioSink
+        );
+    }
+
+    #[test]
+    fn values() {
+        let parse = |value| parse_value(&MetadataValue::parse(Some(MetadataValue::JSON), value));
+        assert_eq!(
+            parse(r#"{ "name": "alsa_output.analog-stereo" }"#).as_deref(),
+            Some("alsa_output.analog-stereo")
+        );
+        assert_eq!(parse(r#"{ "other": 1 }"#), None);
+        assert_eq!(parse("not json {"), None);
+
+        let value = format_value(r#"a "quoted" name"#);
+        assert_eq!(value.to_string(), r#"{"name": "a \"quoted\" name"}"#);
+        assert_eq!(parse_value(&value).as_deref(), Some(r#"a "quoted" name"#));
+    }
+}
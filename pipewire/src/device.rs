@@ -9,9 +9,16 @@ use std::{pin::Pin, ptr};
 
 use crate::{
     proxy::{Listener, Proxy, ProxyT},
+    reply::{self, Param, Reply},
     types::ObjectType,
+    Error,
+};
+use spa::{
+    param::device::{Profile, Route},
+    pod::{Object, Pod},
+    spa_interface_call_method,
+    utils::result::{AsyncSeq, SpaResult},
 };
-use spa::{pod::Pod, spa_interface_call_method};
 
 #[derive(Debug)]
 pub struct Device {
@@ -31,17 +38,19 @@ impl Device {
     /// Subscribe to parameter changes
     ///
     /// Automatically emit `param` events for the given ids when they are changed
-    // FIXME: Return result?
-    pub fn subscribe_params(&self, ids: &[spa::param::ParamType]) {
-        unsafe {
+    pub fn subscribe_params(&self, ids: &[spa::param::ParamType]) -> Result<AsyncSeq, Error> {
+        let res = unsafe {
             spa_interface_call_method!(
                 self.proxy.as_ptr(),
                 pw_sys::pw_device_methods,
                 subscribe_params,
                 ids.as_ptr() as *mut _,
                 ids.len().try_into().unwrap()
-            );
-        }
+            )
+        };
+
+        let res = SpaResult::from_c(res).into_async_result()?;
+        Ok(res)
     }
 
     /// Enumerate device parameters
@@ -53,13 +62,20 @@ impl Device {
     /// `seq`: a sequence number to place in the reply \
     /// `id`: the parameter id to enum, or [`None`] to allow any id \
     /// `start`: the start index or 0 for the first param \
-    /// `num`: the maximum number of params to retrieve ([`u32::MAX`] may be used to retrieve all params)
-    // FIXME: Add filter parameter
-    // FIXME: Return result?
-    pub fn enum_params(&self, seq: i32, id: Option<spa::param::ParamType>, start: u32, num: u32) {
+    /// `num`: the maximum number of params to retrieve ([`u32::MAX`] may be used to retrieve all params) \
+    /// `filter`: a param to filter the enumerated params with, or [`None`] to retrieve all params
+    pub fn enum_params(
+        &self,
+        seq: i32,
+        id: Option<spa::param::ParamType>,
+        start: u32,
+        num: u32,
+        filter: Option<&Pod>,
+    ) -> Result<AsyncSeq, Error> {
         let id = id.map(|id| id.as_raw()).unwrap_or(crate::constants::ID_ANY);
+        let filter = filter.map_or(std::ptr::null(), |filter| filter.as_raw_ptr().cast_const());
 
-        unsafe {
+        let res = unsafe {
             spa_interface_call_method!(
                 self.proxy.as_ptr(),
                 pw_sys::pw_device_methods,
@@ -68,13 +84,110 @@ impl Device {
                 id,
                 start,
                 num,
-                std::ptr::null()
-            );
-        }
+                filter
+            )
+        };
+
+        let res = SpaResult::from_c(res).into_async_result()?;
+        Ok(res)
     }
 
-    pub fn set_param(&self, id: spa::param::ParamType, flags: u32, param: &Pod) {
-        unsafe {
+    /// Enumerate device parameters and collect the replies
+    ///
+    /// Same as [`enum_params()`](`Self::enum_params()`), but returns a [`Reply`] resolving
+    /// to all the params emitted for this call once the enumeration is finished.
+    pub fn enum_params_async(
+        &self,
+        id: Option<spa::param::ParamType>,
+        start: u32,
+        num: u32,
+        filter: Option<&Pod>,
+    ) -> Result<Reply<Vec<Param>>, Error> {
+        let seq = self.enum_params(0, id, start, num, filter)?;
+
+        let mut reply = reply::track(&self.proxy, seq, Vec::new())?;
+        let state = reply.state();
+        let listener = self
+            .add_listener_local()
+            .param(move |param_seq, id, index, next, param| {
+                if let (true, Some(param)) = (param_seq == seq.raw(), param) {
+                    state
+                        .borrow_mut()
+                        .update(|params| params.push(Param::new(id, index, next, param)));
+                }
+            })
+            .register();
+        reply.hold(listener);
+
+        Ok(reply)
+    }
+
+    /// Enumerate the profiles of the device
+    ///
+    /// Shorthand for [`enum_params()`](`Self::enum_params()`) with the [`EnumProfile`](`spa::param::ParamType::EnumProfile`)
+    /// id, retrieving all params.
+    pub fn enum_profiles(&self, filter: Option<&Pod>) -> Result<AsyncSeq, Error> {
+        self.enum_params(
+            0,
+            Some(spa::param::ParamType::EnumProfile),
+            0,
+            u32::MAX,
+            filter,
+        )
+    }
+
+    /// Enumerate the routes of the device
+    ///
+    /// Shorthand for [`enum_params()`](`Self::enum_params()`) with the [`EnumRoute`](`spa::param::ParamType::EnumRoute`)
+    /// id, retrieving all params.
+    pub fn enum_routes(&self, filter: Option<&Pod>) -> Result<AsyncSeq, Error> {
+        self.enum_params(
+            0,
+            Some(spa::param::ParamType::EnumRoute),
+            0,
+            u32::MAX,
+            filter,
+        )
+    }
+
+    /// Set the active route of the device, to switch its port or change its hardware volume.
+    ///
+    /// The route must have its [`index`](`Route::index`) and [`device`](`Route::device`) set,
+    /// the volumes are set from its [`props`](`Route::props`).
+    pub fn set_route(&self, route: &Route) -> Result<AsyncSeq, Error> {
+        self.set_param_object(spa::param::ParamType::Route, Object::from(route))
+    }
+
+    /// Set the active profile of the device.
+    ///
+    /// Only the [`index`](`Profile::index`) and [`save`](`Profile::save`) fields
+    /// of the profile are used by the device.
+    pub fn set_profile(&self, profile: &Profile) -> Result<AsyncSeq, Error> {
+        self.set_param_object(spa::param::ParamType::Profile, Object::from(profile))
+    }
+
+    fn set_param_object(
+        &self,
+        id: spa::param::ParamType,
+        object: Object,
+    ) -> Result<AsyncSeq, Error> {
+        let bytes = crate::utils::serialize_param(id, object)?;
+        let pod = Pod::from_bytes(&bytes).expect("Serialized param is a valid pod");
+
+        self.set_param(id, 0, pod)
+    }
+
+    /// Set a parameter on the device
+    ///
+    /// Errors reported asynchronously by the remote are emitted through the proxy
+    /// `error` event with the returned [`AsyncSeq`].
+    pub fn set_param(
+        &self,
+        id: spa::param::ParamType,
+        flags: u32,
+        param: &Pod,
+    ) -> Result<AsyncSeq, Error> {
+        let res = unsafe {
             spa_interface_call_method!(
                 self.proxy.as_ptr(),
                 pw_sys::pw_device_methods,
@@ -82,8 +195,15 @@ impl Device {
                 id.as_raw(),
                 flags,
                 param.as_raw_ptr()
-            );
-        }
+            )
+        };
+
+        SpaResult::from_c(res)
+            .into_async_result()
+            .map_err(|e| Error::SetParam {
+                id,
+                errno: nix::errno::Errno::from_raw(e.errno() as i32),
+            })
     }
 }
 
@@ -211,6 +331,7 @@ impl AsRef<DeviceInfoRef> for DeviceInfo {
 
 bitflags! {
     #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct DeviceChangeMask: u64 {
         const PROPS = pw_sys::PW_DEVICE_CHANGE_MASK_PROPS as u64;
         const PARAMS = pw_sys::PW_DEVICE_CHANGE_MASK_PARAMS as u64;
@@ -0,0 +1,1152 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Export of the objects of the graph as JSON, in the format produced by `pw-dump`.
+//!
+//! A [`Dump`] binds every global announced by the registry, collects their info and params,
+//! and reports the objects that changed each time the server finished sending updates.
+//! The first report contains every object, so it can be used as a one-shot dump of the graph,
+//! later reports only contain the objects that changed since the previous one,
+//! like `pw-dump --monitor`.
+//!
+//! Removed objects are reported as `{ "id": <id>, "info": null }`.
+//!
+//! ```no_run
+//! use pipewire::{context::Context, dump::Dump, main_loop::MainLoop};
+//!
+//! let mainloop = MainLoop::new(None).expect("Failed to create main loop");
+//! let context = Context::new(&mainloop).expect("Failed to create context");
+//! let core = context.connect(None).expect("Failed to connect to remote");
+//!
+//! let dump = Dump::new(&core).expect("Failed to create dump");
+//! let _listener = dump
+//!     .add_listener_local()
+//!     .changed(|objects| println!("{:#}", objects))
+//!     .register();
+//!
+//! mainloop.run();
+//! ```
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, BTreeSet},
+    ffi::CStr,
+    rc::{Rc, Weak},
+};
+
+use bitflags::Flags;
+use spa::{
+    param::{ParamInfo, ParamInfoFlags, ParamType},
+    pod::{deserialize::PodDeserializer, ChoiceValue, Object, Pod, Value as PodValue, ValueArray},
+    utils::{dict::DictRef, json::Value, result::AsyncSeq, Choice, ChoiceEnum, Id},
+};
+
+use crate::{
+    client::Client,
+    core::CoreRef,
+    device::Device,
+    factory::Factory,
+    link::Link,
+    metadata::Metadata,
+    module::Module,
+    node::Node,
+    port::Port,
+    properties::Properties,
+    proxy::{Listener, Proxy, ProxyT},
+    registry::{self, GlobalObject, Registry},
+    snapshot::{
+        self, ClientInfoSnapshot, DeviceInfoSnapshot, FactoryInfoSnapshot, InfoProxy, InfoSnapshot,
+        LinkInfoSnapshot, LinkStateSnapshot, ModuleInfoSnapshot, NodeInfoSnapshot,
+        NodeStateSnapshot, PortInfoSnapshot, Props,
+    },
+    types::ObjectType,
+    utils::LocalListeners,
+    Error,
+};
+
+/// Convert a pod to JSON the way `pw-dump` does.
+///
+/// Object keys and ids are replaced by their short type names when they are known,
+/// and choices are converted to objects with the default value and the alternatives.
+/// Pods that cannot be parsed are converted to `null`.
+pub fn pod_to_json(pod: &Pod) -> Value {
+    match PodDeserializer::deserialize_any_from(pod.as_bytes()) {
+        Ok((_, value)) => value_to_json(&value, None),
+        Err(_) => Value::Null,
+    }
+}
+
+/// Find the type info of `type_` in `table`, if the table is known.
+fn find_type(table: *const spa_sys::spa_type_info, type_: u32) -> Option<&'static TypeInfo> {
+    if table.is_null() {
+        return None;
+    }
+
+    unsafe {
+        spa_sys::spa_debug_type_find(table, type_)
+            .cast::<TypeInfo>()
+            .as_ref()
+    }
+}
+
+#[repr(transparent)]
+struct TypeInfo(spa_sys::spa_type_info);
+
+impl TypeInfo {
+    /// The last component of the type name, e.g. `mediaType` for
+    /// `Spa:Pod:Object:Param:Format:mediaType`.
+    fn short_name(&self) -> Option<&str> {
+        let name = unsafe { CStr::from_ptr(self.0.name) }.to_str().ok()?;
+        name.rsplit(':').next()
+    }
+
+    fn values(&self) -> *const spa_sys::spa_type_info {
+        self.0.values
+    }
+}
+
+fn id_to_json(id: u32, info: Option<&TypeInfo>) -> Value {
+    info.and_then(|info| find_type(info.values(), id))
+        .and_then(TypeInfo::short_name)
+        .map_or_else(|| Value::from(i64::from(id)), Value::from)
+}
+
+fn float_to_json<T: Into<f64> + ToString>(value: T) -> Value {
+    // Formatting the value with its own precision avoids printing f32 rounding errors.
+    let string = value.to_string();
+    if value.into().is_finite() {
+        Value::Number(string)
+    } else {
+        Value::Null
+    }
+}
+
+fn choice_to_json<T>(choice: &Choice<T>, to_json: impl Fn(&T) -> Value) -> Value
+where
+    T: spa::pod::CanonicalFixedSizedPod,
+{
+    let members = match &choice.1 {
+        ChoiceEnum::None(value) => return to_json(value),
+        ChoiceEnum::Range { default, min, max } => vec![
+            ("default".to_owned(), to_json(default)),
+            ("min".to_owned(), to_json(min)),
+            ("max".to_owned(), to_json(max)),
+        ],
+        ChoiceEnum::Step {
+            default,
+            min,
+            max,
+            step,
+        } => vec![
+            ("default".to_owned(), to_json(default)),
+            ("min".to_owned(), to_json(min)),
+            ("max".to_owned(), to_json(max)),
+            ("step".to_owned(), to_json(step)),
+        ],
+        ChoiceEnum::Enum {
+            default,
+            alternatives: values,
+        }
+        | ChoiceEnum::Flags {
+            default,
+            flags: values,
+        } => std::iter::once(("default".to_owned(), to_json(default)))
+            .chain(
+                values
+                    .iter()
+                    .enumerate()
+                    .map(|(i, value)| (format!("alt{}", i + 1), to_json(value))),
+            )
+            .collect(),
+    };
+
+    Value::Object(members)
+}
+
+fn fraction_to_json(fraction: &spa::utils::Fraction) -> Value {
+    Value::Object(vec![
+        ("num".to_owned(), Value::from(i64::from(fraction.num))),
+        ("denom".to_owned(), Value::from(i64::from(fraction.denom))),
+    ])
+}
+
+fn rectangle_to_json(rectangle: &spa::utils::Rectangle) -> Value {
+    Value::Object(vec![
+        ("width".to_owned(), Value::from(i64::from(rectangle.width))),
+        (
+            "height".to_owned(),
+            Value::from(i64::from(rectangle.height)),
+        ),
+    ])
+}
+
+fn object_to_json(object: &Object) -> Value {
+    let keys = find_type(unsafe { spa_sys::spa_types }, object.type_).map(TypeInfo::values);
+
+    Value::Object(
+        object
+            .properties
+            .iter()
+            .map(|property| {
+                let info = keys.and_then(|keys| find_type(keys, property.key));
+                let key = info
+                    .and_then(TypeInfo::short_name)
+                    .map_or_else(|| property.key.to_string(), str::to_owned);
+                (key, value_to_json(&property.value, info))
+            })
+            .collect(),
+    )
+}
+
+/// Convert `value`, using `info`, the type info of the object property holding it,
+/// to name ids.
+fn value_to_json(value: &PodValue, info: Option<&TypeInfo>) -> Value {
+    fn array<T>(values: &[T], to_json: impl Fn(&T) -> Value) -> Value {
+        Value::Array(values.iter().map(to_json).collect())
+    }
+
+    match value {
+        PodValue::None | PodValue::Pointer(..) => Value::Null,
+        PodValue::Bool(b) => Value::from(*b),
+        PodValue::Id(Id(id)) => id_to_json(*id, info),
+        PodValue::Int(i) => Value::from(i64::from(*i)),
+        PodValue::Long(l) => Value::from(*l),
+        PodValue::Float(f) => float_to_json(*f),
+        PodValue::Double(d) => float_to_json(*d),
+        PodValue::String(s) => Value::from(s.as_str()),
+        PodValue::Bytes(bytes) => Value::from(
+            bytes
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<String>(),
+        ),
+        PodValue::Rectangle(rectangle) => rectangle_to_json(rectangle),
+        PodValue::Fraction(fraction) => fraction_to_json(fraction),
+        PodValue::Fd(fd) => Value::from(fd.0),
+        PodValue::ValueArray(values) => match values {
+            ValueArray::None(values) => array(values, |_| Value::Null),
+            ValueArray::Bool(values) => array(values, |b| Value::from(*b)),
+            ValueArray::Id(values) => array(values, |Id(id)| id_to_json(*id, info)),
+            ValueArray::Int(values) => array(values, |i| Value::from(i64::from(*i))),
+            ValueArray::Long(values) => array(values, |l| Value::from(*l)),
+            ValueArray::Float(values) => array(values, |f| float_to_json(*f)),
+            ValueArray::Double(values) => array(values, |d| float_to_json(*d)),
+            ValueArray::Rectangle(values) => array(values, rectangle_to_json),
+            ValueArray::Fraction(values) => array(values, fraction_to_json),
+            ValueArray::Fd(values) => array(values, |fd| Value::from(fd.0)),
+        },
+        PodValue::Struct(values) => array(values, |value| value_to_json(value, None)),
+        PodValue::Object(object) => object_to_json(object),
+        PodValue::Choice(choice) => match choice {
+            ChoiceValue::Bool(choice) => choice_to_json(choice, |b| Value::from(*b)),
+            ChoiceValue::Int(choice) => choice_to_json(choice, |i| Value::from(i64::from(*i))),
+            ChoiceValue::Long(choice) => choice_to_json(choice, |l| Value::from(*l)),
+            ChoiceValue::Float(choice) => choice_to_json(choice, |f| float_to_json(*f)),
+            ChoiceValue::Double(choice) => choice_to_json(choice, |d| float_to_json(*d)),
+            ChoiceValue::Id(choice) => choice_to_json(choice, |Id(id)| id_to_json(*id, info)),
+            ChoiceValue::Rectangle(choice) => choice_to_json(choice, rectangle_to_json),
+            ChoiceValue::Fraction(choice) => choice_to_json(choice, fraction_to_json),
+            ChoiceValue::Fd(choice) => choice_to_json(choice, |fd| Value::from(fd.0)),
+        },
+    }
+}
+
+/// Convert a property value, keeping booleans and numbers unquoted like `pw-dump`.
+fn prop_to_json(value: &str) -> Value {
+    match value {
+        "true" => Value::from(true),
+        "false" => Value::from(false),
+        _ => {
+            if let Ok(i) = value.parse::<i64>() {
+                Value::from(i)
+            } else {
+                match value.parse::<f64>() {
+                    Ok(f) if f.is_finite() => Value::from(f),
+                    _ => Value::from(value),
+                }
+            }
+        }
+    }
+}
+
+fn dict_to_json(dict: Option<&DictRef>) -> Value {
+    Value::Object(dict.map_or_else(Vec::new, |dict| {
+        dict.iter()
+            .map(|(key, value)| (key.to_owned(), prop_to_json(value)))
+            .collect()
+    }))
+}
+
+fn props_to_json(props: &Props) -> Value {
+    Value::Object(
+        props
+            .iter()
+            .map(|(key, value)| (key.clone(), prop_to_json(value)))
+            .collect(),
+    )
+}
+
+/// The names of the flags set in `flags`, e.g. `input-ports` for `INPUT_PORTS`
+/// or `r` for [`PermissionFlags::R`](`crate::permissions::PermissionFlags::R`).
+fn flags_to_json<F: Flags>(flags: F) -> Value {
+    Value::Array(
+        flags
+            .iter_names()
+            .map(|(name, _)| Value::from(name.to_lowercase().replace('_', "-")))
+            .collect(),
+    )
+}
+
+fn error_to_json(error: Option<&str>) -> Value {
+    error.map_or(Value::Null, Value::from)
+}
+
+fn member(key: &str, value: impl Into<Value>) -> (String, Value) {
+    (key.to_owned(), value.into())
+}
+
+fn u32_member(key: &str, value: u32) -> (String, Value) {
+    member(key, i64::from(value))
+}
+
+enum Info {
+    Node(NodeInfoSnapshot),
+    Port(PortInfoSnapshot),
+    Link(LinkInfoSnapshot),
+    Device(DeviceInfoSnapshot),
+    Client(ClientInfoSnapshot),
+    Module(ModuleInfoSnapshot),
+    Factory(FactoryInfoSnapshot),
+}
+
+impl Info {
+    fn to_json(&self, params: Value) -> Value {
+        let members = match self {
+            Self::Node(info) => {
+                let (state, error) = match &info.state {
+                    NodeStateSnapshot::Error(error) => ("error", Some(error.as_str())),
+                    NodeStateSnapshot::Creating => ("creating", None),
+                    NodeStateSnapshot::Suspended => ("suspended", None),
+                    NodeStateSnapshot::Idle => ("idle", None),
+                    NodeStateSnapshot::Running => ("running", None),
+                };
+                vec![
+                    u32_member("max-input-ports", info.max_input_ports),
+                    u32_member("max-output-ports", info.max_output_ports),
+                    member("change-mask", flags_to_json(info.change_mask)),
+                    u32_member("n-input-ports", info.n_input_ports),
+                    u32_member("n-output-ports", info.n_output_ports),
+                    member("state", state),
+                    member("error", error_to_json(error)),
+                    member("props", props_to_json(&info.props)),
+                    member("params", params),
+                ]
+            }
+            Self::Port(info) => {
+                let direction = if info.direction == spa::utils::Direction::Input {
+                    "input"
+                } else {
+                    "output"
+                };
+                vec![
+                    member("direction", direction),
+                    member("change-mask", flags_to_json(info.change_mask)),
+                    member("props", props_to_json(&info.props)),
+                    member("params", params),
+                ]
+            }
+            Self::Link(info) => {
+                let (state, error) = match &info.state {
+                    LinkStateSnapshot::Error(error) => ("error", Some(error.as_str())),
+                    LinkStateSnapshot::Unlinked => ("unlinked", None),
+                    LinkStateSnapshot::Init => ("init", None),
+                    LinkStateSnapshot::Negotiating => ("negotiating", None),
+                    LinkStateSnapshot::Allocating => ("allocating", None),
+                    LinkStateSnapshot::Paused => ("paused", None),
+                    LinkStateSnapshot::Active => ("active", None),
+                };
+                vec![
+                    u32_member("output-node-id", info.output_node_id),
+                    u32_member("output-port-id", info.output_port_id),
+                    u32_member("input-node-id", info.input_node_id),
+                    u32_member("input-port-id", info.input_port_id),
+                    member("change-mask", flags_to_json(info.change_mask)),
+                    member("state", state),
+                    member("error", error_to_json(error)),
+                    member("format", info.format().map_or(Value::Null, pod_to_json)),
+                    member("props", props_to_json(&info.props)),
+                ]
+            }
+            Self::Device(info) => vec![
+                member("change-mask", flags_to_json(info.change_mask)),
+                member("props", props_to_json(&info.props)),
+                member("params", params),
+            ],
+            Self::Client(info) => vec![
+                member("change-mask", flags_to_json(info.change_mask)),
+                member("props", props_to_json(&info.props)),
+            ],
+            Self::Module(info) => vec![
+                member("name", info.name.as_str()),
+                member("filename", info.filename.as_str()),
+                member(
+                    "args",
+                    info.args.as_deref().map_or(Value::Null, Value::from),
+                ),
+                member("change-mask", flags_to_json(info.change_mask)),
+                member("props", props_to_json(&info.props)),
+            ],
+            Self::Factory(info) => vec![
+                member("name", info.name.as_str()),
+                member("type", info.type_.to_str()),
+                u32_member("version", info.version),
+                member("change-mask", flags_to_json(info.change_mask)),
+                member("props", props_to_json(&info.props)),
+            ],
+        };
+
+        Value::Object(members)
+    }
+}
+
+/// Converts a snapshot to its [`Info`] variant and back.
+type Variant<S> = (fn(S) -> Info, fn(&mut Info) -> Option<&mut S>);
+
+/// The proxy bound for an object, used to enumerate its params.
+enum Bound {
+    Node(Node),
+    Port(Port),
+    Device(Device),
+    // Only needs to stay alive for its listener.
+    Other(#[allow(dead_code)] Proxy),
+}
+
+/// A proxy with params, which are dumped with its info.
+trait ParamProxy: InfoProxy {
+    fn add_param_listener<F>(&self, param: F) -> Box<dyn Listener>
+    where
+        F: Fn(i32, ParamType, Option<&Pod>) + 'static;
+}
+
+macro_rules! impl_param_proxy {
+    ($proxy:ty) => {
+        impl ParamProxy for $proxy {
+            fn add_param_listener<F>(&self, param: F) -> Box<dyn Listener>
+            where
+                F: Fn(i32, ParamType, Option<&Pod>) + 'static,
+            {
+                let listener = self
+                    .add_listener_local()
+                    .param(move |seq, id, _, _, pod| param(seq, id, pod))
+                    .register();
+                Box::new(listener)
+            }
+        }
+    };
+}
+
+impl_param_proxy!(Node);
+impl_param_proxy!(Port);
+impl_param_proxy!(Device);
+
+impl Bound {
+    /// Enumerate the params `id`, returning the seq of the replies, or `None` for
+    /// objects without params.
+    fn enum_params(&self, id: ParamType) -> Result<Option<AsyncSeq>, Error> {
+        let seq = match self {
+            Self::Node(node) => node.enum_params(0, Some(id), 0, u32::MAX, None)?,
+            Self::Port(port) => port.enum_params(0, Some(id), 0, u32::MAX, None)?,
+            Self::Device(device) => device.enum_params(0, Some(id), 0, u32::MAX, None)?,
+            Self::Other(_) => return Ok(None),
+        };
+        Ok(Some(seq))
+    }
+}
+
+struct ParamList {
+    id: ParamType,
+    // Raw seq of the last enumeration, replies to older ones are dropped.
+    seq: Option<i32>,
+    values: Vec<Value>,
+}
+
+struct MetadataEntry {
+    subject: u32,
+    key: String,
+    type_: Option<String>,
+    value: String,
+}
+
+impl MetadataEntry {
+    fn to_json(&self) -> Value {
+        // Values of JSON type are embedded as is, like pw-dump does.
+        let value = match self.type_.as_deref() {
+            Some("Spa:String:JSON") => {
+                Value::parse(&self.value).unwrap_or_else(|_| Value::from(self.value.as_str()))
+            }
+            _ => Value::from(self.value.as_str()),
+        };
+
+        Value::Object(vec![
+            u32_member("subject", self.subject),
+            member("key", self.key.as_str()),
+            member(
+                "type",
+                self.type_.as_deref().map_or(Value::Null, Value::from),
+            ),
+            member("value", value),
+        ])
+    }
+}
+
+struct Entry {
+    // Need to be dropped before the proxy they are registered on.
+    _listeners: Vec<Box<dyn Listener>>,
+    bound: Option<Bound>,
+    global: GlobalObject<Properties>,
+    info: Option<Info>,
+    params: Vec<ParamList>,
+    metadata: Option<Vec<MetadataEntry>>,
+}
+
+impl Entry {
+    fn to_json(&self) -> Value {
+        let mut members = vec![
+            u32_member("id", self.global.id),
+            member("type", self.global.type_.to_str()),
+            u32_member("version", self.global.version),
+            member("permissions", flags_to_json(self.global.permissions)),
+        ];
+
+        match &self.info {
+            Some(info) => {
+                let params = Value::Object(
+                    self.params
+                        .iter()
+                        .map(|params| {
+                            let name =
+                                find_type(unsafe { spa_sys::spa_type_param }, params.id.as_raw())
+                                    .and_then(TypeInfo::short_name)
+                                    .map_or_else(|| params.id.as_raw().to_string(), str::to_owned);
+                            (name, Value::Array(params.values.clone()))
+                        })
+                        .collect(),
+                );
+                members.push(member("info", info.to_json(params)));
+            }
+            None => {
+                members.push(member(
+                    "props",
+                    dict_to_json(self.global.props.as_ref().map(|props| props.dict())),
+                ));
+                if let Some(metadata) = &self.metadata {
+                    members.push(member(
+                        "metadata",
+                        Value::Array(metadata.iter().map(MetadataEntry::to_json).collect()),
+                    ));
+                }
+            }
+        }
+
+        Value::Object(members)
+    }
+
+    /// Apply an info event, returning whether params are enumerated again.
+    fn update_info<S: InfoSnapshot>(&mut self, info: &S::Info, variant: Variant<S>) -> bool {
+        let (wrap, get) = variant;
+        snapshot::update_object(&mut self.info, info, wrap, get);
+        match S::changed_params(info) {
+            Some(params) => {
+                self.enum_params(params);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Start enumerating the readable params of the object.
+    fn enum_params(&mut self, params: &[ParamInfo]) {
+        self.params = params
+            .iter()
+            .map(|param| {
+                let seq = match &self.bound {
+                    Some(bound) if param.flags().contains(ParamInfoFlags::READ) => {
+                        bound.enum_params(param.id()).ok().flatten()
+                    }
+                    _ => None,
+                };
+                ParamList {
+                    id: param.id(),
+                    seq: seq.map(|seq| seq.raw()),
+                    values: Vec::new(),
+                }
+            })
+            .collect();
+    }
+
+    fn add_param(&mut self, seq: i32, id: ParamType, param: Option<&Pod>) {
+        let list = self
+            .params
+            .iter_mut()
+            .find(|list| list.id == id && list.seq == Some(seq));
+
+        if let (Some(list), Some(param)) = (list, param) {
+            list.values.push(pod_to_json(param));
+        }
+    }
+
+    fn set_metadata(
+        &mut self,
+        subject: u32,
+        key: Option<&str>,
+        type_: Option<&str>,
+        value: Option<&str>,
+    ) {
+        let metadata = self.metadata.get_or_insert_with(Vec::new);
+
+        let Some(key) = key else {
+            metadata.retain(|entry| entry.subject != subject);
+            return;
+        };
+
+        let position = metadata
+            .iter()
+            .position(|entry| entry.subject == subject && entry.key == key);
+        match (position, value) {
+            (Some(position), None) => {
+                metadata.remove(position);
+            }
+            (Some(position), Some(value)) => {
+                metadata[position].type_ = type_.map(str::to_owned);
+                metadata[position].value = value.to_owned();
+            }
+            (None, Some(value)) => metadata.push(MetadataEntry {
+                subject,
+                key: key.to_owned(),
+                type_: type_.map(str::to_owned),
+                value: value.to_owned(),
+            }),
+            (None, None) => {}
+        }
+    }
+}
+
+type ChangedCallback = dyn Fn(&Value);
+
+#[derive(Default)]
+struct ListenerLocalCallbacks {
+    changed: Option<Box<ChangedCallback>>,
+}
+
+struct Inner {
+    // Also used to sync with the server, so that the dump doesn't need to own the core.
+    registry: Registry,
+    objects: RefCell<BTreeMap<u32, Entry>>,
+    changed: RefCell<BTreeSet<u32>>,
+    // Sequence number of the sync waited for before reporting changes.
+    pending_sync: Cell<Option<i32>>,
+    // Requests were sent after the pending sync, so another sync is needed to see their replies.
+    resync: Cell<bool>,
+    listeners: LocalListeners<ListenerLocalCallbacks>,
+}
+
+impl Inner {
+    fn global(self: &Rc<Self>, global: &GlobalObject<&DictRef>) {
+        macro_rules! variant {
+            ($variant:path) => {
+                ($variant, |info: &mut Info| match info {
+                    $variant(info) => Some(info),
+                    _ => None,
+                })
+            };
+        }
+
+        let bound = match global.type_ {
+            ObjectType::Node => self.bind_params::<Node>(global, variant!(Info::Node), Bound::Node),
+            ObjectType::Port => self.bind_params::<Port>(global, variant!(Info::Port), Bound::Port),
+            ObjectType::Device => {
+                self.bind_params::<Device>(global, variant!(Info::Device), Bound::Device)
+            }
+            ObjectType::Link => self.bind_other::<Link>(global, variant!(Info::Link)),
+            ObjectType::Client => self.bind_other::<Client>(global, variant!(Info::Client)),
+            ObjectType::Module => self.bind_other::<Module>(global, variant!(Info::Module)),
+            ObjectType::Factory => self.bind_other::<Factory>(global, variant!(Info::Factory)),
+            ObjectType::Metadata => self.bind_metadata(global),
+            // Other objects are dumped with their global properties only.
+            _ => None,
+        };
+        let (listeners, bound) = bound.unzip();
+
+        let requested = bound.is_some();
+        self.objects.borrow_mut().insert(
+            global.id,
+            Entry {
+                _listeners: listeners.unwrap_or_default(),
+                bound,
+                global: global.to_owned(),
+                info: None,
+                params: Vec::new(),
+                metadata: matches!(global.type_, ObjectType::Metadata).then(Vec::new),
+            },
+        );
+        self.changed(global.id, requested);
+    }
+
+    /// Bind `global` and keep the snapshot of its info up to date.
+    ///
+    /// `variant` converts between the snapshot and its [`Info`] variant.
+    fn bind<P: InfoProxy>(
+        self: &Rc<Self>,
+        global: &GlobalObject<&DictRef>,
+        variant: Variant<P::Snapshot>,
+    ) -> Option<(Box<dyn Listener>, P)> {
+        let id = global.id;
+        let this = Rc::downgrade(self);
+        let proxy = self.registry.bind::<P, _>(global).ok()?;
+        let listener = proxy.add_info_listener(move |info| {
+            Self::update(&this, id, |entry| entry.update_info(info, variant))
+        });
+
+        Some((listener, proxy))
+    }
+
+    /// Bind `global` and collect its info and params.
+    fn bind_params<P: ParamProxy>(
+        self: &Rc<Self>,
+        global: &GlobalObject<&DictRef>,
+        variant: Variant<P::Snapshot>,
+        bound: fn(P) -> Bound,
+    ) -> Option<(Vec<Box<dyn Listener>>, Bound)> {
+        let (info_listener, proxy) = self.bind::<P>(global, variant)?;
+        let id = global.id;
+        let this = Rc::downgrade(self);
+        let param_listener = proxy.add_param_listener(move |seq, param_id, param| {
+            Self::update(&this, id, |entry| {
+                entry.add_param(seq, param_id, param);
+                false
+            })
+        });
+
+        Some((vec![info_listener, param_listener], bound(proxy)))
+    }
+
+    /// Bind `global` and collect its info.
+    fn bind_other<P: InfoProxy>(
+        self: &Rc<Self>,
+        global: &GlobalObject<&DictRef>,
+        variant: Variant<P::Snapshot>,
+    ) -> Option<(Vec<Box<dyn Listener>>, Bound)> {
+        let (listener, proxy) = self.bind::<P>(global, variant)?;
+        Some((vec![listener], Bound::Other(proxy.upcast())))
+    }
+
+    /// Bind the metadata `global` and collect its properties.
+    fn bind_metadata(
+        self: &Rc<Self>,
+        global: &GlobalObject<&DictRef>,
+    ) -> Option<(Vec<Box<dyn Listener>>, Bound)> {
+        let id = global.id;
+        let this = Rc::downgrade(self);
+        let metadata = self.registry.bind::<Metadata, _>(global).ok()?;
+        let listener = metadata
+            .add_listener_local()
+            .property(move |subject, key, type_, value| {
+                Self::update(&this, id, |entry| {
+                    entry.set_metadata(subject, key, type_, value);
+                    false
+                });
+                0
+            })
+            .register();
+
+        Some((vec![Box::new(listener)], Bound::Other(metadata.upcast())))
+    }
+
+    fn global_remove(&self, id: u32) {
+        let entry = self.objects.borrow_mut().remove(&id);
+        if entry.is_some() {
+            self.changed(id, false);
+        }
+    }
+
+    /// Update the object `id`, `update` returns whether requests were sent to the server.
+    fn update(this: &Weak<Self>, id: u32, update: impl FnOnce(&mut Entry) -> bool) {
+        let Some(this) = this.upgrade() else {
+            return;
+        };
+
+        let requested = {
+            let mut objects = this.objects.borrow_mut();
+            let Some(entry) = objects.get_mut(&id) else {
+                return;
+            };
+            update(entry)
+        };
+
+        this.changed(id, requested);
+    }
+
+    /// Mark `id` as changed and make sure the changes are reported once the server
+    /// processed all the requests sent so far.
+    fn changed(&self, id: u32, requested: bool) {
+        self.changed.borrow_mut().insert(id);
+
+        if self.pending_sync.get().is_some() {
+            self.resync.set(self.resync.get() || requested);
+        } else {
+            self.sync();
+        }
+    }
+
+    fn sync(&self) {
+        self.resync.set(false);
+        self.pending_sync
+            .set(self.registry.sync(0).ok().map(|seq| seq.seq()));
+    }
+
+    fn done(&self, seq: i32) {
+        if self.pending_sync.get() != Some(seq) {
+            return;
+        }
+
+        if self.resync.get() {
+            self.sync();
+            return;
+        }
+        self.pending_sync.set(None);
+
+        let changed = std::mem::take(&mut *self.changed.borrow_mut());
+        let diff = {
+            let objects = self.objects.borrow();
+            Value::Array(
+                changed
+                    .into_iter()
+                    .map(|id| match objects.get(&id) {
+                        Some(entry) => entry.to_json(),
+                        None => {
+                            Value::Object(vec![u32_member("id", id), member("info", Value::Null)])
+                        }
+                    })
+                    .collect(),
+            )
+        };
+
+        self.listeners.emit(|cbs| {
+            if let Some(changed) = &cbs.changed {
+                changed(&diff);
+            }
+        });
+    }
+}
+
+/// Collects the objects of the graph as `pw-dump` compatible JSON.
+///
+/// See the [module documentation](`self`) for details.
+pub struct Dump {
+    // Need to be dropped before the registry and core they are registered on.
+    _registry_listener: registry::Listener,
+    _core_listener: crate::core::Listener,
+    inner: Rc<Inner>,
+}
+
+impl Dump {
+    /// Create a dump of the graph of the remote of `core`.
+    pub fn new(core: &CoreRef) -> Result<Self, Error> {
+        let inner = Rc::new(Inner {
+            registry: core.get_registry()?,
+            objects: RefCell::new(BTreeMap::new()),
+            changed: RefCell::new(BTreeSet::new()),
+            pending_sync: Cell::new(None),
+            resync: Cell::new(false),
+            listeners: LocalListeners::default(),
+        });
+
+        let registry_listener = inner
+            .registry
+            .add_listener_local()
+            .global({
+                let inner = Rc::downgrade(&inner);
+                move |global| {
+                    if let Some(inner) = inner.upgrade() {
+                        inner.global(global);
+                    }
+                }
+            })
+            .global_remove({
+                let inner = Rc::downgrade(&inner);
+                move |id| {
+                    if let Some(inner) = inner.upgrade() {
+                        inner.global_remove(id);
+                    }
+                }
+            })
+            .register();
+
+        let core_listener = core
+            .add_listener_local()
+            .done({
+                let inner = Rc::downgrade(&inner);
+                move |id, seq| {
+                    if let Some(inner) = inner.upgrade() {
+                        if id == inner.registry.proxy_id() {
+                            inner.done(seq.seq());
+                        }
+                    }
+                }
+            })
+            .register();
+
+        Ok(Self {
+            _registry_listener: registry_listener,
+            _core_listener: core_listener,
+            inner,
+        })
+    }
+
+    #[must_use]
+    pub fn add_listener_local(&self) -> DumpListenerLocalBuilder {
+        DumpListenerLocalBuilder {
+            dump: self,
+            cbs: ListenerLocalCallbacks::default(),
+        }
+    }
+
+    /// The JSON of the object with the given global id.
+    pub fn object(&self, id: u32) -> Option<Value> {
+        self.inner.objects.borrow().get(&id).map(Entry::to_json)
+    }
+
+    /// The JSON array of all the objects, ordered by id.
+    pub fn to_json(&self) -> Value {
+        Value::Array(
+            self.inner
+                .objects
+                .borrow()
+                .values()
+                .map(Entry::to_json)
+                .collect(),
+        )
+    }
+}
+
+pub struct DumpListenerLocalBuilder<'a> {
+    dump: &'a Dump,
+    cbs: ListenerLocalCallbacks,
+}
+
+/// A listener of the changes of a [`Dump`], removed when dropped.
+pub struct DumpListener {
+    _cbs: Rc<ListenerLocalCallbacks>,
+}
+
+impl<'a> DumpListenerLocalBuilder<'a> {
+    /// Called with a JSON array of the objects that changed, once the server sent all the updates.
+    #[must_use]
+    pub fn changed<F>(mut self, changed: F) -> Self
+    where
+        F: Fn(&Value) + 'static,
+    {
+        self.cbs.changed = Some(Box::new(changed));
+        self
+    }
+
+    #[must_use]
+    pub fn register(self) -> DumpListener {
+        DumpListener {
+            _cbs: self.dump.inner.listeners.register(self.cbs),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use spa::{pod::serialize::PodSerializer, utils::Direction};
+
+    use super::*;
+    use crate::{
+        permissions::PermissionFlags,
+        port::PortChangeMask,
+        snapshot::test_utils::{module_info, port_info, RawInfo},
+    };
+
+    #[test]
+    fn props() {
+        assert_eq!(prop_to_json("true"), Value::Bool(true));
+        assert_eq!(prop_to_json("42"), Value::Number("42".into()));
+        assert_eq!(prop_to_json("-1.5"), Value::Number("-1.5".into()));
+        assert_eq!(prop_to_json("nan"), Value::String("nan".into()));
+        assert_eq!(
+            prop_to_json("Audio/Sink"),
+            Value::String("Audio/Sink".into())
+        );
+    }
+
+    #[test]
+    fn choices() {
+        let choice = Choice(
+            spa::utils::ChoiceFlags::empty(),
+            ChoiceEnum::Range {
+                default: 2,
+                min: 1,
+                max: 3,
+            },
+        );
+        assert_eq!(
+            choice_to_json(&choice, |i: &i32| Value::from(i64::from(*i))).to_string(),
+            r#"{"default": 2, "min": 1, "max": 3}"#
+        );
+
+        let choice = Choice(
+            spa::utils::ChoiceFlags::empty(),
+            ChoiceEnum::Enum {
+                default: 2,
+                alternatives: vec![2, 4],
+            },
+        );
+        assert_eq!(
+            choice_to_json(&choice, |i: &i32| Value::from(i64::from(*i))).to_string(),
+            r#"{"default": 2, "alt1": 2, "alt2": 4}"#
+        );
+    }
+
+    #[test]
+    fn flags() {
+        assert_eq!(
+            flags_to_json(
+                crate::node::NodeChangeMask::INPUT_PORTS | crate::node::NodeChangeMask::PARAMS
+            )
+            .to_string(),
+            r#"["input-ports", "params"]"#
+        );
+        assert_eq!(
+            flags_to_json(PermissionFlags::R | PermissionFlags::X).to_string(),
+            r#"["r", "x"]"#
+        );
+    }
+
+    fn entry(id: u32, type_: ObjectType) -> Entry {
+        let metadata = matches!(type_, ObjectType::Metadata).then(Vec::new);
+        Entry {
+            _listeners: Vec::new(),
+            bound: None,
+            global: GlobalObject {
+                id,
+                permissions: PermissionFlags::R,
+                type_,
+                version: 3,
+                props: None,
+            },
+            info: None,
+            params: Vec::new(),
+            metadata,
+        }
+    }
+
+    fn long_pod(value: i64) -> Vec<u8> {
+        let (cursor, _) =
+            PodSerializer::serialize(Cursor::new(Vec::new()), &PodValue::Long(value)).unwrap();
+        cursor.into_inner()
+    }
+
+    #[test]
+    fn info() {
+        let props = spa::static_dict! { "module.name" => "libpipewire-module-rt" };
+        let raw = module_info(
+            3,
+            c"libpipewire-module-rt",
+            c"/usr/lib/libpipewire-module-rt.so",
+            &props,
+        );
+        let variant: Variant<ModuleInfoSnapshot> = (Info::Module, |info| match info {
+            Info::Module(info) => Some(info),
+            _ => None,
+        });
+        let mut entry = entry(3, ObjectType::Module);
+        assert!(!entry.update_info(raw.info(), variant));
+        // The props are only sent again when they changed.
+        assert!(!entry.update_info(raw.partial(0).info(), variant));
+
+        assert_eq!(
+            entry.to_json().to_string(),
+            r#"{"id": 3, "type": "PipeWire:Interface:Module", "version": 3, "permissions": ["r"], "info": {"name": "libpipewire-module-rt", "filename": "/usr/lib/libpipewire-module-rt.so", "args": null, "change-mask": [], "props": {"module.name": "libpipewire-module-rt"}}}"#
+        );
+    }
+
+    #[test]
+    fn params() {
+        let props = spa::static_dict! { "port.name" => "capture_FL" };
+        let mut params = [
+            ParamInfo::new(ParamType::EnumFormat, ParamInfoFlags::READ),
+            ParamInfo::new(ParamType::Props, ParamInfoFlags::empty()),
+        ];
+        let raw = port_info(40, Direction::Output, &props);
+        let mut update = raw.partial(PortChangeMask::PARAMS.bits());
+        update.params = params.as_mut_ptr().cast();
+        update.n_params = params.len() as u32;
+        let variant: Variant<PortInfoSnapshot> = (Info::Port, |info| match info {
+            Info::Port(info) => Some(info),
+            _ => None,
+        });
+        let mut entry = entry(40, ObjectType::Port);
+        entry.update_info(raw.info(), variant);
+        assert!(entry.update_info(update.info(), variant));
+        let add_param = |entry: &mut Entry, seq: i32, id: ParamType, value: i64| {
+            let bytes = long_pod(value);
+            entry.add_param(seq, id, Pod::from_bytes(&bytes));
+        };
+
+        // Nothing is enumerated without a proxy, so no reply is expected.
+        assert!(entry.params.iter().all(|list| list.seq.is_none()));
+        add_param(
+            &mut entry,
+            AsyncSeq::from_seq(0).raw(),
+            ParamType::EnumFormat,
+            0,
+        );
+
+        // Replies are matched with the seq returned by the enumeration.
+        let seq = AsyncSeq::from_seq(5).raw();
+        entry.params[0].seq = Some(seq);
+        add_param(&mut entry, seq, ParamType::EnumFormat, 1);
+        add_param(&mut entry, seq, ParamType::EnumFormat, 2);
+        // Replies to an older enumeration, or for params that were not enumerated.
+        add_param(
+            &mut entry,
+            AsyncSeq::from_seq(4).raw(),
+            ParamType::EnumFormat,
+            3,
+        );
+        add_param(&mut entry, seq, ParamType::Props, 4);
+        entry.add_param(seq, ParamType::EnumFormat, None);
+
+        assert_eq!(
+            entry.to_json().to_string(),
+            r#"{"id": 40, "type": "PipeWire:Interface:Port", "version": 3, "permissions": ["r"], "info": {"direction": "output", "change-mask": ["params"], "props": {"port.name": "capture_FL"}, "params": {"EnumFormat": [1, 2], "Props": []}}}"#
+        );
+    }
+
+    #[test]
+    fn metadata() {
+        let mut entry = entry(30, ObjectType::Metadata);
+        entry.set_metadata(
+            0,
+            Some("default.audio.sink"),
+            Some("Spa:String:JSON"),
+            Some(r#"{ "name": "sink" }"#),
+        );
+        entry.set_metadata(0, Some("default.audio.source"), None, Some("source"));
+        entry.set_metadata(40, Some("target.object"), Some("Spa:Id"), Some("41"));
+        entry.set_metadata(40, Some("target.object"), Some("Spa:Id"), Some("42"));
+        entry.set_metadata(0, Some("default.audio.source"), None, None);
+        entry.set_metadata(41, Some("target.object"), Some("Spa:Id"), Some("40"));
+        // Removing all the properties of a subject.
+        entry.set_metadata(41, None, None, None);
+
+        assert_eq!(
+            entry.to_json().to_string(),
+            r#"{"id": 30, "type": "PipeWire:Interface:Metadata", "version": 3, "permissions": ["r"], "props": {}, "metadata": [{"subject": 0, "key": "default.audio.sink", "type": "Spa:String:JSON", "value": {"name": "sink"}}, {"subject": 40, "key": "target.object", "type": "Spa:Id", "value": "42"}]}"#
+        );
+    }
+}
@@ -1,7 +1,12 @@
 // Copyright The pipewire-rs Contributors.
 // SPDX-License-Identifier: MIT
 
+use std::fmt;
+
+use nix::errno::Errno;
+use spa::utils::result::AsyncSeq;
 use thiserror::Error;
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("Creation failed")]
@@ -10,6 +15,124 @@ pub enum Error {
     NoMemory,
     #[error("Wrong proxy type")]
     WrongProxyType,
+    #[error("Unsupported object type {0}")]
+    UnsupportedType(crate::types::ObjectType),
+    #[error("Failed to connect to the PipeWire daemon: {0}")]
+    Connect(Errno),
+    #[error("Failed to bind global {id}: {errno}")]
+    Bind { id: u32, errno: Errno },
+    #[error("Failed to create object with factory {factory}: {errno}")]
+    CreateObject { factory: String, errno: Errno },
+    #[error("Failed to load module {name}: {errno}")]
+    LoadModule { name: String, errno: Errno },
+    #[error("Failed to load SPA plugin with factory {factory}: {errno}")]
+    LoadSpaHandle { factory: String, errno: Errno },
+    #[error("Failed to export {type_}: {errno}")]
+    Export { type_: String, errno: Errno },
+    #[error("Memory operation failed: {0}")]
+    Mem(Errno),
+    #[error("No ports to link from node {output_node_id} to node {input_node_id}")]
+    NoPortsToLink {
+        output_node_id: u32,
+        input_node_id: u32,
+    },
+    #[error("Failed to link port {output_port_id} to port {input_port_id}: {message}")]
+    LinkFailed {
+        output_port_id: u32,
+        input_port_id: u32,
+        message: String,
+    },
+    #[error("No metadata object named {0}")]
+    MetadataNotFound(String),
+    #[error("The volume of node {0} is not known yet")]
+    VolumeUnknown(u32),
+    #[error("Failed to set param {id:?}: {errno}")]
+    SetParam {
+        id: spa::param::ParamType,
+        errno: Errno,
+    },
+    #[error(transparent)]
+    Remote(#[from] RemoteError),
+    #[error(transparent)]
+    Json(#[from] spa::utils::json::ParseError),
+    #[error("Invalid string: {0}")]
+    Nul(#[from] std::ffi::NulError),
     #[error(transparent)]
     SpaError(#[from] spa::utils::result::Error),
 }
+
+impl Error {
+    /// The errno describing the error, if the error carries one.
+    pub fn errno(&self) -> Option<Errno> {
+        match self {
+            Error::Connect(errno)
+            | Error::Mem(errno)
+            | Error::Bind { errno, .. }
+            | Error::CreateObject { errno, .. }
+            | Error::LoadModule { errno, .. }
+            | Error::LoadSpaHandle { errno, .. }
+            | Error::Export { errno, .. }
+            | Error::SetParam { errno, .. } => Some(*errno),
+            Error::Remote(e) => Some(e.errno),
+            Error::SpaError(e) => Some(Errno::from_raw(e.errno() as i32)),
+            Error::NoMemory => Some(Errno::ENOMEM),
+            Error::Json(_) | Error::Nul(_) => Some(Errno::EINVAL),
+            Error::CreationFailed
+            | Error::WrongProxyType
+            | Error::UnsupportedType(_)
+            | Error::NoPortsToLink { .. }
+            | Error::LinkFailed { .. }
+            | Error::MetadataNotFound(_)
+            | Error::VolumeUnknown(_) => None,
+        }
+    }
+}
+
+/// An error reported by the remote through an `error` event of the core or of a proxy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteError {
+    /// The id of the object that caused the error.
+    pub id: u32,
+    /// The sequence number of the method call that caused the error, as passed to the event.
+    pub seq: i32,
+    /// The errno describing the error.
+    pub errno: Errno,
+    /// A human readable description of the error.
+    pub message: String,
+}
+
+impl RemoteError {
+    /// Build a [`RemoteError`] from the raw arguments of an `error` event,
+    /// where `res` is a negative errno.
+    pub fn from_raw(id: u32, seq: i32, res: i32, message: &str) -> Self {
+        Self {
+            id,
+            seq,
+            errno: Errno::from_raw(-res),
+            message: message.to_owned(),
+        }
+    }
+
+    /// The [`AsyncSeq`] returned by the method call that caused this error,
+    /// or [`None`] if the error is not a reply to an asynchronous method call.
+    pub fn async_seq(&self) -> Option<AsyncSeq> {
+        AsyncSeq::try_from_raw(self.seq)
+    }
+
+    /// Check if this error is a reply to the method call that returned `seq`.
+    pub fn is_reply_to(&self, seq: AsyncSeq) -> bool {
+        self.seq == seq.raw()
+    }
+}
+
+impl fmt::Display for RemoteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "remote error on object {} (seq {}): {}: {}",
+            self.id, self.seq, self.errno, self.message
+        )
+    }
+}
+
+impl std::error::Error for RemoteError {}
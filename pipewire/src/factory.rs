@@ -78,6 +78,10 @@ impl FactoryInfoRef {
         self.0.id
     }
 
+    pub fn name(&self) -> &str {
+        unsafe { CStr::from_ptr(self.0.name).to_str().unwrap() }
+    }
+
     pub fn type_(&self) -> ObjectType {
         ObjectType::from_str(unsafe { CStr::from_ptr(self.0.type_).to_str().unwrap() })
     }
@@ -100,6 +104,7 @@ impl fmt::Debug for FactoryInfoRef {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("FactoryInfoRef")
             .field("id", &self.id())
+            .field("name", &self.name())
             .field("type", &self.type_())
             .field("version", &self.version())
             .field("change_mask", &self.change_mask())
@@ -150,6 +155,7 @@ impl AsRef<FactoryInfoRef> for FactoryInfo {
 
 bitflags! {
     #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct FactoryChangeMask: u64 {
         const PROPS = pw_sys::PW_FACTORY_CHANGE_MASK_PROPS as u64;
     }
@@ -159,6 +165,7 @@ impl fmt::Debug for FactoryInfo {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("FactoryInfo")
             .field("id", &self.id())
+            .field("name", &self.name())
             .field("type", &self.type_())
             .field("version", &self.version())
             .field("change_mask", &self.change_mask())
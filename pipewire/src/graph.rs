@@ -0,0 +1,671 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! A local cache of the nodes, ports, links and devices of the graph.
+//!
+//! A [`Graph`] listens to the [`Registry`], binds every node, port, link and device it announces
+//! and keeps their info up to date, so that tools do not have to track the objects themselves.
+//!
+//! ```no_run
+//! use pipewire::{graph::Graph, main_loop::MainLoop, context::Context};
+//!
+//! let mainloop = MainLoop::new(None).expect("Failed to create main loop");
+//! let context = Context::new(&mainloop).expect("Failed to create context");
+//! let core = context.connect(None).expect("Failed to connect to remote");
+//!
+//! let graph = Graph::new(&core).expect("Failed to create graph");
+//! let _listener = graph
+//!     .add_listener_local()
+//!     .added(|object| println!("added: {:?}", object))
+//!     .removed(|object| println!("removed: {}", object.id()))
+//!     .register();
+//!
+//! mainloop.run();
+//! ```
+
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    rc::{Rc, Weak},
+};
+
+use spa::utils::{dict::DictRef, Direction};
+
+use crate::{
+    core::CoreRef,
+    device::Device,
+    keys,
+    link::Link,
+    node::Node,
+    port::Port,
+    proxy::{Listener, Proxy},
+    registry::{self, GlobalObject, Registry},
+    snapshot::{
+        self, DeviceInfoSnapshot, InfoProxy, LinkInfoSnapshot, NodeInfoSnapshot, PortInfoSnapshot,
+        Props,
+    },
+    types::ObjectType,
+    utils::LocalListeners,
+    Error,
+};
+
+/// An object tracked by a [`Graph`], with its latest info.
+#[derive(Debug, Clone)]
+pub enum GraphObject {
+    Node(NodeInfoSnapshot),
+    Port(PortInfoSnapshot),
+    Link(LinkInfoSnapshot),
+    Device(DeviceInfoSnapshot),
+}
+
+impl GraphObject {
+    /// The global id of the object.
+    pub fn id(&self) -> u32 {
+        match self {
+            Self::Node(info) => info.id,
+            Self::Port(info) => info.id,
+            Self::Link(info) => info.id,
+            Self::Device(info) => info.id,
+        }
+    }
+
+    /// The type of the object.
+    pub fn type_(&self) -> ObjectType {
+        match self {
+            Self::Node(_) => ObjectType::Node,
+            Self::Port(_) => ObjectType::Port,
+            Self::Link(_) => ObjectType::Link,
+            Self::Device(_) => ObjectType::Device,
+        }
+    }
+
+    /// The properties of the object.
+    pub fn props(&self) -> &Props {
+        match self {
+            Self::Node(info) => &info.props,
+            Self::Port(info) => &info.props,
+            Self::Link(info) => &info.props,
+            Self::Device(info) => &info.props,
+        }
+    }
+
+    /// The raw change mask of the last info update.
+    ///
+    /// Use the typed info for the meaning of the bits, e.g.
+    /// [`NodeChangeMask`](`crate::node::NodeChangeMask`) for nodes.
+    pub fn change_mask(&self) -> u64 {
+        match self {
+            Self::Node(info) => info.change_mask.bits(),
+            Self::Port(info) => info.change_mask.bits(),
+            Self::Link(info) => info.change_mask.bits(),
+            Self::Device(info) => info.change_mask.bits(),
+        }
+    }
+
+    fn node_mut(&mut self) -> Option<&mut NodeInfoSnapshot> {
+        match self {
+            Self::Node(info) => Some(info),
+            _ => None,
+        }
+    }
+
+    fn port_mut(&mut self) -> Option<&mut PortInfoSnapshot> {
+        match self {
+            Self::Port(info) => Some(info),
+            _ => None,
+        }
+    }
+
+    fn link_mut(&mut self) -> Option<&mut LinkInfoSnapshot> {
+        match self {
+            Self::Link(info) => Some(info),
+            _ => None,
+        }
+    }
+
+    fn device_mut(&mut self) -> Option<&mut DeviceInfoSnapshot> {
+        match self {
+            Self::Device(info) => Some(info),
+            _ => None,
+        }
+    }
+}
+
+struct Bound {
+    // Needs to be dropped before the proxy it is registered on.
+    _listener: Box<dyn Listener>,
+    _proxy: Proxy,
+}
+
+/// The objects whose info was received, by id.
+#[derive(Default)]
+struct Objects(BTreeMap<u32, GraphObject>);
+
+impl Objects {
+    /// Update the object `id`, returning a copy of it and whether it was added.
+    fn update(
+        &mut self,
+        id: u32,
+        update: impl FnOnce(&mut Option<GraphObject>),
+    ) -> Option<(GraphObject, bool)> {
+        let mut object = self.0.remove(&id);
+        let added = object.is_none();
+        update(&mut object);
+
+        let object = object?;
+        self.0.insert(id, object.clone());
+        Some((object, added))
+    }
+
+    fn filter<T>(&self, filter: impl Fn(&GraphObject) -> Option<&T>) -> Vec<T>
+    where
+        T: Clone,
+    {
+        self.0
+            .values()
+            .filter_map(|object| filter(object).cloned())
+            .collect()
+    }
+
+    fn nodes(&self) -> Vec<NodeInfoSnapshot> {
+        self.filter(|object| match object {
+            GraphObject::Node(info) => Some(info),
+            _ => None,
+        })
+    }
+
+    fn node_by_name(&self, name: &str) -> Option<NodeInfoSnapshot> {
+        self.nodes()
+            .into_iter()
+            .find(|node| node_prop(node, *keys::NODE_NAME) == Some(name))
+    }
+
+    fn nodes_by_media_class(&self, media_class: &str) -> Vec<NodeInfoSnapshot> {
+        self.filter(|object| match object {
+            GraphObject::Node(info) if node_prop(info, *keys::MEDIA_CLASS) == Some(media_class) => {
+                Some(info)
+            }
+            _ => None,
+        })
+    }
+
+    fn node_ports(&self, node_id: u32, direction: Direction) -> Vec<PortInfoSnapshot> {
+        self.filter(|object| match object {
+            GraphObject::Port(info)
+                if info.direction == direction && port_node_id(info) == Some(node_id) =>
+            {
+                Some(info)
+            }
+            _ => None,
+        })
+    }
+
+    fn links_between(&self, output_node_id: u32, input_node_id: u32) -> Vec<LinkInfoSnapshot> {
+        self.filter(|object| match object {
+            GraphObject::Link(info)
+                if info.output_node_id == output_node_id && info.input_node_id == input_node_id =>
+            {
+                Some(info)
+            }
+            _ => None,
+        })
+    }
+}
+
+type ObjectCallback = dyn Fn(&GraphObject);
+
+#[derive(Default)]
+struct ListenerLocalCallbacks {
+    added: Option<Box<ObjectCallback>>,
+    changed: Option<Box<ObjectCallback>>,
+    removed: Option<Box<ObjectCallback>>,
+}
+
+struct Inner {
+    registry: Registry,
+    bound: RefCell<BTreeMap<u32, Bound>>,
+    objects: RefCell<Objects>,
+    listeners: LocalListeners<ListenerLocalCallbacks>,
+}
+
+impl Inner {
+    fn global(self: &Rc<Self>, global: &GlobalObject<&DictRef>) {
+        let bound = match global.type_ {
+            ObjectType::Node => self.bind::<Node>(global, GraphObject::Node, GraphObject::node_mut),
+            ObjectType::Port => self.bind::<Port>(global, GraphObject::Port, GraphObject::port_mut),
+            ObjectType::Link => self.bind::<Link>(global, GraphObject::Link, GraphObject::link_mut),
+            ObjectType::Device => {
+                self.bind::<Device>(global, GraphObject::Device, GraphObject::device_mut)
+            }
+            _ => return,
+        };
+
+        // Objects we are not allowed to bind are not tracked.
+        if let Ok((listener, proxy)) = bound {
+            self.bound.borrow_mut().insert(
+                global.id,
+                Bound {
+                    _listener: listener,
+                    _proxy: proxy,
+                },
+            );
+        }
+    }
+
+    /// Bind `global` and keep the snapshot of its info up to date.
+    fn bind<P: InfoProxy>(
+        self: &Rc<Self>,
+        global: &GlobalObject<&DictRef>,
+        wrap: fn(P::Snapshot) -> GraphObject,
+        get: fn(&mut GraphObject) -> Option<&mut P::Snapshot>,
+    ) -> Result<(Box<dyn Listener>, Proxy), Error> {
+        let id = global.id;
+        let this = Rc::downgrade(self);
+        let proxy = self.registry.bind::<P, _>(global)?;
+        let listener = proxy.add_info_listener(move |info| {
+            Self::update(&this, id, |object| {
+                snapshot::update_object(object, info, wrap, get)
+            })
+        });
+
+        Ok((listener, proxy.upcast()))
+    }
+
+    fn update(this: &Weak<Self>, id: u32, update: impl FnOnce(&mut Option<GraphObject>)) {
+        let Some(this) = this.upgrade() else {
+            return;
+        };
+
+        let Some((object, added)) = this.objects.borrow_mut().update(id, update) else {
+            return;
+        };
+
+        // Callbacks are called without borrowing the objects so that they can query the graph.
+        if added {
+            this.emit(&object, |cbs| cbs.added.as_deref());
+        } else {
+            this.emit(&object, |cbs| cbs.changed.as_deref());
+        }
+    }
+
+    fn global_remove(&self, id: u32) {
+        self.bound.borrow_mut().remove(&id);
+        let object = self.objects.borrow_mut().0.remove(&id);
+
+        if let Some(object) = object {
+            self.emit(&object, |cbs| cbs.removed.as_deref());
+        }
+    }
+
+    fn emit(
+        &self,
+        object: &GraphObject,
+        select: fn(&ListenerLocalCallbacks) -> Option<&ObjectCallback>,
+    ) {
+        self.listeners.emit(|cbs| {
+            if let Some(cb) = select(cbs) {
+                cb(object);
+            }
+        });
+    }
+
+    fn objects<T>(&self, filter: impl Fn(&GraphObject) -> Option<&T>) -> Vec<T>
+    where
+        T: Clone,
+    {
+        self.objects.borrow().filter(filter)
+    }
+}
+
+/// A cache of the nodes, ports, links and devices announced by the registry.
+///
+/// Objects show up in the graph once their first info was received, and lookups return
+/// owned copies of their latest info.
+/// The graph is only updated while the loop of its core is running.
+pub struct Graph {
+    // Needs to be dropped before the registry it is registered on.
+    _listener: registry::Listener,
+    inner: Rc<Inner>,
+}
+
+impl Graph {
+    /// Create a graph, binding the objects of the registry of `core`.
+    pub fn new(core: &CoreRef) -> Result<Self, Error> {
+        let inner = Rc::new(Inner {
+            registry: core.get_registry()?,
+            bound: RefCell::new(BTreeMap::new()),
+            objects: RefCell::new(Objects::default()),
+            listeners: LocalListeners::default(),
+        });
+
+        let listener = inner
+            .registry
+            .add_listener_local()
+            .global({
+                let inner = Rc::downgrade(&inner);
+                move |global| {
+                    if let Some(inner) = inner.upgrade() {
+                        inner.global(global);
+                    }
+                }
+            })
+            .global_remove({
+                let inner = Rc::downgrade(&inner);
+                move |id| {
+                    if let Some(inner) = inner.upgrade() {
+                        inner.global_remove(id);
+                    }
+                }
+            })
+            .register();
+
+        Ok(Self {
+            _listener: listener,
+            inner,
+        })
+    }
+
+    /// The registry the objects are bound from.
+    pub fn registry(&self) -> &Registry {
+        &self.inner.registry
+    }
+
+    #[must_use]
+    pub fn add_listener_local(&self) -> GraphListenerLocalBuilder {
+        GraphListenerLocalBuilder {
+            graph: self,
+            cbs: ListenerLocalCallbacks::default(),
+        }
+    }
+
+    /// Get the object with the given global id.
+    pub fn object(&self, id: u32) -> Option<GraphObject> {
+        self.inner.objects.borrow().0.get(&id).cloned()
+    }
+
+    /// Get all the objects of the graph, ordered by id.
+    pub fn objects(&self) -> Vec<GraphObject> {
+        self.inner.objects(|object| Some(object))
+    }
+
+    pub fn node(&self, id: u32) -> Option<NodeInfoSnapshot> {
+        match self.object(id)? {
+            GraphObject::Node(info) => Some(info),
+            _ => None,
+        }
+    }
+
+    pub fn port(&self, id: u32) -> Option<PortInfoSnapshot> {
+        match self.object(id)? {
+            GraphObject::Port(info) => Some(info),
+            _ => None,
+        }
+    }
+
+    pub fn link(&self, id: u32) -> Option<LinkInfoSnapshot> {
+        match self.object(id)? {
+            GraphObject::Link(info) => Some(info),
+            _ => None,
+        }
+    }
+
+    pub fn device(&self, id: u32) -> Option<DeviceInfoSnapshot> {
+        match self.object(id)? {
+            GraphObject::Device(info) => Some(info),
+            _ => None,
+        }
+    }
+
+    /// Get all the nodes, ordered by id.
+    pub fn nodes(&self) -> Vec<NodeInfoSnapshot> {
+        self.inner.objects.borrow().nodes()
+    }
+
+    /// Get all the ports, ordered by id.
+    pub fn ports(&self) -> Vec<PortInfoSnapshot> {
+        self.inner.objects(|object| match object {
+            GraphObject::Port(info) => Some(info),
+            _ => None,
+        })
+    }
+
+    /// Get all the links, ordered by id.
+    pub fn links(&self) -> Vec<LinkInfoSnapshot> {
+        self.inner.objects(|object| match object {
+            GraphObject::Link(info) => Some(info),
+            _ => None,
+        })
+    }
+
+    /// Get all the devices, ordered by id.
+    pub fn devices(&self) -> Vec<DeviceInfoSnapshot> {
+        self.inner.objects(|object| match object {
+            GraphObject::Device(info) => Some(info),
+            _ => None,
+        })
+    }
+
+    /// Find the node with the given [`NODE_NAME`](`keys::NODE_NAME`).
+    ///
+    /// If several nodes share the name, the one with the lowest id is returned.
+    pub fn node_by_name(&self, name: &str) -> Option<NodeInfoSnapshot> {
+        self.inner.objects.borrow().node_by_name(name)
+    }
+
+    /// Get the nodes with the given [`MEDIA_CLASS`](`keys::MEDIA_CLASS`), such as `Audio/Sink`.
+    pub fn nodes_by_media_class(&self, media_class: &str) -> Vec<NodeInfoSnapshot> {
+        self.inner
+            .objects
+            .borrow()
+            .nodes_by_media_class(media_class)
+    }
+
+    /// Get the ports of the node `node_id` in the given direction.
+    pub fn node_ports(&self, node_id: u32, direction: Direction) -> Vec<PortInfoSnapshot> {
+        self.inner.objects.borrow().node_ports(node_id, direction)
+    }
+
+    /// Get the links from the node `output_node_id` to the node `input_node_id`.
+    pub fn links_between(&self, output_node_id: u32, input_node_id: u32) -> Vec<LinkInfoSnapshot> {
+        self.inner
+            .objects
+            .borrow()
+            .links_between(output_node_id, input_node_id)
+    }
+}
+
+fn node_prop<'a>(node: &'a NodeInfoSnapshot, key: &str) -> Option<&'a str> {
+    node.props.get(key).map(String::as_str)
+}
+
+fn port_node_id(port: &PortInfoSnapshot) -> Option<u32> {
+    port.props.get(*keys::NODE_ID)?.parse().ok()
+}
+
+pub struct GraphListenerLocalBuilder<'a> {
+    graph: &'a Graph,
+    cbs: ListenerLocalCallbacks,
+}
+
+/// A listener of the changes of a [`Graph`], removed when dropped.
+pub struct GraphListener {
+    _cbs: Rc<ListenerLocalCallbacks>,
+}
+
+impl<'a> GraphListenerLocalBuilder<'a> {
+    /// Called when the first info of an object was received.
+    #[must_use]
+    pub fn added<F>(mut self, added: F) -> Self
+    where
+        F: Fn(&GraphObject) + 'static,
+    {
+        self.cbs.added = Some(Box::new(added));
+        self
+    }
+
+    /// Called when the info of an object was updated.
+    ///
+    /// The [change mask](`GraphObject::change_mask`) of the object tells which fields changed.
+    #[must_use]
+    pub fn changed<F>(mut self, changed: F) -> Self
+    where
+        F: Fn(&GraphObject) + 'static,
+    {
+        self.cbs.changed = Some(Box::new(changed));
+        self
+    }
+
+    /// Called with the last info of an object when it was removed from the registry.
+    #[must_use]
+    pub fn removed<F>(mut self, removed: F) -> Self
+    where
+        F: Fn(&GraphObject) + 'static,
+    {
+        self.cbs.removed = Some(Box::new(removed));
+        self
+    }
+
+    #[must_use]
+    pub fn register(self) -> GraphListener {
+        GraphListener {
+            _cbs: self.graph.inner.listeners.register(self.cbs),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use spa::static_dict;
+
+    use super::*;
+    use crate::{
+        link::LinkChangeMask,
+        node::NodeChangeMask,
+        snapshot::{
+            test_utils::{link_info, node_info, port_info, RawInfo},
+            InfoSnapshot,
+        },
+    };
+
+    /// Apply an info event to the object `id`, like the info listeners do.
+    fn update<S: InfoSnapshot>(
+        objects: &mut Objects,
+        id: u32,
+        info: &S::Info,
+        wrap: fn(S) -> GraphObject,
+        get: fn(&mut GraphObject) -> Option<&mut S>,
+    ) -> (GraphObject, bool) {
+        objects
+            .update(id, |object| {
+                snapshot::update_object(object, info, wrap, get)
+            })
+            .unwrap()
+    }
+
+    fn ids<T>(objects: Vec<T>, id: fn(&T) -> u32) -> Vec<u32> {
+        objects.iter().map(id).collect()
+    }
+
+    #[test]
+    fn lookups() {
+        let sink = static_dict! { "node.name" => "sink", "media.class" => "Audio/Sink" };
+        let source = static_dict! { "node.name" => "source", "media.class" => "Audio/Source" };
+        let other_sink = static_dict! { "node.name" => "sink", "media.class" => "Audio/Sink" };
+        let sink_input = static_dict! { "node.id" => "40", "port.name" => "playback_FL" };
+        let sink_monitor = static_dict! { "node.id" => "40", "port.name" => "monitor_FL" };
+        let source_output = static_dict! { "node.id" => "41", "port.name" => "capture_FL" };
+        let link = static_dict! { "link.passive" => "false" };
+        let mut objects = Objects::default();
+
+        let raw = node_info(40, &sink);
+        let (_, added) = update(
+            &mut objects,
+            40,
+            raw.info(),
+            GraphObject::Node,
+            GraphObject::node_mut,
+        );
+        assert!(added);
+        // The props are not sent again by the later events, so they are kept.
+        let mut partial = raw.partial(NodeChangeMask::STATE.bits());
+        partial.state = pw_sys::pw_node_state_PW_NODE_STATE_RUNNING;
+        let (object, added) = update(
+            &mut objects,
+            40,
+            partial.info(),
+            GraphObject::Node,
+            GraphObject::node_mut,
+        );
+        assert!(!added);
+        assert_eq!(object.change_mask(), NodeChangeMask::STATE.bits());
+
+        for (id, props) in [(41, &source), (42, &other_sink)] {
+            let raw = node_info(id, props);
+            update(
+                &mut objects,
+                id,
+                raw.info(),
+                GraphObject::Node,
+                GraphObject::node_mut,
+            );
+        }
+        for (id, direction, props) in [
+            (50, Direction::Input, &sink_input),
+            (51, Direction::Output, &sink_monitor),
+            (52, Direction::Output, &source_output),
+        ] {
+            let raw = port_info(id, direction, props);
+            update(
+                &mut objects,
+                id,
+                raw.info(),
+                GraphObject::Port,
+                GraphObject::port_mut,
+            );
+        }
+        let raw = link_info(60, (41, 52), (40, 50), &link);
+        update(
+            &mut objects,
+            60,
+            raw.info(),
+            GraphObject::Link,
+            GraphObject::link_mut,
+        );
+        let partial = raw.partial(LinkChangeMask::STATE.bits());
+        update(
+            &mut objects,
+            60,
+            partial.info(),
+            GraphObject::Link,
+            GraphObject::link_mut,
+        );
+
+        // The node with the lowest id is returned when several share the name.
+        let sink = objects.node_by_name("sink").unwrap();
+        assert_eq!(sink.id, 40);
+        assert_eq!(sink.state, snapshot::NodeStateSnapshot::Running);
+        assert!(objects.node_by_name("none").is_none());
+
+        assert_eq!(
+            ids(objects.nodes_by_media_class("Audio/Sink"), |node| node.id),
+            [40, 42]
+        );
+        assert_eq!(
+            ids(objects.nodes_by_media_class("Audio/Source"), |node| node.id),
+            [41]
+        );
+
+        assert_eq!(
+            ids(objects.node_ports(40, Direction::Input), |port| port.id),
+            [50]
+        );
+        assert_eq!(
+            ids(objects.node_ports(40, Direction::Output), |port| port.id),
+            [51]
+        );
+        assert!(objects.node_ports(42, Direction::Input).is_empty());
+
+        assert_eq!(ids(objects.links_between(41, 40), |link| link.id), [60]);
+        assert!(objects.links_between(40, 41).is_empty());
+    }
+}
@@ -0,0 +1,67 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+use std::{ffi::CStr, fmt, ptr};
+
+use crate::properties::PropertiesRef;
+
+/// The core object of a context, exposing the context to clients.
+///
+/// It is owned by its context and is available with
+/// [`ContextRef::default_core`](`crate::context::ContextRef::default_core`).
+#[repr(transparent)]
+pub struct ImplCoreRef(pw_sys::pw_impl_core);
+
+impl ImplCoreRef {
+    pub fn as_raw(&self) -> &pw_sys::pw_impl_core {
+        &self.0
+    }
+
+    pub fn as_raw_ptr(&self) -> *mut pw_sys::pw_impl_core {
+        std::ptr::addr_of!(self.0).cast_mut()
+    }
+
+    /// The name of the core, as exposed to clients.
+    ///
+    /// The name comes from the configuration and is not necessarily valid UTF-8.
+    pub fn name(&self) -> &CStr {
+        unsafe {
+            let info = pw_sys::pw_impl_core_get_info(self.as_raw_ptr());
+            let name = info.as_ref().expect("core info is NULL").name;
+            CStr::from_ptr(name)
+        }
+    }
+
+    pub fn properties(&self) -> &PropertiesRef {
+        unsafe {
+            let props = pw_sys::pw_impl_core_get_properties(self.as_raw_ptr());
+            ptr::NonNull::new(props.cast_mut())
+                .expect("core properties is NULL")
+                .cast()
+                .as_ref()
+        }
+    }
+
+    pub fn update_properties(&self, properties: &spa::utils::dict::DictRef) {
+        unsafe {
+            pw_sys::pw_impl_core_update_properties(self.as_raw_ptr(), properties.as_raw_ptr());
+        }
+    }
+
+    /// The id of the global exposing the core, or [`None`] if it is not registered.
+    pub fn global_id(&self) -> Option<u32> {
+        unsafe {
+            let global = pw_sys::pw_impl_core_get_global(self.as_raw_ptr());
+            (!global.is_null()).then(|| pw_sys::pw_global_get_id(global))
+        }
+    }
+}
+
+impl fmt::Debug for ImplCoreRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImplCoreRef")
+            .field("name", &self.name())
+            .field("global-id", &self.global_id())
+            .finish()
+    }
+}
@@ -0,0 +1,66 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+use std::{fmt, ptr};
+
+use crate::{factory::FactoryInfoRef, properties::PropertiesRef};
+
+/// A factory registered in the local context, usually by a loaded module.
+///
+/// Factories are owned by the module that registered them, so they are only available by reference,
+/// see [`ContextRef::find_factory`](`crate::context::ContextRef::find_factory`).
+#[repr(transparent)]
+pub struct ImplFactoryRef(pw_sys::pw_impl_factory);
+
+impl ImplFactoryRef {
+    pub fn as_raw(&self) -> &pw_sys::pw_impl_factory {
+        &self.0
+    }
+
+    pub fn as_raw_ptr(&self) -> *mut pw_sys::pw_impl_factory {
+        std::ptr::addr_of!(self.0).cast_mut()
+    }
+
+    /// The info of the factory, as exposed to clients.
+    pub fn info(&self) -> &FactoryInfoRef {
+        unsafe {
+            let info = pw_sys::pw_impl_factory_get_info(self.as_raw_ptr());
+            ptr::NonNull::new(info.cast_mut())
+                .expect("factory info is NULL")
+                .cast()
+                .as_ref()
+        }
+    }
+
+    pub fn properties(&self) -> &PropertiesRef {
+        unsafe {
+            let props = pw_sys::pw_impl_factory_get_properties(self.as_raw_ptr());
+            ptr::NonNull::new(props.cast_mut())
+                .expect("factory properties is NULL")
+                .cast()
+                .as_ref()
+        }
+    }
+
+    pub fn update_properties(&self, properties: &spa::utils::dict::DictRef) {
+        unsafe {
+            pw_sys::pw_impl_factory_update_properties(self.as_raw_ptr(), properties.as_raw_ptr());
+        }
+    }
+
+    /// The id of the global exposing the factory, or [`None`] if it is not registered.
+    pub fn global_id(&self) -> Option<u32> {
+        unsafe {
+            let global = pw_sys::pw_impl_factory_get_global(self.as_raw_ptr());
+            (!global.is_null()).then(|| pw_sys::pw_global_get_id(global))
+        }
+    }
+}
+
+impl fmt::Debug for ImplFactoryRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImplFactoryRef")
+            .field("info", self.info())
+            .finish()
+    }
+}
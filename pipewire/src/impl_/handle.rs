@@ -0,0 +1,45 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+use std::{ffi::CStr, ptr};
+
+use libc::c_void;
+use spa::utils::result::SpaResult;
+
+use crate::Error;
+
+/// A SPA plugin instance loaded with `pw_context_load_spa_handle()`, unloaded when dropped.
+#[derive(Debug)]
+pub(crate) struct SpaHandle {
+    ptr: ptr::NonNull<spa_sys::spa_handle>,
+}
+
+impl SpaHandle {
+    pub(crate) fn from_ptr(ptr: ptr::NonNull<spa_sys::spa_handle>) -> Self {
+        Self { ptr }
+    }
+
+    /// Get the interface of type `type_` implemented by the plugin.
+    pub(crate) fn interface(&self, type_: &CStr) -> Result<ptr::NonNull<c_void>, Error> {
+        let mut iface: *mut c_void = ptr::null_mut();
+
+        let res = unsafe {
+            let handle = self.ptr.as_ptr();
+            let get_interface = (*handle)
+                .get_interface
+                .expect("spa_handle has no get_interface method");
+            get_interface(handle, type_.as_ptr(), &mut iface)
+        };
+        SpaResult::from_c(res).into_sync_result()?;
+
+        ptr::NonNull::new(iface).ok_or(Error::CreationFailed)
+    }
+}
+
+impl Drop for SpaHandle {
+    fn drop(&mut self) {
+        unsafe {
+            pw_sys::pw_unload_spa_handle(self.ptr.as_ptr());
+        }
+    }
+}
@@ -0,0 +1,162 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+use std::{cell::Cell, fmt, mem, pin::Pin, ptr};
+
+use libc::c_void;
+use spa::utils::result::SpaResult;
+
+use crate::{
+    context::Context, impl_::ImplPortRef, link::LinkInfoRef, properties::Properties, Error,
+};
+
+/// A link between two ports of nodes implemented in the local process.
+#[repr(transparent)]
+pub struct ImplLinkRef(pw_sys::pw_impl_link);
+
+impl ImplLinkRef {
+    pub fn as_raw(&self) -> &pw_sys::pw_impl_link {
+        &self.0
+    }
+
+    pub fn as_raw_ptr(&self) -> *mut pw_sys::pw_impl_link {
+        std::ptr::addr_of!(self.0).cast_mut()
+    }
+
+    /// The info of the link, as exposed to clients.
+    pub fn info(&self) -> &LinkInfoRef {
+        unsafe {
+            let info = pw_sys::pw_impl_link_get_info(self.as_raw_ptr());
+            ptr::NonNull::new(info.cast_mut())
+                .expect("link info is NULL")
+                .cast()
+                .as_ref()
+        }
+    }
+
+    /// Complete the configuration of the link and expose it as a global,
+    /// making it visible to clients.
+    pub fn register(&self, properties: Option<Properties>) -> Result<(), Error> {
+        let properties = properties.map_or(ptr::null_mut(), |p| p.into_raw());
+
+        let res = unsafe { pw_sys::pw_impl_link_register(self.as_raw_ptr(), properties) };
+        SpaResult::from_c(res).into_sync_result()?;
+
+        Ok(())
+    }
+
+    /// The id of the global exposing the link, or [`None`] if it was not
+    /// [registered](`Self::register`) yet.
+    pub fn global_id(&self) -> Option<u32> {
+        unsafe {
+            let global = pw_sys::pw_impl_link_get_global(self.as_raw_ptr());
+            (!global.is_null()).then(|| pw_sys::pw_global_get_id(global))
+        }
+    }
+
+    pub fn output(&self) -> &ImplPortRef {
+        unsafe {
+            let port = pw_sys::pw_impl_link_get_output(self.as_raw_ptr());
+            ptr::NonNull::new(port)
+                .expect("link output port is NULL")
+                .cast()
+                .as_ref()
+        }
+    }
+
+    pub fn input(&self) -> &ImplPortRef {
+        unsafe {
+            let port = pw_sys::pw_impl_link_get_input(self.as_raw_ptr());
+            ptr::NonNull::new(port)
+                .expect("link input port is NULL")
+                .cast()
+                .as_ref()
+        }
+    }
+}
+
+impl fmt::Debug for ImplLinkRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImplLinkRef")
+            .field("info", self.info())
+            .finish()
+    }
+}
+
+/// An owned link between two ports of nodes implemented in the local process,
+/// created with [`Context::create_link`].
+///
+/// PipeWire destroys a link when one of its ports is destroyed, so the link may be gone
+/// before this is dropped. Use [`ImplLink::get`] to access it, which returns [`None`] in that case.
+/// Otherwise, the link is destroyed when this is dropped.
+pub struct ImplLink {
+    ptr: ptr::NonNull<pw_sys::pw_impl_link>,
+    // Need to stay allocated while the listener is registered
+    #[allow(dead_code)]
+    events: Pin<Box<pw_sys::pw_impl_link_events>>,
+    listener: Pin<Box<spa_sys::spa_hook>>,
+    destroyed: Pin<Box<Cell<bool>>>,
+    /// The link must be destroyed before the context it was created in.
+    _context: Context,
+}
+
+impl ImplLink {
+    pub(crate) fn from_ptr(ptr: ptr::NonNull<pw_sys::pw_impl_link>, context: Context) -> Self {
+        unsafe extern "C" fn link_destroy(data: *mut c_void) {
+            let destroyed = (data as *const Cell<bool>).as_ref().unwrap();
+            destroyed.set(true);
+        }
+
+        let events = unsafe {
+            let mut events: Pin<Box<pw_sys::pw_impl_link_events>> = Box::pin(mem::zeroed());
+            events.version = pw_sys::PW_VERSION_IMPL_LINK_EVENTS;
+            events.destroy = Some(link_destroy);
+
+            events
+        };
+
+        let destroyed = Box::pin(Cell::new(false));
+        let mut listener: Pin<Box<spa_sys::spa_hook>> = Box::pin(unsafe { mem::zeroed() });
+
+        unsafe {
+            let listener_ptr: *mut spa_sys::spa_hook = listener.as_mut().get_unchecked_mut();
+            pw_sys::pw_impl_link_add_listener(
+                ptr.as_ptr(),
+                listener_ptr.cast(),
+                events.as_ref().get_ref(),
+                destroyed.as_ref().get_ref() as *const Cell<bool> as *mut c_void,
+            );
+        }
+
+        Self {
+            ptr,
+            events,
+            listener,
+            destroyed,
+            _context: context,
+        }
+    }
+
+    /// The link, or [`None`] if it was destroyed because one of its ports was destroyed.
+    pub fn get(&self) -> Option<&ImplLinkRef> {
+        (!self.destroyed.get()).then(|| unsafe { self.ptr.cast::<ImplLinkRef>().as_ref() })
+    }
+}
+
+impl fmt::Debug for ImplLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImplLink")
+            .field("link", &self.get())
+            .finish()
+    }
+}
+
+impl Drop for ImplLink {
+    fn drop(&mut self) {
+        // The listener list of a destroyed link is gone along with the link.
+        if !self.destroyed.get() {
+            spa::utils::hook::remove(*self.listener);
+            unsafe { pw_sys::pw_impl_link_destroy(self.ptr.as_ptr()) }
+        }
+    }
+}
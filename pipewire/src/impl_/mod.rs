@@ -0,0 +1,30 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Objects implemented in the local process.
+//!
+//! While the proxies found in the rest of the crate represent objects living on a remote,
+//! the types of this module are objects implemented by the local [`Context`](`crate::context::Context`),
+//! such as modules loaded into the current process, or nodes and links of a graph hosted by it.
+//!
+//! Objects created by the application, such as [`ImplModule`], [`ImplNode`] and [`ImplLink`], are owned
+//! handles destroying the object when dropped and keeping the context alive until then.
+//! Objects owned by another object, such as ports owned by their node, are only available by reference.
+//!
+//! Local objects are not visible to clients until they are registered as globals,
+//! see [`ImplNodeRef::register`] and [`ImplLinkRef::register`].
+
+mod core;
+pub use self::core::*;
+mod factory;
+pub use factory::*;
+mod handle;
+pub(crate) use handle::SpaHandle;
+mod link;
+pub use link::*;
+mod module;
+pub use module::*;
+mod node;
+pub use node::*;
+mod port;
+pub use port::*;
@@ -0,0 +1,142 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+use std::{cell::Cell, fmt, mem, pin::Pin, ptr};
+
+use libc::c_void;
+
+use crate::{context::Context, module::ModuleInfoRef, properties::PropertiesRef};
+
+/// A module loaded in the local process.
+#[repr(transparent)]
+pub struct ImplModuleRef(pw_sys::pw_impl_module);
+
+impl ImplModuleRef {
+    pub fn as_raw(&self) -> &pw_sys::pw_impl_module {
+        &self.0
+    }
+
+    pub fn as_raw_ptr(&self) -> *mut pw_sys::pw_impl_module {
+        std::ptr::addr_of!(self.0).cast_mut()
+    }
+
+    /// The info of the module, as exposed to clients.
+    pub fn info(&self) -> &ModuleInfoRef {
+        unsafe {
+            let info = pw_sys::pw_impl_module_get_info(self.as_raw_ptr());
+            ptr::NonNull::new(info.cast_mut())
+                .expect("module info is NULL")
+                .cast()
+                .as_ref()
+        }
+    }
+
+    /// The id of the global exposing the module.
+    pub fn global_id(&self) -> u32 {
+        unsafe {
+            let global = pw_sys::pw_impl_module_get_global(self.as_raw_ptr());
+            pw_sys::pw_global_get_id(global)
+        }
+    }
+
+    pub fn properties(&self) -> &PropertiesRef {
+        unsafe {
+            let props = pw_sys::pw_impl_module_get_properties(self.as_raw_ptr());
+            ptr::NonNull::new(props.cast_mut())
+                .expect("module properties is NULL")
+                .cast()
+                .as_ref()
+        }
+    }
+
+    pub fn update_properties(&self, properties: &spa::utils::dict::DictRef) {
+        unsafe {
+            pw_sys::pw_impl_module_update_properties(self.as_raw_ptr(), properties.as_raw_ptr());
+        }
+    }
+}
+
+impl fmt::Debug for ImplModuleRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImplModuleRef")
+            .field("info", self.info())
+            .finish()
+    }
+}
+
+/// An owned module loaded in the local process, created with [`Context::load_module`].
+///
+/// A module may destroy itself, for example when the resources it manages go away,
+/// so it may be gone before this is dropped. Use [`ImplModule::get`] to access it, which
+/// returns [`None`] in that case. Otherwise, the module is unloaded when this is dropped.
+pub struct ImplModule {
+    ptr: ptr::NonNull<pw_sys::pw_impl_module>,
+    // Need to stay allocated while the listener is registered
+    #[allow(dead_code)]
+    events: Pin<Box<pw_sys::pw_impl_module_events>>,
+    listener: Pin<Box<spa_sys::spa_hook>>,
+    destroyed: Pin<Box<Cell<bool>>>,
+    /// The module must be destroyed before the context it was loaded in.
+    _context: Context,
+}
+
+impl ImplModule {
+    pub(crate) fn from_ptr(ptr: ptr::NonNull<pw_sys::pw_impl_module>, context: Context) -> Self {
+        unsafe extern "C" fn module_destroy(data: *mut c_void) {
+            let destroyed = (data as *const Cell<bool>).as_ref().unwrap();
+            destroyed.set(true);
+        }
+
+        let events = unsafe {
+            let mut events: Pin<Box<pw_sys::pw_impl_module_events>> = Box::pin(mem::zeroed());
+            events.version = pw_sys::PW_VERSION_IMPL_MODULE_EVENTS;
+            events.destroy = Some(module_destroy);
+
+            events
+        };
+
+        let destroyed = Box::pin(Cell::new(false));
+        let mut listener: Pin<Box<spa_sys::spa_hook>> = Box::pin(unsafe { mem::zeroed() });
+
+        unsafe {
+            let listener_ptr: *mut spa_sys::spa_hook = listener.as_mut().get_unchecked_mut();
+            pw_sys::pw_impl_module_add_listener(
+                ptr.as_ptr(),
+                listener_ptr.cast(),
+                events.as_ref().get_ref(),
+                destroyed.as_ref().get_ref() as *const Cell<bool> as *mut c_void,
+            );
+        }
+
+        Self {
+            ptr,
+            events,
+            listener,
+            destroyed,
+            _context: context,
+        }
+    }
+
+    /// The module, or [`None`] if it destroyed itself.
+    pub fn get(&self) -> Option<&ImplModuleRef> {
+        (!self.destroyed.get()).then(|| unsafe { self.ptr.cast::<ImplModuleRef>().as_ref() })
+    }
+}
+
+impl fmt::Debug for ImplModule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImplModule")
+            .field("module", &self.get())
+            .finish()
+    }
+}
+
+impl Drop for ImplModule {
+    fn drop(&mut self) {
+        // The listener list of a destroyed module is gone along with the module.
+        if !self.destroyed.get() {
+            spa::utils::hook::remove(*self.listener);
+            unsafe { pw_sys::pw_impl_module_destroy(self.ptr.as_ptr()) }
+        }
+    }
+}
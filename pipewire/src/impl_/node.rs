@@ -0,0 +1,227 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+use std::{cell::Cell, fmt, mem, pin::Pin, ptr};
+
+use libc::{c_int, c_void};
+use spa::utils::result::SpaResult;
+
+use crate::{
+    context::Context,
+    impl_::{handle::SpaHandle, ImplPortRef},
+    node::NodeInfoRef,
+    properties::{Properties, PropertiesRef},
+    spa::utils::Direction,
+    Error,
+};
+
+/// A node implemented in the local process.
+#[repr(transparent)]
+pub struct ImplNodeRef(pw_sys::pw_impl_node);
+
+impl ImplNodeRef {
+    pub fn as_raw(&self) -> &pw_sys::pw_impl_node {
+        &self.0
+    }
+
+    pub fn as_raw_ptr(&self) -> *mut pw_sys::pw_impl_node {
+        std::ptr::addr_of!(self.0).cast_mut()
+    }
+
+    /// The info of the node, as exposed to clients.
+    pub fn info(&self) -> &NodeInfoRef {
+        unsafe {
+            let info = pw_sys::pw_impl_node_get_info(self.as_raw_ptr());
+            ptr::NonNull::new(info.cast_mut())
+                .expect("node info is NULL")
+                .cast()
+                .as_ref()
+        }
+    }
+
+    pub fn properties(&self) -> &PropertiesRef {
+        unsafe {
+            let props = pw_sys::pw_impl_node_get_properties(self.as_raw_ptr());
+            ptr::NonNull::new(props.cast_mut())
+                .expect("node properties is NULL")
+                .cast()
+                .as_ref()
+        }
+    }
+
+    pub fn update_properties(&self, properties: &spa::utils::dict::DictRef) {
+        unsafe {
+            pw_sys::pw_impl_node_update_properties(self.as_raw_ptr(), properties.as_raw_ptr());
+        }
+    }
+
+    /// Complete the initialization of the node and expose it as a global,
+    /// making it visible to clients.
+    pub fn register(&self, properties: Option<Properties>) -> Result<(), Error> {
+        let properties = properties.map_or(ptr::null_mut(), |p| p.into_raw());
+
+        let res = unsafe { pw_sys::pw_impl_node_register(self.as_raw_ptr(), properties) };
+        SpaResult::from_c(res).into_sync_result()?;
+
+        Ok(())
+    }
+
+    /// The id of the global exposing the node, or [`None`] if it was not
+    /// [registered](`Self::register`) yet.
+    pub fn global_id(&self) -> Option<u32> {
+        unsafe {
+            let global = pw_sys::pw_impl_node_get_global(self.as_raw_ptr());
+            (!global.is_null()).then(|| pw_sys::pw_global_get_id(global))
+        }
+    }
+
+    /// Set the node active, starting format negotiation with the linked nodes
+    /// and data transport.
+    pub fn set_active(&self, active: bool) -> Result<(), Error> {
+        let res = unsafe { pw_sys::pw_impl_node_set_active(self.as_raw_ptr(), active) };
+        SpaResult::from_c(res).into_sync_result()?;
+
+        Ok(())
+    }
+
+    pub fn is_active(&self) -> bool {
+        unsafe { pw_sys::pw_impl_node_is_active(self.as_raw_ptr()) }
+    }
+
+    /// Find the port of the node with `direction` and `port_id`.
+    ///
+    /// If `port_id` is [`None`], any port is returned, preferably an unlinked one.
+    pub fn find_port(&self, direction: Direction, port_id: Option<u32>) -> Option<&ImplPortRef> {
+        unsafe {
+            let port = pw_sys::pw_impl_node_find_port(
+                self.as_raw_ptr(),
+                direction.as_raw(),
+                port_id.unwrap_or(crate::constants::ID_ANY),
+            );
+            ptr::NonNull::new(port).map(|port| port.cast().as_ref())
+        }
+    }
+
+    /// All the ports of the node in `direction`.
+    pub fn ports(&self, direction: Direction) -> Vec<&ImplPortRef> {
+        unsafe extern "C" fn collect_port(
+            data: *mut c_void,
+            port: *mut pw_sys::pw_impl_port,
+        ) -> c_int {
+            let ports = (data as *mut Vec<*mut pw_sys::pw_impl_port>)
+                .as_mut()
+                .unwrap();
+            ports.push(port);
+            0
+        }
+
+        let mut ports: Vec<*mut pw_sys::pw_impl_port> = Vec::new();
+        unsafe {
+            pw_sys::pw_impl_node_for_each_port(
+                self.as_raw_ptr(),
+                direction.as_raw(),
+                Some(collect_port),
+                std::ptr::addr_of_mut!(ports).cast(),
+            );
+        }
+
+        ports
+            .into_iter()
+            .filter_map(|port| ptr::NonNull::new(port).map(|port| unsafe { port.cast().as_ref() }))
+            .collect()
+    }
+}
+
+impl fmt::Debug for ImplNodeRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImplNodeRef")
+            .field("info", self.info())
+            .field("active", &self.is_active())
+            .finish()
+    }
+}
+
+/// An owned node implemented in the local process.
+///
+/// PipeWire may destroy the node before this is dropped, for example when the module
+/// or the client it belongs to goes away. Use [`ImplNode::get`] to access it, which returns
+/// [`None`] in that case. Otherwise, the node is destroyed when this is dropped, which also
+/// destroys its ports and the links connected to them.
+pub struct ImplNode {
+    ptr: ptr::NonNull<pw_sys::pw_impl_node>,
+    // Need to stay allocated while the listener is registered
+    #[allow(dead_code)]
+    events: Pin<Box<pw_sys::pw_impl_node_events>>,
+    listener: Pin<Box<spa_sys::spa_hook>>,
+    destroyed: Pin<Box<Cell<bool>>>,
+    /// The plugin implementing the node, unloaded after the node is destroyed.
+    _handle: Option<SpaHandle>,
+    /// The node must be destroyed before the context it was created in.
+    _context: Context,
+}
+
+impl ImplNode {
+    pub(crate) fn from_ptr(
+        ptr: ptr::NonNull<pw_sys::pw_impl_node>,
+        handle: Option<SpaHandle>,
+        context: Context,
+    ) -> Self {
+        unsafe extern "C" fn node_destroy(data: *mut c_void) {
+            let destroyed = (data as *const Cell<bool>).as_ref().unwrap();
+            destroyed.set(true);
+        }
+
+        let events = unsafe {
+            let mut events: Pin<Box<pw_sys::pw_impl_node_events>> = Box::pin(mem::zeroed());
+            events.version = pw_sys::PW_VERSION_IMPL_NODE_EVENTS;
+            events.destroy = Some(node_destroy);
+
+            events
+        };
+
+        let destroyed = Box::pin(Cell::new(false));
+        let mut listener: Pin<Box<spa_sys::spa_hook>> = Box::pin(unsafe { mem::zeroed() });
+
+        unsafe {
+            let listener_ptr: *mut spa_sys::spa_hook = listener.as_mut().get_unchecked_mut();
+            pw_sys::pw_impl_node_add_listener(
+                ptr.as_ptr(),
+                listener_ptr.cast(),
+                events.as_ref().get_ref(),
+                destroyed.as_ref().get_ref() as *const Cell<bool> as *mut c_void,
+            );
+        }
+
+        Self {
+            ptr,
+            events,
+            listener,
+            destroyed,
+            _handle: handle,
+            _context: context,
+        }
+    }
+
+    /// The node, or [`None`] if it was destroyed by PipeWire.
+    pub fn get(&self) -> Option<&ImplNodeRef> {
+        (!self.destroyed.get()).then(|| unsafe { self.ptr.cast::<ImplNodeRef>().as_ref() })
+    }
+}
+
+impl fmt::Debug for ImplNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImplNode")
+            .field("node", &self.get())
+            .finish()
+    }
+}
+
+impl Drop for ImplNode {
+    fn drop(&mut self) {
+        // The listener list of a destroyed node is gone along with the node.
+        if !self.destroyed.get() {
+            spa::utils::hook::remove(*self.listener);
+            unsafe { pw_sys::pw_impl_node_destroy(self.ptr.as_ptr()) }
+        }
+    }
+}
@@ -0,0 +1,83 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+use std::{fmt, ptr};
+
+use crate::{
+    impl_::ImplNodeRef, port::PortInfoRef, properties::PropertiesRef, spa::utils::Direction,
+};
+
+/// A port of a node implemented in the local process.
+///
+/// Ports are owned by their node, so they are only available by reference,
+/// see [`ImplNodeRef::ports`] and [`ImplNodeRef::find_port`].
+#[repr(transparent)]
+pub struct ImplPortRef(pw_sys::pw_impl_port);
+
+impl ImplPortRef {
+    pub fn as_raw(&self) -> &pw_sys::pw_impl_port {
+        &self.0
+    }
+
+    pub fn as_raw_ptr(&self) -> *mut pw_sys::pw_impl_port {
+        std::ptr::addr_of!(self.0).cast_mut()
+    }
+
+    /// The id of the port on its node.
+    pub fn id(&self) -> u32 {
+        unsafe { pw_sys::pw_impl_port_get_id(self.as_raw_ptr()) }
+    }
+
+    pub fn direction(&self) -> Direction {
+        unsafe { Direction::from_raw(pw_sys::pw_impl_port_get_direction(self.as_raw_ptr())) }
+    }
+
+    /// The info of the port, as exposed to clients.
+    pub fn info(&self) -> &PortInfoRef {
+        unsafe {
+            let info = pw_sys::pw_impl_port_get_info(self.as_raw_ptr());
+            ptr::NonNull::new(info.cast_mut())
+                .expect("port info is NULL")
+                .cast()
+                .as_ref()
+        }
+    }
+
+    pub fn properties(&self) -> &PropertiesRef {
+        unsafe {
+            let props = pw_sys::pw_impl_port_get_properties(self.as_raw_ptr());
+            ptr::NonNull::new(props.cast_mut())
+                .expect("port properties is NULL")
+                .cast()
+                .as_ref()
+        }
+    }
+
+    pub fn update_properties(&self, properties: &spa::utils::dict::DictRef) {
+        unsafe {
+            pw_sys::pw_impl_port_update_properties(self.as_raw_ptr(), properties.as_raw_ptr());
+        }
+    }
+
+    /// The node the port belongs to, or [`None`] if it was not added to a node yet.
+    pub fn node(&self) -> Option<&ImplNodeRef> {
+        unsafe {
+            let node = pw_sys::pw_impl_port_get_node(self.as_raw_ptr());
+            ptr::NonNull::new(node).map(|node| node.cast().as_ref())
+        }
+    }
+
+    pub fn is_linked(&self) -> bool {
+        unsafe { pw_sys::pw_impl_port_is_linked(self.as_raw_ptr()) > 0 }
+    }
+}
+
+impl fmt::Debug for ImplPortRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImplPortRef")
+            .field("id", &self.id())
+            .field("direction", &self.direction())
+            .field("linked", &self.is_linked())
+            .finish()
+    }
+}
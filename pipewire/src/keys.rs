@@ -48,6 +48,9 @@ key_constant!(SEC_GID, PW_KEY_SEC_GID,
 key_constant!(SEC_LABEL, PW_KEY_SEC_LABEL,
     /// client security label, set by protocol
 );
+key_constant!(SEC_ENGINE, PW_KEY_SEC_ENGINE,
+    /// client secure context engine, set by protocol. This can also be set by a client when making a new security context.
+);
 key_constant!(LIBRARY_NAME_SYSTEM, PW_KEY_LIBRARY_NAME_SYSTEM,
     /// name of the system library to use
 );
@@ -533,6 +536,12 @@ key_constant!(VIDEO_SIZE, PW_KEY_VIDEO_SIZE,
 key_constant!(TARGET_OBJECT, PW_KEY_TARGET_OBJECT,
     /// a target object to link to. This can be and object name or object.serial PIPEWIRE_KEYS_H
 );
+key_constant!(METADATA_NAME, PW_KEY_METADATA_NAME,
+    /// the name of a metadata object
+);
+key_constant!(METADATA_VALUES, PW_KEY_METADATA_VALUES,
+    /// initial metadata values, as a JSON array of objects with `subject`, `key`, `type` and `value`
+);
 
 #[cfg(test)]
 mod tests {
@@ -111,26 +111,39 @@
 pub mod buffer;
 pub mod channel;
 pub mod client;
+pub mod conf;
 pub mod constants;
 pub mod context;
 pub mod core;
+pub mod default_nodes;
 pub mod device;
+pub mod dump;
 pub mod factory;
+pub mod graph;
+pub mod impl_;
 pub mod keys;
 pub mod link;
 pub mod loop_;
 pub mod main_loop;
+pub mod mem;
 pub mod metadata;
 pub mod module;
 pub mod node;
+pub mod permission_manager;
 pub mod permissions;
 pub mod port;
+pub mod profiler;
 pub mod properties;
 pub mod proxy;
 pub mod registry;
+pub mod reply;
+pub mod security_context;
+pub mod session_manager;
+pub mod snapshot;
 pub mod stream;
 pub mod thread_loop;
 pub mod types;
+pub mod volume;
 
 mod error;
 pub use error::*;
@@ -1,17 +1,29 @@
 use std::{
+    cell::Cell,
     ffi::{c_void, CStr},
-    fmt, mem,
+    fmt,
+    future::Future,
+    mem,
     ops::Deref,
     pin::Pin,
     ptr,
+    rc::Rc,
+    task::{Context, Poll},
 };
 
 use bitflags::bitflags;
-use spa::spa_interface_call_method;
+use spa::{spa_interface_call_method, utils::Direction};
 
 use crate::{
+    core::CoreRef,
+    graph::Graph,
+    keys,
+    properties::properties,
     proxy::{Listener, Proxy, ProxyT},
+    reply::Reply,
+    snapshot::PortInfoSnapshot,
     types::ObjectType,
+    Error,
 };
 
 #[derive(Debug)]
@@ -264,6 +276,7 @@ impl AsRef<LinkInfoRef> for LinkInfo {
 
 bitflags! {
     #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct LinkChangeMask: u64 {
         const STATE = pw_sys::PW_LINK_CHANGE_MASK_STATE as u64;
         const FORMAT = pw_sys::PW_LINK_CHANGE_MASK_FORMAT as u64;
@@ -297,3 +310,353 @@ pub enum LinkState<'a> {
     Paused,
     Active,
 }
+
+/// How [`connect_nodes`] links the ports of two nodes.
+#[derive(Debug, Clone, Default)]
+pub struct LinkPolicy {
+    /// Link the monitor ports of the output node, such as the monitor of a sink,
+    /// instead of its regular output ports.
+    pub monitor: bool,
+    /// Keep the links after the handles are dropped or the client disconnects,
+    /// by setting [`OBJECT_LINGER`](`crate::keys::OBJECT_LINGER`) on them.
+    pub linger: bool,
+}
+
+/// A link created by [`connect_nodes`].
+///
+/// Dropping the handle destroys the link, unless it was created with [`LinkPolicy::linger`].
+pub struct NodeLink {
+    // Need to be dropped before the link they are registered on.
+    _listener: LinkListener,
+    _proxy_listener: crate::proxy::ProxyListener,
+    link: Link,
+    output_port_id: u32,
+    input_port_id: u32,
+}
+
+impl NodeLink {
+    pub fn link(&self) -> &Link {
+        &self.link
+    }
+
+    /// The global id of the linked output port.
+    pub fn output_port_id(&self) -> u32 {
+        self.output_port_id
+    }
+
+    /// The global id of the linked input port.
+    pub fn input_port_id(&self) -> u32 {
+        self.input_port_id
+    }
+}
+
+impl fmt::Debug for NodeLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NodeLink")
+            .field("link", &self.link)
+            .field("output_port_id", &self.output_port_id)
+            .field("input_port_id", &self.input_port_id)
+            .finish()
+    }
+}
+
+/// Links being created by [`connect_nodes`].
+///
+/// Resolves to the links once they all became [`LinkState::Active`], or to an error as soon as
+/// one of them failed, in which case all the links are dropped.
+/// Links between nodes that are never started stay paused, so this only resolves once
+/// the nodes are running.
+#[must_use]
+pub struct PendingNodeLinks {
+    links: Option<Vec<NodeLink>>,
+    reply: Reply<()>,
+}
+
+impl PendingNodeLinks {
+    /// The links being created.
+    pub fn links(&self) -> &[NodeLink] {
+        self.links.as_deref().unwrap_or_default()
+    }
+
+    /// Get the links without waiting for them to become active.
+    pub fn into_links(mut self) -> Vec<NodeLink> {
+        self.links.take().unwrap_or_default()
+    }
+
+    /// Block until the links are active by iterating the main loop of `core`,
+    /// see [`Reply::wait`].
+    pub fn wait(mut self, core: &CoreRef) -> Result<Vec<NodeLink>, Error> {
+        let links = self.links.take().unwrap_or_default();
+        self.reply.wait(core).map(|()| links)
+    }
+}
+
+impl Future for PendingNodeLinks {
+    type Output = Result<Vec<NodeLink>, Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match Pin::new(&mut self.reply).poll(cx) {
+            Poll::Ready(res) => {
+                let links = self.links.take().unwrap_or_default();
+                Poll::Ready(res.map(|()| links))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl fmt::Debug for PendingNodeLinks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PendingNodeLinks")
+            .field("links", &self.links())
+            .field("reply", &self.reply)
+            .finish()
+    }
+}
+
+/// Link the output ports of the node `output_node_id` to the input ports of the node
+/// `input_node_id`, like `pw-link` does.
+///
+/// Ports are paired by their [`AUDIO_CHANNEL`](`crate::keys::AUDIO_CHANNEL`) when the nodes
+/// share channel positions, and in the order of their [`PORT_ID`](`crate::keys::PORT_ID`)
+/// otherwise. Ports that are already linked together are skipped.
+///
+/// The ports are looked up in `graph`, which must have received the info of both nodes' ports.
+/// Returns [`Error::NoPortsToLink`] if there is no pair of ports to link.
+pub fn connect_nodes(
+    core: &CoreRef,
+    graph: &Graph,
+    output_node_id: u32,
+    input_node_id: u32,
+    policy: &LinkPolicy,
+) -> Result<PendingNodeLinks, Error> {
+    let outputs = link_ports(
+        graph.node_ports(output_node_id, Direction::Output),
+        Some(policy.monitor),
+    );
+    let inputs = link_ports(graph.node_ports(input_node_id, Direction::Input), None);
+
+    let existing = graph.links_between(output_node_id, input_node_id);
+    let pairs: Vec<_> = pair_ports(&outputs, &inputs)
+        .into_iter()
+        .filter(|&(output, input)| {
+            !existing
+                .iter()
+                .any(|link| link.output_port_id == output && link.input_port_id == input)
+        })
+        .collect();
+    if pairs.is_empty() && existing.is_empty() {
+        return Err(Error::NoPortsToLink {
+            output_node_id,
+            input_node_id,
+        });
+    }
+
+    let seq = core.sync(0)?;
+    let mut reply = Reply::new(seq, ());
+    reply.watch_core(core);
+    let remaining = Rc::new(Cell::new(pairs.len()));
+    if pairs.is_empty() {
+        reply.state().borrow_mut().complete();
+    }
+
+    let links = pairs
+        .into_iter()
+        .map(|(output_port_id, input_port_id)| {
+            let mut props = properties! {
+                *keys::LINK_OUTPUT_NODE => output_node_id.to_string(),
+                *keys::LINK_OUTPUT_PORT => output_port_id.to_string(),
+                *keys::LINK_INPUT_NODE => input_node_id.to_string(),
+                *keys::LINK_INPUT_PORT => input_port_id.to_string(),
+            };
+            if policy.linger {
+                props.insert(*keys::OBJECT_LINGER, "true");
+            }
+            let link: Link = core.create_object("link-factory", &props)?;
+
+            // The links only hold weak references, as the reply does not own them.
+            let state = Rc::downgrade(&reply.state());
+            let remaining = remaining.clone();
+            let active = Cell::new(false);
+            let listener = link
+                .add_listener_local()
+                .info(move |info| {
+                    let Some(state) = state.upgrade() else {
+                        return;
+                    };
+                    match info.state() {
+                        LinkState::Active if !active.replace(true) => {
+                            remaining.set(remaining.get() - 1);
+                            if remaining.get() == 0 {
+                                state.borrow_mut().complete();
+                            }
+                        }
+                        LinkState::Error(message) => {
+                            state.borrow_mut().fail(Error::LinkFailed {
+                                output_port_id,
+                                input_port_id,
+                                message: message.to_owned(),
+                            });
+                        }
+                        _ => {}
+                    }
+                })
+                .register();
+
+            let state = Rc::downgrade(&reply.state());
+            let proxy_listener = link
+                .upcast_ref()
+                .add_listener_local()
+                .error(move |error| {
+                    if let Some(state) = state.upgrade() {
+                        state.borrow_mut().fail(Error::Remote(error.clone()));
+                    }
+                })
+                .register();
+
+            Ok(NodeLink {
+                _listener: listener,
+                _proxy_listener: proxy_listener,
+                link,
+                output_port_id,
+                input_port_id,
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    Ok(PendingNodeLinks {
+        links: Some(links),
+        reply,
+    })
+}
+
+fn port_prop<'a>(port: &'a PortInfoSnapshot, key: &str) -> Option<&'a str> {
+    port.props.get(key).map(String::as_str)
+}
+
+/// The id and channel of the ports to link, in the order of their
+/// [`PORT_ID`](`crate::keys::PORT_ID`).
+///
+/// If `monitor` is set, only the ports whose [`PORT_MONITOR`](`crate::keys::PORT_MONITOR`)
+/// property matches it are kept.
+fn link_ports(ports: Vec<PortInfoSnapshot>, monitor: Option<bool>) -> Vec<(u32, Option<String>)> {
+    let mut ports: Vec<(u32, u32, Option<String>)> = ports
+        .into_iter()
+        .filter(|port| {
+            monitor.map_or(true, |monitor| {
+                (port_prop(port, *keys::PORT_MONITOR) == Some("true")) == monitor
+            })
+        })
+        .map(|port| {
+            let index = port_prop(&port, *keys::PORT_ID)
+                .and_then(|index| index.parse().ok())
+                .unwrap_or(u32::MAX);
+            let channel = port_prop(&port, *keys::AUDIO_CHANNEL).map(str::to_owned);
+            (index, port.id, channel)
+        })
+        .collect();
+    ports.sort_by_key(|&(index, id, _)| (index, id));
+    ports
+        .into_iter()
+        .map(|(_, id, channel)| (id, channel))
+        .collect()
+}
+
+/// Pair output and input ports, given as their id and channel, by channel if they have
+/// channels in common and in order otherwise.
+fn pair_ports(
+    outputs: &[(u32, Option<String>)],
+    inputs: &[(u32, Option<String>)],
+) -> Vec<(u32, u32)> {
+    let by_channel: Vec<_> = outputs
+        .iter()
+        .filter_map(|(output, channel)| {
+            let channel = channel.as_ref()?;
+            inputs
+                .iter()
+                .find(|(_, input_channel)| input_channel.as_ref() == Some(channel))
+                .map(|(input, _)| (*output, *input))
+        })
+        .collect();
+
+    if !by_channel.is_empty() {
+        return by_channel;
+    }
+
+    outputs
+        .iter()
+        .zip(inputs)
+        .map(|((output, _), (input, _))| (*output, *input))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use spa::{
+        param::{ParamInfo, ParamInfoFlags, ParamType},
+        static_dict,
+    };
+
+    use super::*;
+    use crate::{
+        port::PortChangeMask,
+        snapshot::test_utils::{port_info, RawInfo},
+    };
+
+    fn ports(ports: &[(u32, Option<&str>)]) -> Vec<(u32, Option<String>)> {
+        ports
+            .iter()
+            .map(|(id, channel)| (*id, channel.map(str::to_owned)))
+            .collect()
+    }
+
+    #[test]
+    fn pair_by_channel() {
+        let outputs = ports(&[(1, Some("FL")), (2, Some("FR"))]);
+        let inputs = ports(&[(10, Some("FR")), (11, Some("FL")), (12, Some("LFE"))]);
+        assert_eq!(pair_ports(&outputs, &inputs), vec![(1, 11), (2, 10)]);
+    }
+
+    #[test]
+    fn pair_by_order() {
+        let outputs = ports(&[(1, Some("MONO")), (2, None)]);
+        let inputs = ports(&[(10, Some("FL")), (11, Some("FR")), (12, None)]);
+        assert_eq!(pair_ports(&outputs, &inputs), vec![(1, 10), (2, 11)]);
+
+        assert_eq!(pair_ports(&outputs, &[]), vec![]);
+    }
+
+    #[test]
+    fn ports_after_params_update() {
+        let fl = static_dict! { "port.id" => "1", "audio.channel" => "FL" };
+        let fr = static_dict! { "port.id" => "0", "audio.channel" => "FR" };
+        let monitor = static_dict! {
+            "port.id" => "2",
+            "audio.channel" => "FL",
+            "port.monitor" => "true"
+        };
+        let mut params = [ParamInfo::new(ParamType::Format, ParamInfoFlags::READ)];
+
+        let ports = [(10, &fl), (11, &fr), (12, &monitor)].map(|(id, props)| {
+            let raw = port_info(id, Direction::Output, props);
+            let mut port = PortInfoSnapshot::from(raw.info());
+
+            // The props are not sent again when only the params change.
+            let mut update = raw.partial(PortChangeMask::PARAMS.bits());
+            update.params = params.as_mut_ptr().cast();
+            update.n_params = params.len() as u32;
+            port.update(update.info());
+            port
+        });
+
+        assert_eq!(
+            link_ports(ports.to_vec(), Some(false)),
+            vec![(11, Some("FR".to_owned())), (10, Some("FL".to_owned()))]
+        );
+        assert_eq!(
+            link_ports(ports.to_vec(), Some(true)),
+            vec![(12, Some("FL".to_owned()))]
+        );
+        assert_eq!(link_ports(ports.to_vec(), None).len(), 3);
+    }
+}
@@ -0,0 +1,514 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Memory shared with the server.
+//!
+//! Memory is shared as blocks, usually memfds or dmabufs, collected in a [`MemPool`].
+//! Each block has an id unique in its pool, which is how the server refers to it,
+//! for example in the `add_mem` and `remove_mem` events of the core.
+//! Regions of a block are mapped into the process with [`MemBlockRef::map`] or
+//! [`MemPoolRef::map_id`], and unmapped when the returned [`MemMap`] is dropped.
+//!
+//! The memory imported by the core is found in [`CoreRef::mempool`](`crate::core::CoreRef::mempool`).
+
+use std::{
+    ffi::c_void,
+    fmt,
+    marker::PhantomData,
+    ops::Deref,
+    os::fd::{BorrowedFd, IntoRawFd, OwnedFd, RawFd},
+    ptr, slice,
+};
+
+use bitflags::bitflags;
+use nix::errno::Errno;
+use spa::{buffer::DataType, utils::result::SpaResult};
+
+use crate::{properties::Properties, Error};
+
+bitflags! {
+    /// Flags of a [`MemBlockRef`].
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub struct MemBlockFlags: u32 {
+        const READABLE = pw_sys::pw_memblock_flags_PW_MEMBLOCK_FLAG_READABLE;
+        const WRITABLE = pw_sys::pw_memblock_flags_PW_MEMBLOCK_FLAG_WRITABLE;
+        /// Seal the fd so that its size can't change.
+        const SEAL = pw_sys::pw_memblock_flags_PW_MEMBLOCK_FLAG_SEAL;
+        /// Map the whole block when it is created, see [`MemBlockRef::map`].
+        const MAP = pw_sys::pw_memblock_flags_PW_MEMBLOCK_FLAG_MAP;
+        /// Don't close the fd when the block is freed.
+        const DONT_CLOSE = pw_sys::pw_memblock_flags_PW_MEMBLOCK_FLAG_DONT_CLOSE;
+        /// Don't notify the pool listeners about the block.
+        const DONT_NOTIFY = pw_sys::pw_memblock_flags_PW_MEMBLOCK_FLAG_DONT_NOTIFY;
+        /// The block can't be mapped, such as some dmabufs.
+        const UNMAPPABLE = pw_sys::pw_memblock_flags_PW_MEMBLOCK_FLAG_UNMAPPABLE;
+
+        const READWRITE = pw_sys::pw_memblock_flags_PW_MEMBLOCK_FLAG_READWRITE;
+    }
+}
+
+bitflags! {
+    /// Flags of a [`MemMapRef`].
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub struct MemMapFlags: u32 {
+        const READ = pw_sys::pw_memmap_flags_PW_MEMMAP_FLAG_READ;
+        const WRITE = pw_sys::pw_memmap_flags_PW_MEMMAP_FLAG_WRITE;
+        /// Map the region twice, one after the other, as needed for ring buffers.
+        const TWICE = pw_sys::pw_memmap_flags_PW_MEMMAP_FLAG_TWICE;
+        /// Make a private mapping, changes are not shared.
+        const PRIVATE = pw_sys::pw_memmap_flags_PW_MEMMAP_FLAG_PRIVATE;
+        /// Lock the memory into RAM.
+        const LOCKED = pw_sys::pw_memmap_flags_PW_MEMMAP_FLAG_LOCKED;
+
+        const READWRITE = pw_sys::pw_memmap_flags_PW_MEMMAP_FLAG_READWRITE;
+    }
+}
+
+/// A collection of memory blocks.
+#[repr(transparent)]
+pub struct MemPoolRef(pw_sys::pw_mempool);
+
+impl MemPoolRef {
+    pub fn as_raw(&self) -> &pw_sys::pw_mempool {
+        &self.0
+    }
+
+    pub fn as_raw_ptr(&self) -> *mut pw_sys::pw_mempool {
+        ptr::addr_of!(self.0).cast_mut()
+    }
+
+    /// Allocate a new block of `size` bytes of memory of type `type_`,
+    /// usually [`DataType::MemFd`].
+    pub fn alloc(
+        &self,
+        flags: MemBlockFlags,
+        type_: DataType,
+        size: usize,
+    ) -> Result<MemBlock<'_>, Error> {
+        let block = unsafe {
+            pw_sys::pw_mempool_alloc(self.as_raw_ptr(), flags.bits(), type_.as_raw(), size)
+        };
+
+        ptr::NonNull::new(block)
+            .map(|ptr| unsafe { MemBlock::from_ptr(ptr) })
+            .ok_or_else(|| Error::Mem(Errno::last()))
+    }
+
+    /// Import the memory `fd` of type `type_` as a new block.
+    ///
+    /// The block takes ownership of `fd`, so [`MemBlockFlags::DONT_CLOSE`] is ignored.
+    pub fn import(
+        &self,
+        flags: MemBlockFlags,
+        type_: DataType,
+        fd: OwnedFd,
+    ) -> Result<MemBlock<'_>, Error> {
+        let flags = flags - MemBlockFlags::DONT_CLOSE;
+        let fd = fd.into_raw_fd();
+        let block = unsafe {
+            pw_sys::pw_mempool_import(self.as_raw_ptr(), flags.bits(), type_.as_raw(), fd)
+        };
+
+        ptr::NonNull::new(block)
+            .map(|ptr| unsafe { MemBlock::from_ptr(ptr) })
+            .ok_or_else(|| {
+                let errno = Errno::last();
+                let _ = nix::unistd::close(fd);
+                Error::Mem(errno)
+            })
+    }
+
+    /// Import a block of another pool into this pool.
+    pub fn import_block(&self, block: &MemBlockRef) -> Result<MemBlock<'_>, Error> {
+        let block =
+            unsafe { pw_sys::pw_mempool_import_block(self.as_raw_ptr(), block.as_raw_ptr()) };
+
+        ptr::NonNull::new(block)
+            .map(|ptr| unsafe { MemBlock::from_ptr(ptr) })
+            .ok_or_else(|| Error::Mem(Errno::last()))
+    }
+
+    /// Find the block with id `id`.
+    pub fn find_id(&self, id: u32) -> Option<&MemBlockRef> {
+        unsafe {
+            let block = pw_sys::pw_mempool_find_id(self.as_raw_ptr(), id);
+            ptr::NonNull::new(block).map(|block| block.cast().as_ref())
+        }
+    }
+
+    /// Find the block with the fd `fd`.
+    pub fn find_fd(&self, fd: RawFd) -> Option<&MemBlockRef> {
+        unsafe {
+            let block = pw_sys::pw_mempool_find_fd(self.as_raw_ptr(), fd);
+            ptr::NonNull::new(block).map(|block| block.cast().as_ref())
+        }
+    }
+
+    /// Find the block with a mapping containing `ptr`.
+    // `ptr` is only compared with the mappings of the pool, never dereferenced.
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
+    pub fn find_ptr(&self, ptr: *const c_void) -> Option<&MemBlockRef> {
+        unsafe {
+            let block = pw_sys::pw_mempool_find_ptr(self.as_raw_ptr(), ptr);
+            ptr::NonNull::new(block).map(|block| block.cast().as_ref())
+        }
+    }
+
+    /// Map `size` bytes at `offset` of the block with id `id`.
+    pub fn map_id(
+        &self,
+        id: u32,
+        flags: MemMapFlags,
+        offset: u32,
+        size: u32,
+    ) -> Result<MemMap<'_>, Error> {
+        let map = unsafe {
+            pw_sys::pw_mempool_map_id(
+                self.as_raw_ptr(),
+                id,
+                flags.bits(),
+                offset,
+                size,
+                ptr::null_mut(),
+            )
+        };
+
+        ptr::NonNull::new(map)
+            .map(|ptr| unsafe { MemMap::from_ptr(ptr) })
+            .ok_or_else(|| Error::Mem(Errno::last()))
+    }
+
+    /// Remove the block with id `id` from the pool.
+    ///
+    /// The block is freed once all its [`MemBlock`] and [`MemMap`] handles are dropped.
+    pub fn remove_id(&self, id: u32) -> Result<(), Error> {
+        let res = unsafe { pw_sys::pw_mempool_remove_id(self.as_raw_ptr(), id) };
+        SpaResult::from_c(res).into_sync_result()?;
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for MemPoolRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemPoolRef").finish_non_exhaustive()
+    }
+}
+
+/// An owned memory pool, destroyed with all its blocks when dropped.
+pub struct MemPool {
+    ptr: ptr::NonNull<pw_sys::pw_mempool>,
+}
+
+impl MemPool {
+    pub fn new(properties: Option<Properties>) -> Result<Self, Error> {
+        let properties = properties.map_or(ptr::null_mut(), |p| p.into_raw());
+        let pool = unsafe { pw_sys::pw_mempool_new(properties) };
+        let ptr = ptr::NonNull::new(pool).ok_or(Error::CreationFailed)?;
+
+        Ok(Self { ptr })
+    }
+}
+
+impl Deref for MemPool {
+    type Target = MemPoolRef;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.ptr.cast::<MemPoolRef>().as_ref() }
+    }
+}
+
+impl AsRef<MemPoolRef> for MemPool {
+    fn as_ref(&self) -> &MemPoolRef {
+        self.deref()
+    }
+}
+
+impl fmt::Debug for MemPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemPool").finish_non_exhaustive()
+    }
+}
+
+impl Drop for MemPool {
+    fn drop(&mut self) {
+        unsafe { pw_sys::pw_mempool_destroy(self.ptr.as_ptr()) }
+    }
+}
+
+/// A block of memory of a [`MemPool`].
+#[repr(transparent)]
+pub struct MemBlockRef(pw_sys::pw_memblock);
+
+impl MemBlockRef {
+    pub fn as_raw(&self) -> &pw_sys::pw_memblock {
+        &self.0
+    }
+
+    pub fn as_raw_ptr(&self) -> *mut pw_sys::pw_memblock {
+        ptr::addr_of!(self.0).cast_mut()
+    }
+
+    /// The id of the block, unique in its pool.
+    pub fn id(&self) -> u32 {
+        self.0.id
+    }
+
+    pub fn flags(&self) -> MemBlockFlags {
+        MemBlockFlags::from_bits_retain(self.0.flags)
+    }
+
+    pub fn type_(&self) -> DataType {
+        DataType::from_raw(self.0.type_)
+    }
+
+    pub fn fd(&self) -> BorrowedFd<'_> {
+        unsafe { BorrowedFd::borrow_raw(self.0.fd) }
+    }
+
+    pub fn size(&self) -> u32 {
+        self.0.size
+    }
+
+    /// The mapping of the whole block, if it was created with [`MemBlockFlags::MAP`].
+    pub fn mapping(&self) -> Option<&MemMapRef> {
+        ptr::NonNull::new(self.0.map).map(|map| unsafe { map.cast().as_ref() })
+    }
+
+    /// Map `size` bytes of the block at `offset`.
+    ///
+    /// Mappings of the same region are shared, and the region stays mapped until
+    /// all of them are dropped.
+    pub fn map(&self, flags: MemMapFlags, offset: u32, size: u32) -> Result<MemMap<'_>, Error> {
+        let map = unsafe {
+            pw_sys::pw_memblock_map(
+                self.as_raw_ptr(),
+                flags.bits(),
+                offset,
+                size,
+                ptr::null_mut(),
+            )
+        };
+
+        ptr::NonNull::new(map)
+            .map(|ptr| unsafe { MemMap::from_ptr(ptr) })
+            .ok_or_else(|| Error::Mem(Errno::last()))
+    }
+
+    /// Get an owned handle to the block, keeping it alive even if it is removed from `pool`.
+    ///
+    /// Returns [`None`] if the block does not belong to `pool`.
+    pub fn to_owned<'pool>(&self, pool: &'pool MemPoolRef) -> Option<MemBlock<'pool>> {
+        if self.0.pool != pool.as_raw_ptr() {
+            return None;
+        }
+
+        unsafe {
+            (*self.as_raw_ptr()).ref_ += 1;
+            Some(MemBlock::from_ptr(ptr::NonNull::from(self).cast()))
+        }
+    }
+}
+
+impl fmt::Debug for MemBlockRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemBlockRef")
+            .field("id", &self.id())
+            .field("flags", &self.flags())
+            .field("type", &self.type_())
+            .field("fd", &self.0.fd)
+            .field("size", &self.size())
+            .finish()
+    }
+}
+
+/// A reference to a [`MemBlockRef`], which can't outlive its pool.
+///
+/// The block is freed when its last handle is dropped.
+pub struct MemBlock<'pool> {
+    ptr: ptr::NonNull<pw_sys::pw_memblock>,
+    _pool: PhantomData<&'pool MemPoolRef>,
+}
+
+impl MemBlock<'_> {
+    /// # Safety
+    /// `ptr` must point to a valid block, owning one of its references.
+    unsafe fn from_ptr(ptr: ptr::NonNull<pw_sys::pw_memblock>) -> Self {
+        Self {
+            ptr,
+            _pool: PhantomData,
+        }
+    }
+}
+
+impl Deref for MemBlock<'_> {
+    type Target = MemBlockRef;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.ptr.cast::<MemBlockRef>().as_ref() }
+    }
+}
+
+impl AsRef<MemBlockRef> for MemBlock<'_> {
+    fn as_ref(&self) -> &MemBlockRef {
+        self.deref()
+    }
+}
+
+impl Clone for MemBlock<'_> {
+    fn clone(&self) -> Self {
+        unsafe {
+            (*self.ptr.as_ptr()).ref_ += 1;
+            Self::from_ptr(self.ptr)
+        }
+    }
+}
+
+impl fmt::Debug for MemBlock<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.deref(), f)
+    }
+}
+
+impl Drop for MemBlock<'_> {
+    fn drop(&mut self) {
+        // Same as the inline pw_memblock_unref()
+        unsafe {
+            let block = self.ptr.as_ptr();
+            (*block).ref_ -= 1;
+            if (*block).ref_ == 0 {
+                pw_sys::pw_memblock_free(block);
+            }
+        }
+    }
+}
+
+/// A mapped region of a [`MemBlockRef`].
+#[repr(transparent)]
+pub struct MemMapRef(pw_sys::pw_memmap);
+
+impl MemMapRef {
+    pub fn as_raw(&self) -> &pw_sys::pw_memmap {
+        &self.0
+    }
+
+    pub fn as_raw_ptr(&self) -> *mut pw_sys::pw_memmap {
+        ptr::addr_of!(self.0).cast_mut()
+    }
+
+    /// The block the region belongs to.
+    pub fn block(&self) -> &MemBlockRef {
+        unsafe { &*self.0.block.cast::<MemBlockRef>() }
+    }
+
+    pub fn flags(&self) -> MemMapFlags {
+        MemMapFlags::from_bits_retain(self.0.flags)
+    }
+
+    /// The offset of the region in the block.
+    pub fn offset(&self) -> u32 {
+        self.0.offset
+    }
+
+    pub fn size(&self) -> u32 {
+        self.0.size
+    }
+
+    /// A pointer to the start of the region.
+    pub fn as_ptr(&self) -> *mut c_void {
+        self.0.ptr
+    }
+
+    /// The mapped memory, or [`None`] if the region was not mapped with [`MemMapFlags::READ`].
+    ///
+    /// # Safety
+    /// The memory is shared with the other mappings of the region, possibly in other processes.
+    /// The caller must ensure that it is not written to while the returned slice is alive.
+    pub unsafe fn data(&self) -> Option<&[u8]> {
+        if self.0.ptr.is_null() || !self.flags().contains(MemMapFlags::READ) {
+            return None;
+        }
+
+        Some(slice::from_raw_parts(
+            self.0.ptr.cast(),
+            self.0.size as usize,
+        ))
+    }
+}
+
+impl fmt::Debug for MemMapRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemMapRef")
+            .field("block", &self.block().id())
+            .field("flags", &self.flags())
+            .field("offset", &self.offset())
+            .field("size", &self.size())
+            .field("ptr", &self.as_ptr())
+            .finish()
+    }
+}
+
+/// A mapping of a region of a block, unmapped when dropped.
+///
+/// The mapping keeps its block alive, but can't outlive the pool of the block.
+pub struct MemMap<'pool> {
+    ptr: ptr::NonNull<pw_sys::pw_memmap>,
+    _pool: PhantomData<&'pool MemPoolRef>,
+}
+
+impl MemMap<'_> {
+    /// # Safety
+    /// `ptr` must point to a valid mapping owned by the caller.
+    unsafe fn from_ptr(ptr: ptr::NonNull<pw_sys::pw_memmap>) -> Self {
+        Self {
+            ptr,
+            _pool: PhantomData,
+        }
+    }
+
+    /// The mapped memory, or [`None`] if the region was not mapped with
+    /// [`MemMapFlags::READWRITE`].
+    ///
+    /// # Safety
+    /// The memory is shared with the other mappings of the region, possibly in other processes.
+    /// The caller must ensure that it is not accessed through any of them while the returned
+    /// slice is alive.
+    pub unsafe fn data_mut(&mut self) -> Option<&mut [u8]> {
+        if self.as_ptr().is_null() || !self.flags().contains(MemMapFlags::READWRITE) {
+            return None;
+        }
+
+        Some(slice::from_raw_parts_mut(
+            self.as_ptr().cast(),
+            self.size() as usize,
+        ))
+    }
+}
+
+impl Deref for MemMap<'_> {
+    type Target = MemMapRef;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.ptr.cast::<MemMapRef>().as_ref() }
+    }
+}
+
+impl AsRef<MemMapRef> for MemMap<'_> {
+    fn as_ref(&self) -> &MemMapRef {
+        self.deref()
+    }
+}
+
+impl fmt::Debug for MemMap<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.deref(), f)
+    }
+}
+
+impl Drop for MemMap<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            pw_sys::pw_memmap_free(self.ptr.as_ptr());
+        }
+    }
+}
@@ -4,17 +4,147 @@
 use std::ffi::CString;
 use std::os::raw::c_char;
 use std::{
+    cell::RefCell,
+    collections::BTreeMap,
     ffi::{c_void, CStr},
-    mem,
+    fmt, mem,
     pin::Pin,
     ptr,
+    rc::Rc,
 };
 
 use crate::{
+    constants::ID_ANY,
+    core::CoreRef,
+    keys,
+    properties::properties,
     proxy::{Listener, Proxy, ProxyT},
     types::ObjectType,
+    Error,
 };
-use spa::spa_interface_call_method;
+use spa::{
+    spa_interface_call_method,
+    utils::{
+        json,
+        result::{AsyncSeq, SpaResult},
+    },
+};
+
+/// A typed metadata value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetadataValue {
+    /// A JSON value, of type [`MetadataValue::JSON`].
+    Json(json::Value),
+    /// A string, of type [`MetadataValue::STRING`] or without type.
+    String(String),
+    /// An object id, of type [`MetadataValue::ID`].
+    Id(u32),
+    /// An integer, of type [`MetadataValue::INT`].
+    Int(i64),
+    /// A value of any other type, or one that does not parse as its type.
+    Other { type_: String, value: String },
+}
+
+impl MetadataValue {
+    pub const JSON: &'static str = "Spa:String:JSON";
+    pub const STRING: &'static str = "Spa:String";
+    pub const ID: &'static str = "Spa:Id";
+    pub const INT: &'static str = "Spa:Int";
+
+    /// Parse a value of type `type_`, as received by the `property` event.
+    ///
+    /// Values that do not parse as their type are kept as [`MetadataValue::Other`].
+    pub fn parse(type_: Option<&str>, value: &str) -> Self {
+        let parsed = match type_ {
+            None | Some(Self::STRING) => Some(Self::String(value.to_owned())),
+            Some(Self::JSON) => json::Value::parse(value).ok().map(Self::Json),
+            Some(Self::ID) => value.parse().ok().map(Self::Id),
+            Some(Self::INT) => value.parse().ok().map(Self::Int),
+            Some(_) => None,
+        };
+
+        parsed.unwrap_or_else(|| Self::Other {
+            type_: type_.unwrap_or_default().to_owned(),
+            value: value.to_owned(),
+        })
+    }
+
+    /// The type of the value, as passed to [`Metadata::set_property`].
+    pub fn type_(&self) -> &str {
+        match self {
+            Self::Json(_) => Self::JSON,
+            Self::String(_) => Self::STRING,
+            Self::Id(_) => Self::ID,
+            Self::Int(_) => Self::INT,
+            Self::Other { type_, .. } => type_,
+        }
+    }
+
+    pub fn as_json(&self) -> Option<&json::Value> {
+        match self {
+            Self::Json(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_id(&self) -> Option<u32> {
+        match self {
+            Self::Id(id) => Some(*id),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Self::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+/// Formats the value as passed to [`Metadata::set_property`].
+impl fmt::Display for MetadataValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json(value) => value.fmt(f),
+            Self::String(value) => f.write_str(value),
+            Self::Id(id) => id.fmt(f),
+            Self::Int(value) => value.fmt(f),
+            Self::Other { value, .. } => f.write_str(value),
+        }
+    }
+}
+
+impl From<json::Value> for MetadataValue {
+    fn from(value: json::Value) -> Self {
+        Self::Json(value)
+    }
+}
+
+impl From<&str> for MetadataValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_owned())
+    }
+}
+
+impl From<String> for MetadataValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<i64> for MetadataValue {
+    fn from(value: i64) -> Self {
+        Self::Int(value)
+    }
+}
 
 #[derive(Debug)]
 pub struct Metadata {
@@ -43,6 +173,16 @@ impl ProxyT for Metadata {
 }
 
 impl Metadata {
+    /// Create a new metadata object named `name` on the remote, using the `metadata` factory.
+    pub fn create(core: &CoreRef, name: &str) -> Result<Self, Error> {
+        core.create_object(
+            "metadata",
+            &properties! {
+                *keys::METADATA_NAME => name
+            },
+        )
+    }
+
     pub fn add_listener_local(&self) -> MetadataListenerLocalBuilder {
         MetadataListenerLocalBuilder {
             metadata: self,
@@ -67,7 +207,38 @@ impl Metadata {
         type_: Option<&CStr>,
         value: Option<&CStr>,
     ) {
-        unsafe {
+        // Errors are reported through the proxy `error` event.
+        let _ = self.call_set_property(subject, key, type_, value);
+    }
+
+    /// Set the property `key` of `subject` to the typed `value`.
+    ///
+    /// Returns [`Error::Nul`] if the key or the value contains a NUL byte.
+    pub fn set(&self, subject: u32, key: &str, value: &MetadataValue) -> Result<AsyncSeq, Error> {
+        let key = CString::new(key)?;
+        let type_ = CString::new(value.type_())?;
+        let value = CString::new(value.to_string())?;
+
+        self.call_set_property(subject, &key, Some(&type_), Some(&value))
+    }
+
+    /// Remove the property `key` of `subject`.
+    ///
+    /// Returns [`Error::Nul`] if the key contains a NUL byte.
+    pub fn remove(&self, subject: u32, key: &str) -> Result<AsyncSeq, Error> {
+        let key = CString::new(key)?;
+
+        self.call_set_property(subject, &key, None, None)
+    }
+
+    fn call_set_property(
+        &self,
+        subject: u32,
+        key: &CStr,
+        type_: Option<&CStr>,
+        value: Option<&CStr>,
+    ) -> Result<AsyncSeq, Error> {
+        let res = unsafe {
             spa::spa_interface_call_method!(
                 self.proxy.as_ptr(),
                 pw_sys::pw_metadata_methods,
@@ -76,7 +247,35 @@ impl Metadata {
                 key.as_ptr() as *const _,
                 type_.map_or_else(ptr::null, CStr::as_ptr) as *const _,
                 value.map_or_else(ptr::null, CStr::as_ptr) as *const _
-            );
+            )
+        };
+
+        let seq = SpaResult::from_c(res).into_async_result()?;
+        Ok(seq)
+    }
+
+    /// Keep an in-memory copy of the properties of the metadata object.
+    ///
+    /// The copy is updated from the `property` events for as long as the
+    /// returned [`MetadataMirror`] is alive.
+    #[must_use]
+    pub fn mirror(&self) -> MetadataMirror {
+        let entries = Rc::new(RefCell::new(Entries::new()));
+        let listener = self
+            .add_listener_local()
+            .property_value({
+                let entries = Rc::downgrade(&entries);
+                move |subject, key, value| {
+                    if let Some(entries) = entries.upgrade() {
+                        apply(&mut entries.borrow_mut(), subject, key, value);
+                    }
+                }
+            })
+            .register();
+
+        MetadataMirror {
+            _listener: listener,
+            entries,
         }
     }
 
@@ -108,10 +307,85 @@ impl Drop for MetadataListener {
     }
 }
 
+type Entries = BTreeMap<u32, BTreeMap<String, MetadataValue>>;
+
+fn apply(entries: &mut Entries, subject: u32, key: Option<&str>, value: Option<&MetadataValue>) {
+    match (key, value) {
+        (None, _) if subject == ID_ANY => entries.clear(),
+        (None, _) => {
+            entries.remove(&subject);
+        }
+        (Some(key), Some(value)) => {
+            entries
+                .entry(subject)
+                .or_default()
+                .insert(key.to_owned(), value.clone());
+        }
+        (Some(key), None) => {
+            if let Some(subject_entries) = entries.get_mut(&subject) {
+                subject_entries.remove(key);
+                if subject_entries.is_empty() {
+                    entries.remove(&subject);
+                }
+            }
+        }
+    }
+}
+
+/// An in-memory copy of the properties of a [`Metadata`], created with [`Metadata::mirror`].
+pub struct MetadataMirror {
+    _listener: MetadataListener,
+    entries: Rc<RefCell<Entries>>,
+}
+
+impl MetadataMirror {
+    /// The value of the property `key` of `subject`.
+    pub fn get(&self, subject: u32, key: &str) -> Option<MetadataValue> {
+        self.entries.borrow().get(&subject)?.get(key).cloned()
+    }
+
+    /// The properties of `subject`, sorted by key.
+    pub fn subject(&self, subject: u32) -> Vec<(String, MetadataValue)> {
+        self.entries
+            .borrow()
+            .get(&subject)
+            .map(|entries| {
+                entries
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// All the properties, sorted by subject and key.
+    pub fn entries(&self) -> Vec<(u32, String, MetadataValue)> {
+        self.entries
+            .borrow()
+            .iter()
+            .flat_map(|(subject, entries)| {
+                entries
+                    .iter()
+                    .map(|(key, value)| (*subject, key.clone(), value.clone()))
+            })
+            .collect()
+    }
+
+    /// The number of properties.
+    pub fn len(&self) -> usize {
+        self.entries.borrow().values().map(BTreeMap::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.borrow().is_empty()
+    }
+}
+
 #[derive(Default)]
+#[allow(clippy::type_complexity)]
 struct ListenerLocalCallbacks {
-    #[allow(clippy::type_complexity)]
     property: Option<Box<dyn Fn(u32, Option<&str>, Option<&str>, Option<&str>) -> i32>>,
+    property_value: Option<Box<dyn Fn(u32, Option<&str>, Option<&MetadataValue>)>>,
 }
 
 #[must_use]
@@ -135,6 +409,21 @@ impl<'meta> MetadataListenerLocalBuilder<'meta> {
         self
     }
 
+    /// Add property changed callback, with the value parsed according to its type.
+    ///
+    /// Callback parameters: subject, key, value.
+    ///
+    /// `None` for `value` means removal of property.
+    /// `None` for `key` means removal of all properties of the subject,
+    /// or of all subjects if the subject is [`ID_ANY`](crate::constants::ID_ANY).
+    pub fn property_value<F>(mut self, property_value: F) -> Self
+    where
+        F: Fn(u32, Option<&str>, Option<&MetadataValue>) + 'static,
+    {
+        self.cbs.property_value = Some(Box::new(property_value));
+        self
+    }
+
     #[must_use]
     pub fn register(self) -> MetadataListener {
         unsafe extern "C" fn metadata_events_property(
@@ -160,19 +449,22 @@ impl<'meta> MetadataListenerLocalBuilder<'meta> {
             } else {
                 None
             };
-            callbacks.property.as_ref().unwrap()(
-                subject,
-                key.as_deref(),
-                type_.as_deref(),
-                value.as_deref(),
-            )
+            if let Some(property_value) = &callbacks.property_value {
+                let parsed = value
+                    .as_deref()
+                    .map(|value| MetadataValue::parse(type_.as_deref(), value));
+                property_value(subject, key.as_deref(), parsed.as_ref());
+            }
+            callbacks.property.as_ref().map_or(0, |property| {
+                property(subject, key.as_deref(), type_.as_deref(), value.as_deref())
+            })
         }
 
         let e = unsafe {
             let mut e: Pin<Box<pw_sys::pw_metadata_events>> = Box::pin(mem::zeroed());
             e.version = pw_sys::PW_VERSION_METADATA_EVENTS;
 
-            if self.cbs.property.is_some() {
+            if self.cbs.property.is_some() || self.cbs.property_value.is_some() {
                 e.property = Some(metadata_events_property);
             }
 
@@ -205,3 +497,150 @@ impl<'meta> MetadataListenerLocalBuilder<'meta> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_value() {
+        assert_eq!(
+            MetadataValue::parse(Some("Spa:String:JSON"), r#"{ "name": "sink" }"#),
+            MetadataValue::Json(json::Value::Object(vec![(
+                "name".to_owned(),
+                json::Value::from("sink")
+            )]))
+        );
+        assert_eq!(
+            MetadataValue::parse(None, "value"),
+            MetadataValue::String("value".to_owned())
+        );
+        assert_eq!(
+            MetadataValue::parse(Some("Spa:Id"), "42"),
+            MetadataValue::Id(42)
+        );
+        assert_eq!(
+            MetadataValue::parse(Some("Spa:Int"), "-1"),
+            MetadataValue::Int(-1)
+        );
+        assert_eq!(
+            MetadataValue::parse(Some("Spa:Id"), "none"),
+            MetadataValue::Other {
+                type_: "Spa:Id".to_owned(),
+                value: "none".to_owned()
+            }
+        );
+    }
+
+    #[test]
+    fn format_value() {
+        for value in [
+            MetadataValue::Json(json::Value::Array(vec![json::Value::from(true)])),
+            MetadataValue::String("value".to_owned()),
+            MetadataValue::Id(42),
+            MetadataValue::Int(-1),
+            MetadataValue::Other {
+                type_: "Custom".to_owned(),
+                value: "custom".to_owned(),
+            },
+        ] {
+            let parsed = MetadataValue::parse(Some(value.type_()), &value.to_string());
+            assert_eq!(parsed, value);
+        }
+    }
+
+    #[test]
+    fn mirror() {
+        let mut entries = Entries::new();
+        apply(&mut entries, 0, Some("a"), Some(&MetadataValue::Int(1)));
+        apply(&mut entries, 0, Some("b"), Some(&MetadataValue::Int(2)));
+        apply(&mut entries, 1, Some("a"), Some(&MetadataValue::Id(3)));
+        assert_eq!(entries.len(), 2);
+
+        apply(&mut entries, 0, Some("a"), None);
+        assert_eq!(entries[&0].len(), 1);
+        apply(&mut entries, 1, Some("a"), None);
+        assert!(!entries.contains_key(&1));
+
+        apply(&mut entries, 2, Some("a"), Some(&MetadataValue::Int(1)));
+        apply(&mut entries, 2, None, None);
+        assert!(!entries.contains_key(&2));
+        assert!(entries.contains_key(&0));
+
+        apply(&mut entries, ID_ANY, None, None);
+        assert!(entries.is_empty());
+    }
+    #[derive(Default)]
+    struct Calls(Vec<(u32, String, Option<String>, Option<String>)>);
+
+    unsafe extern "C" fn set_property(
+        data: *mut c_void,
+        subject: u32,
+        key: *const c_char,
+        type_: *const c_char,
+        value: *const c_char,
+    ) -> i32 {
+        let to_string = |s: *const c_char| {
+            (!s.is_null()).then(|| CStr::from_ptr(s).to_string_lossy().into_owned())
+        };
+        let calls = &mut *(data as *mut Calls);
+        calls.0.push((
+            subject,
+            to_string(key).unwrap(),
+            to_string(type_),
+            to_string(value),
+        ));
+
+        // The proxy methods return the seq of the message sent to the server.
+        AsyncSeq::from_seq(calls.0.len() as i32).raw()
+    }
+
+    #[test]
+    fn set_property_is_async() {
+        let mut calls = Calls::default();
+        let methods = pw_sys::pw_metadata_methods {
+            version: pw_sys::PW_VERSION_METADATA_METHODS,
+            add_listener: None,
+            set_property: Some(set_property),
+            clear: None,
+        };
+        let mut iface = spa_sys::spa_interface {
+            type_: ptr::null(),
+            version: pw_sys::PW_VERSION_METADATA,
+            cb: spa_sys::spa_callbacks {
+                funcs: ptr::addr_of!(methods).cast(),
+                data: ptr::addr_of_mut!(calls).cast(),
+            },
+        };
+        let metadata = Metadata {
+            proxy: Proxy::new(ptr::NonNull::from(&mut iface).cast()),
+        };
+
+        let seq = metadata.set(0, "key", &MetadataValue::Int(1)).unwrap();
+        assert_eq!(seq.seq(), 1);
+        let seq = metadata.remove(0, "key").unwrap();
+        assert_eq!(seq.seq(), 2);
+        metadata.set_property(0, "other", None, Some("value"));
+        assert!(matches!(
+            metadata.set(0, "key\0", &MetadataValue::Int(1)),
+            Err(Error::Nul(_))
+        ));
+
+        // Not a real proxy, which must not be destroyed.
+        mem::forget(metadata);
+
+        assert_eq!(
+            calls.0,
+            [
+                (
+                    0,
+                    "key".to_owned(),
+                    Some(MetadataValue::INT.to_owned()),
+                    Some("1".to_owned())
+                ),
+                (0, "key".to_owned(), None, None),
+                (0, "other".to_owned(), None, Some("value".to_owned())),
+            ]
+        );
+    }
+}
@@ -83,7 +83,7 @@ impl ModuleInfoRef {
     }
 
     pub fn filename(&self) -> &str {
-        unsafe { CStr::from_ptr(self.0.name).to_str().unwrap() }
+        unsafe { CStr::from_ptr(self.0.filename).to_str().unwrap() }
     }
 
     pub fn args(&self) -> Option<&str> {
@@ -159,6 +159,7 @@ impl AsRef<ModuleInfoRef> for ModuleInfo {
 
 bitflags! {
     #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct ModuleChangeMask: u64 {
         const PROPS = pw_sys::PW_MODULE_CHANGE_MASK_PROPS as u64;
     }
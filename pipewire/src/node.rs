@@ -10,9 +10,16 @@ use std::{fmt, mem};
 
 use crate::{
     proxy::{Listener, Proxy, ProxyT},
+    reply::{self, Param, Reply},
     types::ObjectType,
+    Error,
+};
+use spa::{
+    node::NodeCommand,
+    pod::Pod,
+    spa_interface_call_method,
+    utils::result::{AsyncSeq, SpaResult},
 };
-use spa::{pod::Pod, spa_interface_call_method};
 
 #[derive(Debug)]
 pub struct Node {
@@ -32,17 +39,19 @@ impl Node {
     /// Subscribe to parameter changes
     ///
     /// Automatically emit `param` events for the given ids when they are changed
-    // FIXME: Return result?
-    pub fn subscribe_params(&self, ids: &[spa::param::ParamType]) {
-        unsafe {
+    pub fn subscribe_params(&self, ids: &[spa::param::ParamType]) -> Result<AsyncSeq, Error> {
+        let res = unsafe {
             spa_interface_call_method!(
                 self.proxy.as_ptr(),
                 pw_sys::pw_node_methods,
                 subscribe_params,
                 ids.as_ptr() as *mut _,
                 ids.len().try_into().unwrap()
-            );
-        }
+            )
+        };
+
+        let res = SpaResult::from_c(res).into_async_result()?;
+        Ok(res)
     }
 
     /// Enumerate node parameters
@@ -54,13 +63,20 @@ impl Node {
     /// `seq`: a sequence number to place in the reply \
     /// `id`: the parameter id to enum, or [`None`] to allow any id \
     /// `start`: the start index or 0 for the first param \
-    /// `num`: the maximum number of params to retrieve ([`u32::MAX`] may be used to retrieve all params)
-    // FIXME: Add filter parameter
-    // FIXME: Return result?
-    pub fn enum_params(&self, seq: i32, id: Option<spa::param::ParamType>, start: u32, num: u32) {
+    /// `num`: the maximum number of params to retrieve ([`u32::MAX`] may be used to retrieve all params) \
+    /// `filter`: a param to filter the enumerated params with, or [`None`] to retrieve all params
+    pub fn enum_params(
+        &self,
+        seq: i32,
+        id: Option<spa::param::ParamType>,
+        start: u32,
+        num: u32,
+        filter: Option<&Pod>,
+    ) -> Result<AsyncSeq, Error> {
         let id = id.map(|id| id.as_raw()).unwrap_or(crate::constants::ID_ANY);
+        let filter = filter.map_or(std::ptr::null(), |filter| filter.as_raw_ptr().cast_const());
 
-        unsafe {
+        let res = unsafe {
             spa_interface_call_method!(
                 self.proxy.as_ptr(),
                 pw_sys::pw_node_methods,
@@ -69,13 +85,69 @@ impl Node {
                 id,
                 start,
                 num,
-                std::ptr::null()
-            );
-        }
+                filter
+            )
+        };
+
+        let res = SpaResult::from_c(res).into_async_result()?;
+        Ok(res)
     }
 
-    pub fn set_param(&self, id: spa::param::ParamType, flags: u32, param: &Pod) {
-        unsafe {
+    /// Enumerate node parameters and collect the replies
+    ///
+    /// Same as [`enum_params()`](`Self::enum_params()`), but returns a [`Reply`] resolving
+    /// to all the params emitted for this call once the enumeration is finished.
+    pub fn enum_params_async(
+        &self,
+        id: Option<spa::param::ParamType>,
+        start: u32,
+        num: u32,
+        filter: Option<&Pod>,
+    ) -> Result<Reply<Vec<Param>>, Error> {
+        let seq = self.enum_params(0, id, start, num, filter)?;
+
+        let mut reply = reply::track(&self.proxy, seq, Vec::new())?;
+        let state = reply.state();
+        let listener = self
+            .add_listener_local()
+            .param(move |param_seq, id, index, next, param| {
+                if let (true, Some(param)) = (param_seq == seq.raw(), param) {
+                    state
+                        .borrow_mut()
+                        .update(|params| params.push(Param::new(id, index, next, param)));
+                }
+            })
+            .register();
+        reply.hold(listener);
+
+        Ok(reply)
+    }
+
+    /// Enumerate the formats of the node
+    ///
+    /// Shorthand for [`enum_params()`](`Self::enum_params()`) with the [`EnumFormat`](`spa::param::ParamType::EnumFormat`)
+    /// id, retrieving all params.
+    pub fn enum_formats(&self, filter: Option<&Pod>) -> Result<AsyncSeq, Error> {
+        self.enum_params(
+            0,
+            Some(spa::param::ParamType::EnumFormat),
+            0,
+            u32::MAX,
+            filter,
+        )
+    }
+
+    /// Set a parameter on the node
+    ///
+    /// Errors reported asynchronously by the remote are emitted through the proxy
+    /// `error` event with the returned [`AsyncSeq`].
+    pub fn set_param(
+        &self,
+        id: spa::param::ParamType,
+        flags: u32,
+        param: &Pod,
+    ) -> Result<AsyncSeq, Error> {
+        let res = unsafe {
             spa_interface_call_method!(
                 self.proxy.as_ptr(),
                 pw_sys::pw_node_methods,
@@ -83,8 +155,36 @@ impl Node {
                 id.as_raw(),
                 flags,
                 param.as_raw_ptr()
-            );
-        }
+            )
+        };
+
+        SpaResult::from_c(res)
+            .into_async_result()
+            .map_err(|e| Error::SetParam {
+                id,
+                errno: nix::errno::Errno::from_raw(e.errno() as i32),
+            })
+    }
+
+    /// Send a command to the node
+    ///
+    /// For example, [`NodeCommand::Suspend`] suspends an idle node, and
+    /// [`NodeCommand::RequestProcess`] triggers a processing cycle of its graph.
+    pub fn send_command(&self, command: NodeCommand) -> Result<AsyncSeq, Error> {
+        let bytes = command.to_bytes();
+        let pod = Pod::from_bytes(&bytes).expect("Node command is a valid pod");
+
+        let res = unsafe {
+            spa_interface_call_method!(
+                self.proxy.as_ptr(),
+                pw_sys::pw_node_methods,
+                send_command,
+                pod.as_raw_ptr().cast_const().cast()
+            )
+        };
+
+        let res = SpaResult::from_c(res).into_async_result()?;
+        Ok(res)
     }
 }
 
@@ -260,6 +360,7 @@ impl AsRef<NodeInfoRef> for NodeInfo {
 
 bitflags! {
     #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct NodeChangeMask: u64 {
         const INPUT_PORTS = pw_sys::PW_NODE_CHANGE_MASK_INPUT_PORTS as u64;
         const OUTPUT_PORTS = pw_sys::PW_NODE_CHANGE_MASK_OUTPUT_PORTS as u64;
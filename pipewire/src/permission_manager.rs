@@ -0,0 +1,350 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Managing the permissions of clients, as an access manager.
+//!
+//! Clients connecting through a restricted access, such as sandboxed applications,
+//! are marked with the [`ACCESS`](keys::ACCESS) property and the `pipewire.sec.*` properties
+//! describing them, and do not see any object until an access manager sets their permissions.
+//!
+//! A [`PermissionManager`] watches the clients appearing on the registry and asks a policy
+//! for the [`PermissionRules`] of each of them.
+//! The rules are applied to all the existing objects, and to the objects appearing later.
+//!
+//! ```no_run
+//! use pipewire::{
+//!     context::Context, keys, main_loop::MainLoop,
+//!     permission_manager::{PermissionManager, PermissionRules},
+//!     permissions::PermissionFlags, types::ObjectType,
+//! };
+//!
+//! let mainloop = MainLoop::new(None).expect("Failed to create main loop");
+//! let context = Context::new(&mainloop).expect("Failed to create context");
+//! let core = context.connect(None).expect("Failed to connect to remote");
+//!
+//! // Let restricted clients see everything but the nodes which are not audio sinks.
+//! let _manager = PermissionManager::new(&core, |props| {
+//!     if props.get(*keys::ACCESS) != Some("restricted") {
+//!         return None;
+//!     }
+//!
+//!     Some(
+//!         PermissionRules::new(PermissionFlags::R | PermissionFlags::X).rule(
+//!             |global| {
+//!                 global.type_ == ObjectType::Node
+//!                     && global.props.and_then(|props| props.get(*keys::MEDIA_CLASS))
+//!                         != Some("Audio/Sink")
+//!             },
+//!             PermissionFlags::empty(),
+//!         ),
+//!     )
+//! })
+//! .expect("Failed to create permission manager");
+//!
+//! mainloop.run();
+//! ```
+
+use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
+
+use spa::utils::dict::DictRef;
+
+use crate::{
+    client::{Client, ClientListener},
+    constants::ID_ANY,
+    core::CoreRef,
+    keys,
+    permissions::{Permission, PermissionFlags},
+    properties::Properties,
+    registry::{self, GlobalObject, Registry},
+    types::ObjectType,
+    Error,
+};
+
+type Predicate = Box<dyn Fn(&GlobalObject<&DictRef>) -> bool>;
+
+/// Rules deciding the permissions of a client on each object.
+///
+/// The permissions of an object are those of the first rule matching it,
+/// or the default permissions if no rule matches.
+pub struct PermissionRules {
+    default: PermissionFlags,
+    rules: Vec<(Predicate, PermissionFlags)>,
+}
+
+impl PermissionRules {
+    /// Create rules granting `default` on all the objects.
+    pub fn new(default: PermissionFlags) -> Self {
+        Self {
+            default,
+            rules: Vec::new(),
+        }
+    }
+
+    /// Add a rule granting `permissions` on the objects matching `predicate`.
+    #[must_use]
+    pub fn rule<F>(mut self, predicate: F, permissions: PermissionFlags) -> Self
+    where
+        F: Fn(&GlobalObject<&DictRef>) -> bool + 'static,
+    {
+        self.rules.push((Box::new(predicate), permissions));
+        self
+    }
+
+    /// The permissions granted when no rule matches.
+    pub fn default_permissions(&self) -> PermissionFlags {
+        self.default
+    }
+
+    /// The permissions granted on `global`.
+    pub fn permissions(&self, global: &GlobalObject<&DictRef>) -> PermissionFlags {
+        self.rules
+            .iter()
+            .find(|(predicate, _)| predicate(global))
+            .map_or(self.default, |(_, permissions)| *permissions)
+    }
+
+    /// The permissions to set for the objects of `globals`,
+    /// only listing the objects which do not get the default permissions.
+    fn object_permissions<'a>(
+        &self,
+        globals: impl IntoIterator<Item = GlobalObject<&'a DictRef>>,
+    ) -> Vec<Permission> {
+        globals
+            .into_iter()
+            .filter_map(|global| {
+                let permissions = self.permissions(&global);
+                (permissions != self.default).then(|| Permission::new(global.id, permissions))
+            })
+            .collect()
+    }
+}
+
+fn borrow_global(global: &GlobalObject<Properties>) -> GlobalObject<&DictRef> {
+    GlobalObject {
+        id: global.id,
+        permissions: global.permissions,
+        type_: global.type_.clone(),
+        version: global.version,
+        props: global.props.as_ref().map(AsRef::as_ref),
+    }
+}
+
+type Policy = Box<dyn Fn(&DictRef) -> Option<PermissionRules>>;
+
+struct ManagedClient {
+    // Needs to be dropped before the client it is registered on.
+    _listener: ClientListener,
+    client: Client,
+    /// The rules of the client, once the policy has been consulted.
+    rules: Option<Option<PermissionRules>>,
+}
+
+struct Inner {
+    registry: Registry,
+    policy: Policy,
+    globals: RefCell<BTreeMap<u32, GlobalObject<Properties>>>,
+    clients: RefCell<BTreeMap<u32, ManagedClient>>,
+}
+
+impl Inner {
+    fn global(self: &Rc<Self>, global: &GlobalObject<&DictRef>) {
+        self.globals
+            .borrow_mut()
+            .insert(global.id, global.to_owned());
+
+        // Apply the rules of the managed clients to the new object.
+        for client in self.clients.borrow().values() {
+            if let Some(Some(rules)) = &client.rules {
+                let permissions = rules.object_permissions([GlobalObject {
+                    id: global.id,
+                    permissions: global.permissions,
+                    type_: global.type_.clone(),
+                    version: global.version,
+                    props: global.props,
+                }]);
+                if !permissions.is_empty() {
+                    client.client.update_permissions(&permissions);
+                }
+            }
+        }
+
+        if global.type_ != ObjectType::Client {
+            return;
+        }
+        let Ok(client) = self.registry.bind::<Client, _>(global) else {
+            return;
+        };
+        let id = global.id;
+        let this = Rc::downgrade(self);
+        let listener = client
+            .add_listener_local()
+            .info(move |info| {
+                if let (Some(this), Some(props)) = (this.upgrade(), info.props()) {
+                    this.client_props(id, props);
+                }
+            })
+            .register();
+
+        self.clients.borrow_mut().insert(
+            id,
+            ManagedClient {
+                _listener: listener,
+                client,
+                rules: None,
+            },
+        );
+    }
+
+    fn global_remove(&self, id: u32) {
+        self.globals.borrow_mut().remove(&id);
+        self.clients.borrow_mut().remove(&id);
+    }
+
+    fn client_props(&self, id: u32, props: &DictRef) {
+        // Wait for the access of the client to be known.
+        if props.get(*keys::ACCESS).is_none() {
+            return;
+        }
+
+        let mut clients = self.clients.borrow_mut();
+        let Some(client) = clients.get_mut(&id) else {
+            return;
+        };
+        if client.rules.is_some() {
+            return;
+        }
+
+        let rules = (self.policy)(props);
+        if let Some(rules) = &rules {
+            let globals = self.globals.borrow();
+            let mut permissions = vec![Permission::new(ID_ANY, rules.default_permissions())];
+            permissions.extend(rules.object_permissions(globals.values().map(borrow_global)));
+            client.client.update_permissions(&permissions);
+        }
+        client.rules = Some(rules);
+    }
+}
+
+/// Sets the permissions of the clients according to a policy.
+///
+/// See the [module documentation](`self`) for details.
+pub struct PermissionManager {
+    // Needs to be dropped before the registry it is registered on.
+    _listener: registry::Listener,
+    inner: Rc<Inner>,
+}
+
+impl PermissionManager {
+    /// Start managing the clients of the remote of `core`.
+    ///
+    /// `policy` is called with the properties of each new client, once its
+    /// [`ACCESS`](keys::ACCESS) property is known, and returns the rules to apply
+    /// to the client, or [`None`] to leave its permissions unchanged.
+    pub fn new<F>(core: &CoreRef, policy: F) -> Result<Self, Error>
+    where
+        F: Fn(&DictRef) -> Option<PermissionRules> + 'static,
+    {
+        let inner = Rc::new(Inner {
+            registry: core.get_registry()?,
+            policy: Box::new(policy),
+            globals: RefCell::new(BTreeMap::new()),
+            clients: RefCell::new(BTreeMap::new()),
+        });
+
+        let listener = inner
+            .registry
+            .add_listener_local()
+            .global({
+                let inner = Rc::downgrade(&inner);
+                move |global| {
+                    if let Some(inner) = inner.upgrade() {
+                        inner.global(global);
+                    }
+                }
+            })
+            .global_remove({
+                let inner = Rc::downgrade(&inner);
+                move |id| {
+                    if let Some(inner) = inner.upgrade() {
+                        inner.global_remove(id);
+                    }
+                }
+            })
+            .register();
+
+        Ok(Self {
+            _listener: listener,
+            inner,
+        })
+    }
+
+    /// Whether the policy returned rules for the client `client_id`, which are applied to it.
+    pub fn is_managed(&self, client_id: u32) -> bool {
+        self.inner
+            .clients
+            .borrow()
+            .get(&client_id)
+            .is_some_and(|client| matches!(client.rules, Some(Some(_))))
+    }
+
+    /// The ids of the clients whose permissions are managed.
+    pub fn managed_clients(&self) -> Vec<u32> {
+        self.inner
+            .clients
+            .borrow()
+            .iter()
+            .filter(|(_, client)| matches!(client.rules, Some(Some(_))))
+            .map(|(id, _)| *id)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn global(id: u32, type_: ObjectType) -> GlobalObject<&'static DictRef> {
+        GlobalObject {
+            id,
+            permissions: PermissionFlags::all(),
+            type_,
+            version: 3,
+            props: None,
+        }
+    }
+
+    #[test]
+    fn rules() {
+        let rw = PermissionFlags::R | PermissionFlags::W;
+        let rules = PermissionRules::new(PermissionFlags::R)
+            .rule(|global| global.id == 42, rw)
+            .rule(
+                |global| global.type_ == ObjectType::Node,
+                PermissionFlags::empty(),
+            );
+
+        assert_eq!(rules.default_permissions(), PermissionFlags::R);
+        assert_eq!(
+            rules.permissions(&global(0, ObjectType::Core)),
+            PermissionFlags::R
+        );
+        assert_eq!(
+            rules.permissions(&global(41, ObjectType::Node)),
+            PermissionFlags::empty()
+        );
+        // The first matching rule wins.
+        assert_eq!(rules.permissions(&global(42, ObjectType::Node)), rw);
+
+        let permissions = rules.object_permissions([
+            global(0, ObjectType::Core),
+            global(41, ObjectType::Node),
+            global(42, ObjectType::Node),
+            global(43, ObjectType::Port),
+        ]);
+        let permissions: Vec<_> = permissions
+            .iter()
+            .map(|p| (p.id(), p.permission_flags()))
+            .collect();
+        assert_eq!(permissions, vec![(41, PermissionFlags::empty()), (42, rw)]);
+    }
+}
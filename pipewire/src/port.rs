@@ -9,10 +9,17 @@ use std::{pin::Pin, ptr};
 
 use crate::{
     proxy::{Listener, Proxy, ProxyT},
+    reply::{self, Param, Reply},
     spa::utils::Direction,
     types::ObjectType,
+    Error,
+};
+use spa::{
+    param::format_utils::FormatCaps,
+    pod::Pod,
+    spa_interface_call_method,
+    utils::result::{AsyncSeq, SpaResult},
 };
-use spa::{pod::Pod, spa_interface_call_method};
 
 #[derive(Debug)]
 pub struct Port {
@@ -32,46 +39,127 @@ impl Port {
     /// Subscribe to parameter changes
     ///
     /// Automatically emit `param` events for the given ids when they are changed
-    // FIXME: Return result?
-    pub fn subscribe_params(&self, ids: &[spa::param::ParamType]) {
-        unsafe {
+    pub fn subscribe_params(&self, ids: &[spa::param::ParamType]) -> Result<AsyncSeq, Error> {
+        let res = unsafe {
             spa_interface_call_method!(
                 self.proxy.as_ptr(),
                 pw_sys::pw_port_methods,
                 subscribe_params,
                 ids.as_ptr() as *mut _,
                 ids.len().try_into().unwrap()
-            );
-        }
+            )
+        };
+
+        let res = SpaResult::from_c(res).into_async_result()?;
+        Ok(res)
     }
 
-    /// Enumerate node parameters
+    /// Enumerate port parameters
     ///
-    /// Start enumeration of node parameters. For each param, a
+    /// Start enumeration of port parameters. For each param, a
     /// param event will be emitted.
     ///
     /// # Parameters
     /// `seq`: a sequence number to place in the reply \
     /// `id`: the parameter id to enum, or [`None`] to allow any id \
     /// `start`: the start index or 0 for the first param \
-    /// `num`: the maximum number of params to retrieve ([`u32::MAX`] may be used to retrieve all params)
-    // FIXME: Add filter parameter
-    // FIXME: Return result?
-    pub fn enum_params(&self, seq: i32, id: Option<spa::param::ParamType>, start: u32, num: u32) {
+    /// `num`: the maximum number of params to retrieve ([`u32::MAX`] may be used to retrieve all params) \
+    /// `filter`: a param to filter the enumerated params with, or [`None`] to retrieve all params
+    pub fn enum_params(
+        &self,
+        seq: i32,
+        id: Option<spa::param::ParamType>,
+        start: u32,
+        num: u32,
+        filter: Option<&Pod>,
+    ) -> Result<AsyncSeq, Error> {
         let id = id.map(|id| id.as_raw()).unwrap_or(crate::constants::ID_ANY);
+        let filter = filter.map_or(std::ptr::null(), |filter| filter.as_raw_ptr().cast_const());
 
-        unsafe {
+        let res = unsafe {
             spa_interface_call_method!(
                 self.proxy.as_ptr(),
-                pw_sys::pw_node_methods,
+                pw_sys::pw_port_methods,
                 enum_params,
                 seq,
                 id,
                 start,
                 num,
-                std::ptr::null()
-            );
-        }
+                filter
+            )
+        };
+
+        let res = SpaResult::from_c(res).into_async_result()?;
+        Ok(res)
+    }
+
+    /// Enumerate port parameters and collect the replies
+    ///
+    /// Same as [`enum_params()`](`Self::enum_params()`), but returns a [`Reply`] resolving
+    /// to all the params emitted for this call once the enumeration is finished.
+    pub fn enum_params_async(
+        &self,
+        id: Option<spa::param::ParamType>,
+        start: u32,
+        num: u32,
+        filter: Option<&Pod>,
+    ) -> Result<Reply<Vec<Param>>, Error> {
+        let seq = self.enum_params(0, id, start, num, filter)?;
+
+        let mut reply = reply::track(&self.proxy, seq, Vec::new())?;
+        let state = reply.state();
+        let listener = self
+            .add_listener_local()
+            .param(move |param_seq, id, index, next, param| {
+                if let (true, Some(param)) = (param_seq == seq.raw(), param) {
+                    state
+                        .borrow_mut()
+                        .update(|params| params.push(Param::new(id, index, next, param)));
+                }
+            })
+            .register();
+        reply.hold(listener);
+
+        Ok(reply)
+    }
+
+    /// Enumerate the formats of the port
+    ///
+    /// Shorthand for [`enum_params()`](`Self::enum_params()`) with the [`EnumFormat`](`spa::param::ParamType::EnumFormat`)
+    /// id, retrieving all params.
+    pub fn enum_formats(&self, filter: Option<&Pod>) -> Result<AsyncSeq, Error> {
+        self.enum_params(
+            0,
+            Some(spa::param::ParamType::EnumFormat),
+            0,
+            u32::MAX,
+            filter,
+        )
+    }
+
+    /// Enumerate the formats of the port and parse them
+    ///
+    /// Returns a [`Reply`] resolving to the capabilities of each `EnumFormat` param of the port
+    /// once the enumeration is finished. Params which cannot be parsed are skipped.
+    pub fn formats(&self) -> Result<Reply<Vec<FormatCaps>>, Error> {
+        let seq = self.enum_formats(None)?;
+
+        let mut reply = reply::track(&self.proxy, seq, Vec::new())?;
+        let state = reply.state();
+        let listener = self
+            .add_listener_local()
+            .param(move |param_seq, _id, _index, _next, param| {
+                if param_seq != seq.raw() {
+                    return;
+                }
+                if let Some(caps) = param.and_then(|param| FormatCaps::from_pod(param).ok()) {
+                    state.borrow_mut().update(|formats| formats.push(caps));
+                }
+            })
+            .register();
+        reply.hold(listener);
+
+        Ok(reply)
     }
 }
 
@@ -205,6 +293,7 @@ impl AsRef<PortInfoRef> for PortInfo {
 
 bitflags! {
     #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct PortChangeMask: u64 {
         const PROPS = pw_sys::PW_PORT_CHANGE_MASK_PROPS as u64;
         const PARAMS = pw_sys::PW_PORT_CHANGE_MASK_PARAMS as u64;
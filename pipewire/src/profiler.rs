@@ -0,0 +1,529 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! The profiler extension, provided by `libpipewire-module-profiler`.
+//!
+//! The profiler global emits, for every graph cycle, timing information about each driver
+//! and the nodes it drives. This is what `pw-top` and `pw-profiler` are built on.
+//! The raw pods are parsed into [`DriverProfile`] records, see `spa/param/profiler.h`.
+
+use std::{ffi::c_void, mem, pin::Pin, time::Duration};
+
+use spa::{
+    pod::{deserialize::PodDeserializer, Pod, Value},
+    spa_interface_call_method,
+    utils::Fraction,
+};
+
+use crate::{
+    proxy::{Listener, Proxy, ProxyT},
+    types::ObjectType,
+};
+
+#[derive(Debug)]
+pub struct Profiler {
+    proxy: Proxy,
+}
+
+impl ProxyT for Profiler {
+    fn type_() -> ObjectType {
+        ObjectType::Profiler
+    }
+
+    fn upcast(self) -> Proxy {
+        self.proxy
+    }
+
+    fn upcast_ref(&self) -> &Proxy {
+        &self.proxy
+    }
+
+    unsafe fn from_proxy_unchecked(proxy: Proxy) -> Self
+    where
+        Self: Sized,
+    {
+        Self { proxy }
+    }
+}
+
+impl Profiler {
+    #[must_use]
+    pub fn add_listener_local(&self) -> ProfilerListenerLocalBuilder {
+        ProfilerListenerLocalBuilder {
+            profiler: self,
+            cbs: ListenerLocalCallbacks::default(),
+        }
+    }
+}
+
+pub struct ProfilerListener {
+    // Need to stay allocated while the listener is registered
+    #[allow(dead_code)]
+    events: Pin<Box<pw_sys::pw_profiler_events>>,
+    listener: Pin<Box<spa_sys::spa_hook>>,
+    #[allow(dead_code)]
+    data: Box<ListenerLocalCallbacks>,
+}
+
+impl Listener for ProfilerListener {}
+
+impl Drop for ProfilerListener {
+    fn drop(&mut self) {
+        spa::utils::hook::remove(*self.listener);
+    }
+}
+
+#[derive(Default)]
+struct ListenerLocalCallbacks {
+    #[allow(clippy::type_complexity)]
+    profile: Option<Box<dyn Fn(&[DriverProfile])>>,
+    #[allow(clippy::type_complexity)]
+    profile_raw: Option<Box<dyn Fn(&Pod)>>,
+}
+
+pub struct ProfilerListenerLocalBuilder<'a> {
+    profiler: &'a Profiler,
+    cbs: ListenerLocalCallbacks,
+}
+
+impl<'a> ProfilerListenerLocalBuilder<'a> {
+    /// Called with the profiles of the drivers that completed a cycle.
+    #[must_use]
+    pub fn profile<F>(mut self, profile: F) -> Self
+    where
+        F: Fn(&[DriverProfile]) + 'static,
+    {
+        self.cbs.profile = Some(Box::new(profile));
+        self
+    }
+
+    /// Called with the unparsed profiling pod, for data not covered by [`DriverProfile`].
+    #[must_use]
+    pub fn profile_raw<F>(mut self, profile: F) -> Self
+    where
+        F: Fn(&Pod) + 'static,
+    {
+        self.cbs.profile_raw = Some(Box::new(profile));
+        self
+    }
+
+    #[must_use]
+    pub fn register(self) -> ProfilerListener {
+        unsafe extern "C" fn profiler_events_profile(
+            data: *mut c_void,
+            pod: *const spa_sys::spa_pod,
+        ) {
+            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
+            if pod.is_null() {
+                return;
+            }
+            let pod = Pod::from_raw(pod);
+
+            if let Some(profile_raw) = &callbacks.profile_raw {
+                profile_raw(pod);
+            }
+            if let Some(profile) = &callbacks.profile {
+                profile(&DriverProfile::parse(pod));
+            }
+        }
+
+        let e = unsafe {
+            let mut e: Pin<Box<pw_sys::pw_profiler_events>> = Box::pin(mem::zeroed());
+            e.version = pw_sys::PW_VERSION_PROFILER_EVENTS;
+
+            if self.cbs.profile.is_some() || self.cbs.profile_raw.is_some() {
+                e.profile = Some(profiler_events_profile);
+            }
+
+            e
+        };
+
+        let (listener, data) = unsafe {
+            let profiler = &self.profiler.proxy.as_ptr();
+
+            let data = Box::into_raw(Box::new(self.cbs));
+            let mut listener: Pin<Box<spa_sys::spa_hook>> = Box::pin(mem::zeroed());
+            let listener_ptr: *mut spa_sys::spa_hook = listener.as_mut().get_unchecked_mut();
+
+            spa_interface_call_method!(
+                profiler,
+                pw_sys::pw_profiler_methods,
+                add_listener,
+                listener_ptr.cast(),
+                e.as_ref().get_ref(),
+                data as *mut _
+            );
+
+            (listener, Box::from_raw(data))
+        };
+
+        ProfilerListener {
+            events: e,
+            listener,
+            data,
+        }
+    }
+}
+
+/// The profile of one cycle of a driver and the nodes it drives.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DriverProfile {
+    pub info: Option<ProfilerInfo>,
+    pub clock: Option<Clock>,
+    pub driver: Option<DriverBlock>,
+    pub followers: Vec<FollowerBlock>,
+}
+
+impl DriverProfile {
+    /// Parse the pod emitted by the profiler, containing the profiles of one or more drivers.
+    ///
+    /// Properties that are unknown or fail to parse are ignored.
+    pub fn parse(pod: &Pod) -> Vec<Self> {
+        let Ok((_, value)) = PodDeserializer::deserialize_any_from(pod.as_bytes()) else {
+            return Vec::new();
+        };
+
+        match value {
+            Value::Struct(objects) => objects.iter().filter_map(Self::from_value).collect(),
+            value => Self::from_value(&value).into_iter().collect(),
+        }
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        let Value::Object(object) = value else {
+            return None;
+        };
+        if object.type_ != spa_sys::SPA_TYPE_OBJECT_Profiler {
+            return None;
+        }
+
+        let mut profile = Self::default();
+        for property in &object.properties {
+            let Value::Struct(fields) = &property.value else {
+                continue;
+            };
+            let fields = Fields(fields.iter());
+
+            match property.key {
+                spa_sys::SPA_PROFILER_info => profile.info = ProfilerInfo::parse(fields),
+                spa_sys::SPA_PROFILER_clock => profile.clock = Clock::parse(fields),
+                spa_sys::SPA_PROFILER_driverBlock => profile.driver = Block::parse(fields),
+                spa_sys::SPA_PROFILER_followerBlock => {
+                    profile.followers.extend(Block::parse(fields))
+                }
+                _ => {}
+            }
+        }
+
+        Some(profile)
+    }
+}
+
+/// General information about the profiled driver.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfilerInfo {
+    /// The number of cycles since the profiler started.
+    pub counter: i64,
+    /// The DSP load over the last cycle, 1/3 s and 10 s, as a fraction of the cycle time.
+    pub cpu_load: [f32; 3],
+    pub xrun_count: i32,
+}
+
+impl ProfilerInfo {
+    fn parse(mut fields: Fields) -> Option<Self> {
+        Some(Self {
+            counter: fields.long()?,
+            cpu_load: [fields.float()?, fields.float()?, fields.float()?],
+            xrun_count: fields.int()?,
+        })
+    }
+}
+
+/// The clock of a driver during the profiled cycle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clock {
+    pub flags: i32,
+    /// The id of the driver node.
+    pub id: i32,
+    pub name: String,
+    /// The time of the cycle start, in nanoseconds.
+    pub nsec: i64,
+    /// The rate of the clock, for example 1/48000.
+    pub rate: Fraction,
+    /// The position of the cycle, in samples.
+    pub position: i64,
+    /// The duration of the cycle, the quantum, in samples.
+    pub duration: i64,
+    pub delay: i64,
+    /// The rate adjustment of the clock, compared to the system clock.
+    pub rate_diff: f64,
+    /// The estimated time of the next cycle start, in nanoseconds.
+    pub next_nsec: i64,
+    /// Only set by PipeWire 0.3.57 and later.
+    pub transport_state: Option<i32>,
+    /// Only set by PipeWire 0.3.57 and later.
+    pub cycle: Option<i32>,
+    /// Only set by PipeWire 0.3.57 and later.
+    pub xrun_duration: Option<i64>,
+}
+
+impl Clock {
+    fn parse(mut fields: Fields) -> Option<Self> {
+        Some(Self {
+            flags: fields.int()?,
+            id: fields.int()?,
+            name: fields.string()?,
+            nsec: fields.long()?,
+            rate: fields.fraction()?,
+            position: fields.long()?,
+            duration: fields.long()?,
+            delay: fields.long()?,
+            rate_diff: fields.double()?,
+            next_nsec: fields.long()?,
+            transport_state: fields.int(),
+            cycle: fields.int(),
+            xrun_duration: fields.long(),
+        })
+    }
+
+    /// The quantum of the cycle, in samples.
+    pub fn quantum(&self) -> i64 {
+        self.duration
+    }
+
+    /// The duration of the cycle, or [`None`] if the clock has no valid rate.
+    pub fn period(&self) -> Option<Duration> {
+        if self.rate.denom == 0 || self.duration < 0 {
+            return None;
+        }
+
+        let nanos =
+            self.duration as u128 * self.rate.num as u128 * 1_000_000_000 / self.rate.denom as u128;
+        Some(Duration::from_nanos(nanos as u64))
+    }
+}
+
+/// The timings of a node during the profiled cycle.
+///
+/// All times are in nanoseconds, using the monotonic clock.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    /// The id of the node.
+    pub id: i32,
+    pub name: String,
+    /// The time the node was signaled in the previous cycle.
+    pub prev_signal: i64,
+    /// The time the node was signaled to start processing.
+    pub signal: i64,
+    /// The time the node woke up and started processing.
+    pub awake: i64,
+    /// The time the node finished processing.
+    pub finish: i64,
+    pub status: i32,
+    pub latency: Fraction,
+    /// Only set by PipeWire 1.0 and later.
+    pub xrun_count: Option<i32>,
+}
+
+/// The timings of a driver during the profiled cycle.
+pub type DriverBlock = Block;
+
+/// The timings of a node driven by the profiled driver.
+pub type FollowerBlock = Block;
+
+impl Block {
+    fn parse(mut fields: Fields) -> Option<Self> {
+        Some(Self {
+            id: fields.int()?,
+            name: fields.string()?,
+            prev_signal: fields.long()?,
+            signal: fields.long()?,
+            awake: fields.long()?,
+            finish: fields.long()?,
+            status: fields.int()?,
+            latency: fields.fraction()?,
+            xrun_count: fields.int(),
+        })
+    }
+
+    /// Whether the node was scheduled in this cycle, and its timings are consistent.
+    pub fn is_complete(&self) -> bool {
+        self.signal > self.prev_signal && self.awake >= self.signal && self.finish >= self.awake
+    }
+
+    /// The time the node waited between being signaled and starting to process,
+    /// or [`None`] if the node did not complete the cycle.
+    pub fn wait_time(&self) -> Option<Duration> {
+        self.is_complete()
+            .then(|| Duration::from_nanos((self.awake - self.signal) as u64))
+    }
+
+    /// The time the node spent processing, or [`None`] if the node did not complete the cycle.
+    pub fn busy_time(&self) -> Option<Duration> {
+        self.is_complete()
+            .then(|| Duration::from_nanos((self.finish - self.awake) as u64))
+    }
+
+    /// The time between the previous and the current cycle, or [`None`] if the node did not
+    /// complete the cycle.
+    pub fn period(&self) -> Option<Duration> {
+        self.is_complete()
+            .then(|| Duration::from_nanos((self.signal - self.prev_signal) as u64))
+    }
+}
+
+/// The fields of a profiler struct, parsed in order.
+struct Fields<'a>(std::slice::Iter<'a, Value>);
+
+impl Fields<'_> {
+    fn int(&mut self) -> Option<i32> {
+        match self.0.next()? {
+            Value::Int(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    fn long(&mut self) -> Option<i64> {
+        match self.0.next()? {
+            Value::Long(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    fn float(&mut self) -> Option<f32> {
+        match self.0.next()? {
+            Value::Float(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    fn double(&mut self) -> Option<f64> {
+        match self.0.next()? {
+            Value::Double(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    fn string(&mut self) -> Option<String> {
+        match self.0.next()? {
+            Value::String(v) => Some(v.clone()),
+            _ => None,
+        }
+    }
+
+    fn fraction(&mut self) -> Option<Fraction> {
+        match self.0.next()? {
+            Value::Fraction(v) => Some(*v),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use spa::pod::{serialize::PodSerializer, Object, Property};
+
+    use super::*;
+
+    fn block(id: i32, name: &str, signal: i64, awake: i64, finish: i64) -> Value {
+        Value::Struct(vec![
+            Value::Int(id),
+            Value::String(name.to_owned()),
+            Value::Long(signal - 1_000_000),
+            Value::Long(signal),
+            Value::Long(awake),
+            Value::Long(finish),
+            Value::Int(0),
+            Value::Fraction(Fraction {
+                num: 1024,
+                denom: 48000,
+            }),
+        ])
+    }
+
+    #[test]
+    fn parse() {
+        let object = Value::Object(Object {
+            type_: spa_sys::SPA_TYPE_OBJECT_Profiler,
+            id: 0,
+            properties: vec![
+                Property::new(
+                    spa_sys::SPA_PROFILER_info,
+                    Value::Struct(vec![
+                        Value::Long(42),
+                        Value::Float(0.1),
+                        Value::Float(0.2),
+                        Value::Float(0.3),
+                        Value::Int(1),
+                    ]),
+                ),
+                Property::new(
+                    spa_sys::SPA_PROFILER_clock,
+                    Value::Struct(vec![
+                        Value::Int(0),
+                        Value::Int(30),
+                        Value::String("dummy".to_owned()),
+                        Value::Long(1_000),
+                        Value::Fraction(Fraction {
+                            num: 1,
+                            denom: 48000,
+                        }),
+                        Value::Long(0),
+                        Value::Long(1024),
+                        Value::Long(0),
+                        Value::Double(1.0),
+                        Value::Long(2_000),
+                    ]),
+                ),
+                Property::new(
+                    spa_sys::SPA_PROFILER_driverBlock,
+                    block(30, "dummy", 10_000, 10_500, 11_000),
+                ),
+                Property::new(
+                    spa_sys::SPA_PROFILER_followerBlock,
+                    block(40, "sink", 11_000, 12_000, 15_000),
+                ),
+                Property::new(
+                    spa_sys::SPA_PROFILER_followerBlock,
+                    block(41, "source", 11_000, 0, 0),
+                ),
+            ],
+        });
+        let bytes = PodSerializer::serialize(
+            std::io::Cursor::new(Vec::new()),
+            &Value::Struct(vec![object]),
+        )
+        .unwrap()
+        .0
+        .into_inner();
+
+        let profiles = DriverProfile::parse(Pod::from_bytes(&bytes).unwrap());
+        assert_eq!(profiles.len(), 1);
+        let profile = &profiles[0];
+
+        let info = profile.info.as_ref().unwrap();
+        assert_eq!(info.counter, 42);
+        assert_eq!(info.xrun_count, 1);
+
+        let clock = profile.clock.as_ref().unwrap();
+        assert_eq!(clock.name, "dummy");
+        assert_eq!(clock.quantum(), 1024);
+        assert_eq!(clock.period(), Some(Duration::from_nanos(21_333_333)));
+        assert_eq!(clock.cycle, None);
+
+        let driver = profile.driver.as_ref().unwrap();
+        assert_eq!(driver.id, 30);
+        assert_eq!(driver.busy_time(), Some(Duration::from_nanos(500)));
+
+        assert_eq!(profile.followers.len(), 2);
+        assert_eq!(
+            profile.followers[0].wait_time(),
+            Some(Duration::from_nanos(1_000))
+        );
+        assert_eq!(
+            profile.followers[0].busy_time(),
+            Some(Duration::from_nanos(3_000))
+        );
+        assert_eq!(profile.followers[1].busy_time(), None);
+    }
+}
@@ -4,6 +4,12 @@ use std::{
     mem::ManuallyDrop,
     ops::Deref,
     ptr,
+    str::FromStr,
+};
+
+use spa::utils::{
+    dict::DictRef,
+    json::{ParseError, Value},
 };
 
 /// A collection of key/value pairs.
@@ -96,9 +102,6 @@ impl Properties {
         this.ptr.as_ptr()
     }
 
-    // TODO: `fn from_string` that calls `pw_sys::pw_properties_new_string`
-    // TODO: bindings for pw_properties_update_keys, pw_properties_update, pw_properties_add, pw_properties_add_keys
-
     /// Create a new `Properties` from a given dictionary.
     ///
     /// All the keys and values from `dict` are copied.
@@ -154,6 +157,42 @@ impl Clone for Properties {
     }
 }
 
+/// Parse properties from the SPA-JSON object syntax, as used by the `PIPEWIRE_PROPS` environment variable.
+///
+/// The properties are parsed by PipeWire itself, so the values are the same as the ones it would store:
+/// the enclosing braces are optional, string values are stored without their quotes,
+/// arrays and objects as they are written, and keys with a `null` value are skipped.
+/// Like PipeWire, the input is only read up to its first NUL byte.
+///
+/// # Examples
+/// ```rust
+/// use pipewire::properties::Properties;
+///
+/// let props: Properties = r#"{ media.class = Audio/Sink audio.position = [ FL FR ] }"#
+///     .parse()
+///     .unwrap();
+///
+/// assert_eq!(Some("Audio/Sink"), props.get("media.class"));
+/// assert_eq!(Some("[ FL FR ]"), props.get("audio.position"));
+/// ```
+impl FromStr for Properties {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.split('\0').next().unwrap_or_default();
+        // PipeWire silently stops at the first syntax error, so check the input beforehand.
+        Value::parse_object(s)?;
+
+        let s = CString::new(s).expect("Input should not contain NUL bytes");
+        unsafe {
+            let ptr = pw_sys::pw_properties_new_string(s.as_ptr());
+            Ok(Self::from_ptr(
+                ptr::NonNull::new(ptr).expect("pw_properties_new_string() returned NULL"),
+            ))
+        }
+    }
+}
+
 impl<K, V> FromIterator<(K, V)> for Properties
 where
     K: Into<Vec<u8>>,
@@ -250,9 +289,162 @@ impl PropertiesRef {
         unsafe { pw_sys::pw_properties_set(self.as_raw_ptr(), key.as_ptr(), std::ptr::null()) };
     }
 
+    /// Set `key` to the decimal representation of `value`.
+    pub fn set_int<K>(&mut self, key: K, value: i64)
+    where
+        K: Into<Vec<u8>>,
+    {
+        self.insert(key, value.to_string());
+    }
+
+    /// Set `key` to `"true"` or `"false"`.
+    pub fn set_bool<K>(&mut self, key: K, value: bool)
+    where
+        K: Into<Vec<u8>>,
+    {
+        self.insert(key, if value { "true" } else { "false" });
+    }
+
     pub fn clear(&mut self) {
         unsafe { pw_sys::pw_properties_clear(self.as_raw_ptr()) }
     }
+
+    /// Copy all the items of `dict`, replacing the existing values.
+    ///
+    /// Returns the number of changed properties.
+    pub fn update(&mut self, dict: &DictRef) -> usize {
+        let res = unsafe { pw_sys::pw_properties_update(self.as_raw_ptr(), dict.as_raw_ptr()) };
+        res.max(0) as usize
+    }
+
+    /// Copy the items of `dict` whose key is in `keys`, replacing the existing values.
+    ///
+    /// Returns the number of changed properties.
+    pub fn update_keys(&mut self, dict: &DictRef, keys: &[&str]) -> usize {
+        let keys = KeyList::new(keys);
+        let res = unsafe {
+            pw_sys::pw_properties_update_keys(self.as_raw_ptr(), dict.as_raw_ptr(), keys.as_ptr())
+        };
+        res.max(0) as usize
+    }
+
+    /// Copy the items of `dict` whose key is not set yet.
+    ///
+    /// Returns the number of added properties.
+    pub fn add(&mut self, dict: &DictRef) -> usize {
+        let res = unsafe { pw_sys::pw_properties_add(self.as_raw_ptr(), dict.as_raw_ptr()) };
+        res.max(0) as usize
+    }
+
+    /// Copy the items of `dict` whose key is in `keys` and is not set yet.
+    ///
+    /// Returns the number of added properties.
+    pub fn add_keys(&mut self, dict: &DictRef, keys: &[&str]) -> usize {
+        let keys = KeyList::new(keys);
+        let res = unsafe {
+            pw_sys::pw_properties_add_keys(self.as_raw_ptr(), dict.as_raw_ptr(), keys.as_ptr())
+        };
+        res.max(0) as usize
+    }
+
+    /// Serialize the properties as a SPA-JSON object, which can be parsed back
+    /// with [`Properties::from_str`].
+    ///
+    /// Values holding a JSON array, object, number or boolean are written as such,
+    /// other values as strings. The output is also valid JSON, so numbers that are only
+    /// accepted by SPA-JSON, like `+5` or `.5`, are written as strings.
+    pub fn serialize(&self) -> String {
+        let members = self
+            .dict()
+            .iter()
+            .map(|(key, value)| {
+                let value = match Value::parse(value) {
+                    Ok(
+                        value @ (Value::Bool(_)
+                        | Value::Number(_)
+                        | Value::Array(_)
+                        | Value::Object(_)),
+                    ) => to_json(value),
+                    _ => Value::from(value),
+                };
+                (key.to_owned(), value)
+            })
+            .collect();
+
+        Value::Object(members).to_string()
+    }
+}
+
+/// Turn the numbers of `value` that are not valid JSON into strings.
+fn to_json(value: Value) -> Value {
+    match value {
+        Value::Number(n) if !is_json_number(&n) => Value::String(n),
+        Value::Array(values) => Value::Array(values.into_iter().map(to_json).collect()),
+        Value::Object(members) => Value::Object(
+            members
+                .into_iter()
+                .map(|(key, value)| (key, to_json(value)))
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
+/// Whether `n` follows the JSON number grammar, e.g. `-0.5e3` but not `+5`, `.5`, `5.` or `05`.
+fn is_json_number(n: &str) -> bool {
+    fn digits(s: &str) -> (&str, &str) {
+        let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        s.split_at(end)
+    }
+
+    let n = n.strip_prefix('-').unwrap_or(n);
+    let (int, mut rest) = digits(n);
+    if int.is_empty() || (int.len() > 1 && int.starts_with('0')) {
+        return false;
+    }
+    if let Some(frac) = rest.strip_prefix('.') {
+        let (frac, tail) = digits(frac);
+        if frac.is_empty() {
+            return false;
+        }
+        rest = tail;
+    }
+    if let Some(exp) = rest.strip_prefix(['e', 'E']) {
+        let exp = exp.strip_prefix(['-', '+']).unwrap_or(exp);
+        let (exp, tail) = digits(exp);
+        if exp.is_empty() {
+            return false;
+        }
+        rest = tail;
+    }
+
+    rest.is_empty()
+}
+
+/// A `NULL` terminated list of keys, as expected by the `pw_properties_*_keys()` functions.
+struct KeyList {
+    _keys: Vec<CString>,
+    ptrs: Vec<*const std::os::raw::c_char>,
+}
+
+impl KeyList {
+    fn new(keys: &[&str]) -> Self {
+        let keys: Vec<CString> = keys
+            .iter()
+            .map(|key| CString::new(*key).expect("key contains null byte"))
+            .collect();
+        let ptrs = keys
+            .iter()
+            .map(|key| key.as_ptr())
+            .chain(std::iter::once(ptr::null()))
+            .collect();
+
+        Self { _keys: keys, ptrs }
+    }
+
+    fn as_ptr(&self) -> *const *const std::os::raw::c_char {
+        self.ptrs.as_ptr()
+    }
 }
 
 impl AsRef<spa::utils::dict::DictRef> for PropertiesRef {
@@ -366,4 +558,102 @@ mod tests {
         assert_eq!(props_copy.dict().len(), 1);
         assert_eq!(props_copy.dict().get("K0"), Some("V0"));
     }
+
+    #[test]
+    fn from_str() {
+        let props: Properties = r#"
+            # A comment
+            node.name = "my-sink"
+            node.latency = 256/48000
+            audio.rate = 48000
+            audio.position = [ FL FR ]
+            node.description = null
+        "#
+        .parse()
+        .unwrap();
+
+        assert_eq!(props.dict().len(), 4);
+        assert_eq!(props.get("node.name"), Some("my-sink"));
+        assert_eq!(props.get("node.latency"), Some("256/48000"));
+        assert_eq!(props.get("audio.rate"), Some("48000"));
+        assert_eq!(props.get("audio.position"), Some("[ FL FR ]"));
+        assert_eq!(props.get("node.description"), None);
+
+        let props: Properties = "{ K0 = V0 }".parse().unwrap();
+        assert_eq!(props.get("K0"), Some("V0"));
+
+        assert!("{ K0 = [ V0 }".parse::<Properties>().is_err());
+    }
+
+    #[test]
+    fn serialize() {
+        let mut props = properties! {
+            "node.name" => "my \"sink\"",
+            "audio.position" => "[ FL FR ]",
+            "null" => "null",
+            "node.priority" => "+5",
+            "volumes" => "[ 0.5 .5 ]",
+        };
+        props.set_int("audio.rate", 48000);
+        props.set_bool("node.pause-on-idle", false);
+
+        let serialized = props.serialize();
+        let json = Value::parse(&serialized).unwrap();
+        assert_eq!(json.get("node.priority"), Some(&Value::String("+5".into())));
+        assert_eq!(
+            json.get("volumes"),
+            Some(&Value::Array(vec![
+                Value::Number("0.5".into()),
+                Value::String(".5".into())
+            ]))
+        );
+
+        let parsed: Properties = serialized.parse().unwrap();
+        assert_eq!(parsed.dict().len(), 7);
+        assert_eq!(parsed.get("node.name"), Some("my \"sink\""));
+        assert_eq!(parsed.get("audio.position"), Some(r#"["FL", "FR"]"#));
+        assert_eq!(parsed.get("null"), Some("null"));
+        assert_eq!(parsed.get("node.priority"), Some("+5"));
+        assert_eq!(parsed.get("audio.rate"), Some("48000"));
+        assert_eq!(parsed.get("node.pause-on-idle"), Some("false"));
+    }
+
+    #[test]
+    fn json_numbers() {
+        for n in ["0", "-0", "48000", "-0.5", "1e3", "1.5E-3", "2e+10"] {
+            assert!(is_json_number(n), "{}", n);
+        }
+        for n in ["", "-", "+5", ".5", "5.", "05", "1e", "0x10", "1/2", "inf"] {
+            assert!(!is_json_number(n), "{}", n);
+        }
+    }
+
+    #[test]
+    fn update_add() {
+        use spa::static_dict;
+
+        let dict = static_dict! {
+            "K0" => "new",
+            "K1" => "new",
+            "K2" => "new"
+        };
+
+        let mut props = properties! { "K0" => "old" };
+        assert_eq!(props.update_keys(&dict, &["K0", "K3"]), 1);
+        assert_eq!(props.get("K0"), Some("new"));
+        assert_eq!(props.get("K1"), None);
+
+        let mut props = properties! { "K0" => "old" };
+        assert_eq!(props.add_keys(&dict, &["K0", "K1"]), 1);
+        assert_eq!(props.get("K0"), Some("old"));
+        assert_eq!(props.get("K1"), Some("new"));
+        assert_eq!(props.get("K2"), None);
+
+        assert_eq!(props.add(&dict), 1);
+        assert_eq!(props.get("K0"), Some("old"));
+        assert_eq!(props.get("K2"), Some("new"));
+
+        assert_eq!(props.update(&dict), 1);
+        assert_eq!(props.get("K0"), Some("new"));
+    }
 }
@@ -7,7 +7,8 @@ use std::mem;
 use std::pin::Pin;
 use std::{ffi::CStr, ptr};
 
-use crate::{types::ObjectType, Error};
+use crate::{types::ObjectType, Error, RemoteError};
+use spa::utils::result::{AsyncSeq, SpaResult};
 
 pub struct Proxy {
     ptr: ptr::NonNull<pw_sys::pw_proxy>,
@@ -34,6 +35,15 @@ impl Proxy {
         unsafe { pw_sys::pw_proxy_get_id(self.as_ptr()) }
     }
 
+    /// Ask the server to emit the `done` event on this proxy once all previous
+    /// method calls on it have been processed.
+    pub fn sync(&self, seq: i32) -> Result<AsyncSeq, Error> {
+        let res = unsafe { pw_sys::pw_proxy_sync(self.as_ptr(), seq) };
+
+        let res = SpaResult::from_c(res).into_async_result()?;
+        Ok(res)
+    }
+
     /// Get the type of the proxy as well as it's version.
     pub fn get_type(&self) -> (ObjectType, u32) {
         unsafe {
@@ -127,12 +137,13 @@ impl Drop for ProxyListener {
 }
 #[derive(Default)]
 struct ListenerLocalCallbacks {
+    id: u32,
     destroy: Option<Box<dyn Fn()>>,
     bound: Option<Box<dyn Fn(u32)>>,
     removed: Option<Box<dyn Fn()>>,
     done: Option<Box<dyn Fn(i32)>>,
     #[allow(clippy::type_complexity)]
-    error: Option<Box<dyn Fn(i32, i32, &str)>>, // TODO: return a proper Error enum?
+    error: Option<Box<dyn Fn(&RemoteError)>>,
 }
 
 pub struct ProxyListenerLocalBuilder<'a> {
@@ -177,17 +188,20 @@ impl<'a> ProxyListenerLocalBuilder<'a> {
         self
     }
 
+    /// Set the callback called when the remote reports an error on this proxy.
+    ///
+    /// The `id` of the reported [`RemoteError`] is the id of the proxy.
     #[must_use]
     pub fn error<F>(mut self, error: F) -> Self
     where
-        F: Fn(i32, i32, &str) + 'static,
+        F: Fn(&RemoteError) + 'static,
     {
         self.cbs.error = Some(Box::new(error));
         self
     }
 
     #[must_use]
-    pub fn register(self) -> ProxyListener {
+    pub fn register(mut self) -> ProxyListener {
         unsafe extern "C" fn proxy_destroy(data: *mut c_void) {
             let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
             callbacks.destroy.as_ref().unwrap()();
@@ -215,8 +229,9 @@ impl<'a> ProxyListenerLocalBuilder<'a> {
             message: *const c_char,
         ) {
             let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
-            let message = CStr::from_ptr(message).to_str().unwrap();
-            callbacks.error.as_ref().unwrap()(seq, res, message);
+            let message = CStr::from_ptr(message).to_string_lossy();
+            let error = RemoteError::from_raw(callbacks.id, seq, res, &message);
+            callbacks.error.as_ref().unwrap()(&error);
         }
 
         let e = unsafe {
@@ -246,6 +261,8 @@ impl<'a> ProxyListenerLocalBuilder<'a> {
             e
         };
 
+        self.cbs.id = self.proxy.id();
+
         let (listener, data) = unsafe {
             let proxy = &self.proxy.as_ptr();
 
@@ -41,13 +41,45 @@ impl Registry {
         }
     }
 
+    /// Bind the global `object`, returning a proxy of type `T`.
+    ///
+    /// The object is bound with the lower of its version and the version implemented
+    /// by this library.
+    /// Binding an object whose type does not match `T` fails with [`Error::WrongProxyType`].
     pub fn bind<T: ProxyT, P: AsRef<spa::utils::dict::DictRef>>(
         &self,
         object: &GlobalObject<P>,
     ) -> Result<T, Error> {
+        self.bind_proxy(object)?.downcast().map_err(|(_, e)| e)
+    }
+
+    /// Bind the global `object`, returning the proxy type matching its type.
+    ///
+    /// Objects of a type that has no proxy type in this library fail with
+    /// [`Error::UnsupportedType`].
+    pub fn bind_any<P: AsRef<spa::utils::dict::DictRef>>(
+        &self,
+        object: &GlobalObject<P>,
+    ) -> Result<AnyProxy, Error> {
+        if !AnyProxy::supports(&object.type_) {
+            return Err(Error::UnsupportedType(object.type_.clone()));
+        }
+
+        AnyProxy::from_proxy(self.bind_proxy(object)?)
+    }
+
+    fn bind_proxy<P: AsRef<spa::utils::dict::DictRef>>(
+        &self,
+        object: &GlobalObject<P>,
+    ) -> Result<Proxy, Error> {
+        let client_version = object
+            .type_
+            .try_client_version()
+            .ok_or_else(|| Error::UnsupportedType(object.type_.clone()))?;
+
         let proxy = unsafe {
             let type_ = CString::new(object.type_.to_str()).unwrap();
-            let version = object.type_.client_version();
+            let version = object.version.min(client_version);
 
             let proxy = spa::spa_interface_call_method!(
                 self.as_ptr(),
@@ -62,9 +94,12 @@ impl Registry {
             proxy
         };
 
-        let proxy = ptr::NonNull::new(proxy.cast()).ok_or(Error::NoMemory)?;
+        let proxy = ptr::NonNull::new(proxy.cast()).ok_or_else(|| Error::Bind {
+            id: object.id,
+            errno: nix::errno::Errno::last(),
+        })?;
 
-        Proxy::new(proxy).downcast().map_err(|(_, e)| e)
+        Ok(Proxy::new(proxy))
     }
 
     /// Attempt to destroy the global object with the specified id on the remote.
@@ -80,6 +115,21 @@ impl Registry {
 
         spa::utils::result::SpaResult::from_c(result)
     }
+
+    /// The id of the registry proxy, reported by the `done` events of the core
+    /// in reply to [`sync()`](`Self::sync()`).
+    pub(crate) fn proxy_id(&self) -> u32 {
+        unsafe { pw_sys::pw_proxy_get_id(self.as_ptr().cast()) }
+    }
+
+    /// Ask the server to emit the `done` event of the core for the registry once all previous
+    /// method calls on the connection have been processed, like [`Proxy::sync`].
+    pub(crate) fn sync(&self, seq: i32) -> Result<spa::utils::result::AsyncSeq, Error> {
+        let res = unsafe { pw_sys::pw_proxy_sync(self.as_ptr().cast(), seq) };
+
+        let res = spa::utils::result::SpaResult::from_c(res).into_async_result()?;
+        Ok(res)
+    }
 }
 
 impl Drop for Registry {
@@ -199,6 +249,70 @@ impl<'a> ListenerLocalBuilder<'a> {
     }
 }
 
+macro_rules! any_proxy {
+    ($( ($variant:ident, $type_:ty) ),*) => {
+        /// A proxy of any of the types that can be bound from the registry.
+        ///
+        /// Returned by [`Registry::bind_any`].
+        #[derive(Debug)]
+        pub enum AnyProxy {
+            $($variant($type_),)*
+        }
+
+        impl AnyProxy {
+            fn supports(type_: &ObjectType) -> bool {
+                matches!(type_, $(ObjectType::$variant)|*)
+            }
+
+            fn from_proxy(proxy: Proxy) -> Result<Self, Error> {
+                match proxy.get_type().0 {
+                    $(
+                        ObjectType::$variant => proxy.downcast().map(Self::$variant),
+                    )*
+                    type_ => return Err(Error::UnsupportedType(type_)),
+                }
+                .map_err(|(_, e)| e)
+            }
+
+            /// The type of the bound object.
+            pub fn type_(&self) -> ObjectType {
+                match self {
+                    $(Self::$variant(_) => ObjectType::$variant,)*
+                }
+            }
+
+            pub fn upcast(self) -> Proxy {
+                match self {
+                    $(Self::$variant(proxy) => proxy.upcast(),)*
+                }
+            }
+
+            pub fn upcast_ref(&self) -> &Proxy {
+                match self {
+                    $(Self::$variant(proxy) => proxy.upcast_ref(),)*
+                }
+            }
+        }
+    };
+}
+
+any_proxy![
+    (Client, crate::client::Client),
+    (Device, crate::device::Device),
+    (Endpoint, crate::session_manager::Endpoint),
+    (EndpointLink, crate::session_manager::EndpointLink),
+    (EndpointStream, crate::session_manager::EndpointStream),
+    (Factory, crate::factory::Factory),
+    (Link, crate::link::Link),
+    (Metadata, crate::metadata::Metadata),
+    (Module, crate::module::Module),
+    (Node, crate::node::Node),
+    (Port, crate::port::Port),
+    (Profiler, crate::profiler::Profiler),
+    (SecurityContext, crate::security_context::SecurityContext),
+    (Session, crate::session_manager::Session)
+];
+
 #[derive(Debug)]
 pub struct GlobalObject<P: AsRef<spa::utils::dict::DictRef>> {
     pub id: u32,
@@ -269,4 +383,15 @@ mod tests {
         let o = ObjectType::Other("PipeWire:Interface:Badger".to_string());
         assert_eq!(o.client_version(), 0);
     }
+
+    #[test]
+    fn any_proxy_supports() {
+        assert!(AnyProxy::supports(&ObjectType::Node));
+        assert!(AnyProxy::supports(&ObjectType::Metadata));
+        assert!(!AnyProxy::supports(&ObjectType::Core));
+
+        let o = ObjectType::Other("PipeWire:Interface:Badger".to_string());
+        assert!(!AnyProxy::supports(&o));
+        assert_eq!(o.try_client_version(), None);
+    }
 }
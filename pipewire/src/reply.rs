@@ -0,0 +1,360 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Awaitable replies to asynchronous method calls.
+//!
+//! Most methods of the PipeWire protocol do not return their result directly.
+//! Instead, the server emits events on the object the method was called on, tagged with the
+//! [`AsyncSeq`] returned by the call, and acknowledges the call once it has been fully processed.
+//!
+//! A [`Reply`] collects those events for a single call. It implements [`Future`] and can be
+//! `.await`ed by an executor driven alongside the PipeWire loop, or waited on synchronously using
+//! [`Reply::wait`], which iterates the loop itself.
+//!
+//! Replies waited on, or watching the core with [`Reply::watch_core`], fail once the
+//! connection to the server is lost.
+
+use std::{
+    cell::RefCell,
+    fmt,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use spa::{
+    param::ParamType,
+    pod::Pod,
+    utils::result::{AsyncSeq, SpaResult},
+};
+
+use nix::errno::Errno;
+
+use crate::{
+    core::{CoreRef, PW_ID_CORE},
+    proxy::{Listener, Proxy},
+    Error, RemoteError,
+};
+
+/// How long [`Reply::wait`] blocks on the loop in a single iteration.
+const WAIT_ITERATE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The pending reply of an asynchronous method call.
+///
+/// The reply resolves to the value collected from the events emitted for the call, or to an
+/// [`Error::Remote`] if the server reported an error for it.
+///
+/// The listeners collecting the reply are kept alive by this struct, so dropping it before it
+/// resolved stops the collection. The object the method was called on must outlive the reply.
+pub struct Reply<T> {
+    seq: AsyncSeq,
+    state: Rc<RefCell<ReplyState<T>>>,
+    listeners: Vec<Box<dyn Listener>>,
+}
+
+impl<T> Reply<T> {
+    pub(crate) fn new(seq: AsyncSeq, init: T) -> Self {
+        Self {
+            seq,
+            state: Rc::new(RefCell::new(ReplyState {
+                value: Some(init),
+                result: None,
+                waker: None,
+            })),
+            listeners: Vec::new(),
+        }
+    }
+
+    /// Shared state, to be moved into the listeners collecting the reply.
+    pub(crate) fn state(&self) -> Rc<RefCell<ReplyState<T>>> {
+        self.state.clone()
+    }
+
+    /// Keep `listener` registered for as long as the reply is pending.
+    pub(crate) fn hold(&mut self, listener: impl Listener + 'static) {
+        self.listeners.push(Box::new(listener));
+    }
+
+    /// The sequence number of the method call this is a reply to.
+    pub fn seq(&self) -> AsyncSeq {
+        self.seq
+    }
+
+    /// Check if the reply has been received, either successfully or not.
+    pub fn is_complete(&self) -> bool {
+        self.state.borrow().result.is_some()
+    }
+
+    /// Fail the reply if the connection of `core` is lost, or if `core` reports an error,
+    /// before the reply was received.
+    ///
+    /// Only the core is notified of those errors, so replies to calls on other objects
+    /// otherwise never resolve when the connection is lost. [`Reply::wait`] calls this itself,
+    /// it is needed when `.await`ing such replies.
+    pub fn watch_core(&mut self, core: &CoreRef)
+    where
+        T: 'static,
+    {
+        let state = self.state();
+        let listener = core
+            .add_listener_local()
+            .error(move |error| {
+                if fails_pending(error) {
+                    state.borrow_mut().fail(Error::Remote(error.clone()));
+                }
+            })
+            .register();
+        self.hold(listener);
+    }
+
+    /// Block until the reply has been received by iterating the main loop of `core`.
+    ///
+    /// `core` must be the core the object the method was called on was created with.
+    /// This must not be called from a callback running on its loop, nor while the loop
+    /// is run by a [`ThreadLoop`](`crate::thread_loop::ThreadLoop`).
+    pub fn wait(mut self, core: &CoreRef) -> Result<T, Error>
+    where
+        T: 'static,
+    {
+        self.watch_core(core);
+        let loop_ = core.main_loop();
+
+        loop {
+            if let Some(result) = self.state.borrow_mut().result.take() {
+                return result;
+            }
+
+            let res = loop_.iterate(WAIT_ITERATE_TIMEOUT);
+            if res < 0 && res != -libc::EINTR {
+                SpaResult::from_c(res).into_result()?;
+            }
+        }
+    }
+}
+
+impl<T> Future for Reply<T> {
+    type Output = Result<T, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.borrow_mut();
+
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T> fmt::Debug for Reply<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Reply")
+            .field("seq", &self.seq)
+            .field("complete", &self.is_complete())
+            .finish()
+    }
+}
+
+pub(crate) struct ReplyState<T> {
+    value: Option<T>,
+    result: Option<Result<T, Error>>,
+    waker: Option<Waker>,
+}
+
+impl<T> ReplyState<T> {
+    /// Update the collected value, unless the reply is already complete.
+    pub(crate) fn update(&mut self, f: impl FnOnce(&mut T)) {
+        if let Some(value) = self.value.as_mut() {
+            f(value);
+        }
+    }
+
+    /// Resolve the reply with the collected value.
+    pub(crate) fn complete(&mut self) {
+        if let Some(value) = self.value.take() {
+            self.finish(Ok(value));
+        }
+    }
+
+    /// Resolve the reply with an error, discarding the collected value.
+    pub(crate) fn fail(&mut self, error: Error) {
+        if self.value.take().is_some() {
+            self.finish(Err(error));
+        }
+    }
+
+    fn finish(&mut self, result: Result<T, Error>) {
+        self.result = Some(result);
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Check if `error`, emitted on the core, means that no reply will be received for any
+/// pending method call: the connection to the server was lost, or the core itself failed.
+pub(crate) fn fails_pending(error: &RemoteError) -> bool {
+    error.id == PW_ID_CORE || matches!(error.errno, Errno::EPIPE | Errno::ECONNRESET)
+}
+
+/// Create a [`Reply`] for the method call on `proxy` that returned `seq`.
+///
+/// The reply completes once the server answered a sync on the proxy issued after the call,
+/// which guarantees that all events emitted for the call have been received, and fails if the
+/// server reports an error for either the call or the sync.
+pub(crate) fn track<T: 'static>(proxy: &Proxy, seq: AsyncSeq, init: T) -> Result<Reply<T>, Error> {
+    let done_seq = proxy.sync(0)?;
+    let mut reply = Reply::new(seq, init);
+
+    let done_state = reply.state();
+    let error_state = reply.state();
+    let listener = proxy
+        .add_listener_local()
+        .done(move |seq| {
+            if seq == done_seq.raw() {
+                done_state.borrow_mut().complete();
+            }
+        })
+        .error(move |error| {
+            if error.is_reply_to(seq) || error.is_reply_to(done_seq) {
+                error_state.borrow_mut().fail(Error::Remote(error.clone()));
+            }
+        })
+        .register();
+    reply.hold(listener);
+
+    Ok(reply)
+}
+
+/// A parameter received in reply to an `enum_params` call.
+#[derive(Clone)]
+pub struct Param {
+    id: ParamType,
+    index: u32,
+    next: u32,
+    // Stored as `u64` so that the pod is correctly aligned.
+    pod: Vec<u64>,
+}
+
+impl Param {
+    pub(crate) fn new(id: ParamType, index: u32, next: u32, pod: &Pod) -> Self {
+        Self {
+            id,
+            index,
+            next,
+            pod: crate::utils::aligned_pod_bytes(pod.as_bytes()),
+        }
+    }
+
+    /// The id of the parameter.
+    pub fn id(&self) -> ParamType {
+        self.id
+    }
+
+    /// The index of the parameter.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// The index of the next parameter.
+    pub fn next(&self) -> u32 {
+        self.next
+    }
+
+    /// The parameter itself.
+    pub fn pod(&self) -> &Pod {
+        crate::utils::aligned_pod(&self.pod).expect("Param does not contain a valid pod")
+    }
+}
+
+impl fmt::Debug for Param {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Param")
+            .field("id", &self.id)
+            .field("index", &self.index)
+            .field("next", &self.next)
+            .field("type", &self.pod().type_())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicU32, Ordering},
+            Arc,
+        },
+        task::Wake,
+    };
+
+    use super::*;
+
+    #[derive(Default)]
+    struct CountWaker(AtomicU32);
+
+    impl Wake for CountWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn connection_errors() {
+        // Errors of the core itself, such as the loss of the connection.
+        assert!(fails_pending(&RemoteError::from_raw(
+            PW_ID_CORE,
+            12,
+            -libc::EPIPE,
+            "connection error"
+        )));
+        assert!(fails_pending(&RemoteError::from_raw(
+            PW_ID_CORE,
+            3,
+            -libc::EINVAL,
+            "invalid message"
+        )));
+        assert!(fails_pending(&RemoteError::from_raw(
+            5,
+            0,
+            -libc::ECONNRESET,
+            "connection reset"
+        )));
+        // Errors of other objects only fail the replies to their own calls.
+        assert!(!fails_pending(&RemoteError::from_raw(
+            5,
+            AsyncSeq::from_seq(3).raw(),
+            -libc::ENOENT,
+            "unknown param"
+        )));
+    }
+
+    #[test]
+    fn fail() {
+        let counter = Arc::new(CountWaker::default());
+        let waker = Waker::from(counter.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let mut reply = Reply::new(AsyncSeq::from_seq(1), 0);
+        let state = reply.state();
+        state.borrow_mut().update(|value| *value += 1);
+        assert!(Pin::new(&mut reply).poll(&mut cx).is_pending());
+        assert!(!reply.is_complete());
+
+        let error = RemoteError::from_raw(PW_ID_CORE, 12, -libc::EPIPE, "connection error");
+        state.borrow_mut().fail(Error::Remote(error.clone()));
+        // The reply already failed, completing it has no effect.
+        state.borrow_mut().complete();
+        assert!(reply.is_complete());
+        assert_eq!(counter.0.load(Ordering::Relaxed), 1);
+
+        match Pin::new(&mut reply).poll(&mut cx) {
+            Poll::Ready(Err(Error::Remote(remote))) => assert_eq!(remote.errno, error.errno),
+            _ => panic!("reply did not fail"),
+        }
+    }
+}
@@ -0,0 +1,167 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Security contexts, used to create sockets for sandboxed clients.
+//!
+//! Clients connecting to the server through a socket created with [`SecurityContext::create`]
+//! get the properties passed at creation time, such as [`SEC_ENGINE`](`crate::keys::SEC_ENGINE`)
+//! and [`ACCESS`](`crate::keys::ACCESS`), set on their client object by the server.
+//! Since a sandboxed client cannot change them, the session manager can use them to restrict
+//! what the client is allowed to access.
+//!
+//! The security context interface is provided by `libpipewire-module-security-context`,
+//! and the global is usually only visible to privileged clients.
+
+use std::{
+    io,
+    os::{
+        fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd},
+        unix::net::UnixListener,
+    },
+    path::{Path, PathBuf},
+};
+
+use nix::{fcntl::OFlag, unistd::pipe2};
+use spa::{
+    spa_interface_call_method,
+    utils::{
+        dict::DictRef,
+        result::{AsyncSeq, SpaResult},
+    },
+};
+
+use crate::{
+    proxy::{Proxy, ProxyT},
+    types::ObjectType,
+    Error,
+};
+
+#[derive(Debug)]
+pub struct SecurityContext {
+    proxy: Proxy,
+}
+
+impl SecurityContext {
+    /// Ask the server to accept clients on `listen_fd`, a listening unix socket,
+    /// until `close_fd` is closed or its other end is.
+    ///
+    /// Clients connecting through the socket get `props` set on their client object.
+    /// The server keeps its own copy of the fds, so the caller may close them
+    /// once the call was flushed, for example after a [roundtrip](`crate::core::CoreRef::roundtrip`).
+    ///
+    /// [`ContextSocket`] creates the socket and a pipe to use as `close_fd`.
+    pub fn create(
+        &self,
+        listen_fd: BorrowedFd,
+        close_fd: BorrowedFd,
+        props: &DictRef,
+    ) -> Result<AsyncSeq, Error> {
+        let res = unsafe {
+            spa_interface_call_method!(
+                self.proxy.as_ptr(),
+                pw_sys::pw_security_context_methods,
+                create,
+                listen_fd.as_raw_fd(),
+                close_fd.as_raw_fd(),
+                props.as_raw_ptr()
+            )
+        };
+
+        let res = SpaResult::from_c(res).into_async_result()?;
+        Ok(res)
+    }
+
+    /// Same as [`create()`](`Self::create()`), using the fds of `socket`.
+    pub fn create_with_socket(
+        &self,
+        socket: &ContextSocket,
+        props: &DictRef,
+    ) -> Result<AsyncSeq, Error> {
+        self.create(socket.listen_fd(), socket.close_fd(), props)
+    }
+}
+
+impl ProxyT for SecurityContext {
+    fn type_() -> ObjectType {
+        ObjectType::SecurityContext
+    }
+
+    fn upcast(self) -> Proxy {
+        self.proxy
+    }
+
+    fn upcast_ref(&self) -> &Proxy {
+        &self.proxy
+    }
+
+    unsafe fn from_proxy_unchecked(proxy: Proxy) -> Self
+    where
+        Self: Sized,
+    {
+        Self { proxy }
+    }
+}
+
+/// A listening socket and close pipe to pass to [`SecurityContext::create`].
+///
+/// The server is given the write end of the pipe as `close_fd` and stops accepting clients
+/// on the socket once every copy of the read end, the [`sync_fd`](`Self::sync_fd`), is closed.
+/// The read end can be passed to the sandboxed process, so that the socket is closed
+/// when the process exits, or kept by the caller until the socket is no longer needed.
+///
+/// The socket file is not removed when this is dropped.
+#[derive(Debug)]
+pub struct ContextSocket {
+    path: PathBuf,
+    listener: UnixListener,
+    sync_fd: OwnedFd,
+    close_fd: OwnedFd,
+}
+
+impl ContextSocket {
+    /// Create a listening socket bound to `path` and the close pipe.
+    ///
+    /// All the fds are created close-on-exec.
+    pub fn bind(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_owned();
+        let listener = UnixListener::bind(&path)?;
+        // The server accepts clients from its main loop.
+        listener.set_nonblocking(true)?;
+        let (sync_fd, close_fd) = pipe2(OFlag::O_CLOEXEC)?;
+
+        Ok(Self {
+            path,
+            listener,
+            sync_fd,
+            close_fd,
+        })
+    }
+
+    /// The path the socket is bound to, to expose to the sandboxed process.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The listening socket.
+    pub fn listen_fd(&self) -> BorrowedFd<'_> {
+        self.listener.as_fd()
+    }
+
+    /// The write end of the close pipe, given to the server.
+    pub fn close_fd(&self) -> BorrowedFd<'_> {
+        self.close_fd.as_fd()
+    }
+
+    /// The read end of the close pipe, keeping the socket open.
+    pub fn sync_fd(&self) -> BorrowedFd<'_> {
+        self.sync_fd.as_fd()
+    }
+
+    /// Drop the fds given to the server, keeping the read end of the close pipe.
+    ///
+    /// This should only be called once [`SecurityContext::create`] was flushed to the server.
+    /// The socket stays open until the returned fd, and any copy of it, is closed.
+    pub fn into_sync_fd(self) -> OwnedFd {
+        self.sync_fd
+    }
+}
@@ -0,0 +1,444 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+use std::{
+    ffi::{c_int, c_void, CStr},
+    fmt, mem,
+    pin::Pin,
+    ptr,
+};
+
+use spa::{
+    param::{ParamInfo, ParamType},
+    pod::Pod,
+    spa_interface_call_method,
+    utils::{
+        dict::DictRef,
+        result::{to_c, AsyncSeq, Errno, SpaResult},
+        Direction,
+    },
+};
+
+use super::{EndpointChangeMask, EndpointFlags, EndpointStreamChangeMask};
+use crate::{
+    proxy::{Listener, Proxy, ProxyT},
+    types::ObjectType,
+    Error,
+};
+
+/// An endpoint implemented by this client.
+///
+/// A client endpoint is created with the `client-endpoint` factory of the session manager module:
+/// ```no_run
+/// # use pipewire as pw;
+/// # fn example(core: &pw::core::CoreRef) -> Result<(), pw::Error> {
+/// let endpoint: pw::session_manager::ClientEndpoint =
+///     core.create_object("client-endpoint", &pw::properties::properties! {})?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// The server exposes it to other clients as an [`Endpoint`](`super::Endpoint`) once its info
+/// is published with [`update()`](`Self::update()`), and forwards the requests made on it
+/// to the listeners of this proxy.
+#[derive(Debug)]
+pub struct ClientEndpoint {
+    proxy: Proxy,
+}
+
+/// The info of a [`ClientEndpoint`], published with [`ClientEndpoint::update`].
+#[derive(Debug, Clone, Copy)]
+pub struct ClientEndpointInfo<'a> {
+    pub name: Option<&'a CStr>,
+    /// The media class of the endpoint, such as `Audio/Sink`.
+    pub media_class: Option<&'a CStr>,
+    pub direction: Direction,
+    pub flags: EndpointFlags,
+    /// Which of the following fields changed.
+    pub change_mask: EndpointChangeMask,
+    pub n_streams: u32,
+    pub session_id: u32,
+    pub props: Option<&'a DictRef>,
+    pub params: &'a [ParamInfo],
+}
+
+impl ClientEndpointInfo<'_> {
+    fn to_raw(self) -> pw_sys::pw_endpoint_info {
+        pw_sys::pw_endpoint_info {
+            version: pw_sys::PW_VERSION_ENDPOINT_INFO,
+            id: crate::constants::ID_ANY,
+            name: self
+                .name
+                .map_or(ptr::null_mut(), |name| name.as_ptr().cast_mut()),
+            media_class: self.media_class.map_or(ptr::null_mut(), |media_class| {
+                media_class.as_ptr().cast_mut()
+            }),
+            direction: self.direction.as_raw(),
+            flags: self.flags.bits(),
+            change_mask: self.change_mask.bits(),
+            n_streams: self.n_streams,
+            session_id: self.session_id,
+            props: self
+                .props
+                .map_or(ptr::null_mut(), |props| props.as_raw_ptr()),
+            params: self.params.as_ptr().cast_mut().cast(),
+            n_params: self.params.len() as u32,
+        }
+    }
+}
+
+/// The info of a stream of a [`ClientEndpoint`], published with [`ClientEndpoint::stream_update`].
+#[derive(Default, Clone, Copy)]
+pub struct ClientEndpointStreamInfo<'a> {
+    pub name: Option<&'a CStr>,
+    /// Which of the following fields changed.
+    pub change_mask: EndpointStreamChangeMask,
+    pub link_params: Option<&'a Pod>,
+    pub props: Option<&'a DictRef>,
+    pub params: &'a [ParamInfo],
+}
+
+impl fmt::Debug for ClientEndpointStreamInfo<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientEndpointStreamInfo")
+            .field("name", &self.name)
+            .field("change_mask", &self.change_mask)
+            // TODO: .field("link_params", &self.link_params)
+            .field("props", &self.props)
+            .field("params", &self.params)
+            .finish()
+    }
+}
+
+impl ClientEndpointStreamInfo<'_> {
+    fn to_raw(self, stream_id: u32) -> pw_sys::pw_endpoint_stream_info {
+        pw_sys::pw_endpoint_stream_info {
+            version: pw_sys::PW_VERSION_ENDPOINT_STREAM_INFO,
+            id: stream_id,
+            endpoint_id: crate::constants::ID_ANY,
+            name: self
+                .name
+                .map_or(ptr::null_mut(), |name| name.as_ptr().cast_mut()),
+            change_mask: self.change_mask.bits(),
+            link_params: self
+                .link_params
+                .map_or(ptr::null_mut(), |link_params| link_params.as_raw_ptr()),
+            props: self
+                .props
+                .map_or(ptr::null_mut(), |props| props.as_raw_ptr()),
+            params: self.params.as_ptr().cast_mut().cast(),
+            n_params: self.params.len() as u32,
+        }
+    }
+}
+
+impl ClientEndpoint {
+    // TODO: add non-local version when we'll bind pw_thread_loop_start()
+    #[must_use]
+    pub fn add_listener_local(&self) -> ClientEndpointListenerLocalBuilder {
+        ClientEndpointListenerLocalBuilder {
+            endpoint: self,
+            cbs: ListenerLocalCallbacks::default(),
+        }
+    }
+
+    /// Update the endpoint.
+    ///
+    /// If `params` is not [`None`], the params of the endpoint are replaced with `params`.
+    /// If `info` is not [`None`], the fields of the info of the endpoint
+    /// set in its `change_mask` are updated.
+    pub fn update(
+        &self,
+        params: Option<&[&Pod]>,
+        info: Option<&ClientEndpointInfo>,
+    ) -> Result<AsyncSeq, Error> {
+        let mut change_mask = 0;
+        if params.is_some() {
+            change_mask |= pw_sys::PW_CLIENT_ENDPOINT_UPDATE_PARAMS;
+        }
+        if info.is_some() {
+            change_mask |= pw_sys::PW_CLIENT_ENDPOINT_UPDATE_INFO;
+        }
+
+        let mut params: Vec<*const spa_sys::spa_pod> = params
+            .unwrap_or_default()
+            .iter()
+            .map(|param| param.as_raw_ptr().cast_const())
+            .collect();
+        let info = info.map(|info| info.to_raw());
+
+        let res = unsafe {
+            spa_interface_call_method!(
+                self.proxy.as_ptr(),
+                pw_sys::pw_client_endpoint_methods,
+                update,
+                change_mask,
+                params.len() as u32,
+                params.as_mut_ptr(),
+                info.as_ref().map_or(ptr::null(), |info| info as *const _)
+            )
+        };
+
+        let res = SpaResult::from_c(res).into_async_result()?;
+        Ok(res)
+    }
+
+    /// Add or update the stream `stream_id` of the endpoint.
+    ///
+    /// `params` and `info` are handled as in [`update()`](`Self::update()`).
+    pub fn stream_update(
+        &self,
+        stream_id: u32,
+        params: Option<&[&Pod]>,
+        info: Option<&ClientEndpointStreamInfo>,
+    ) -> Result<AsyncSeq, Error> {
+        let mut change_mask = 0;
+        if params.is_some() {
+            change_mask |= pw_sys::PW_CLIENT_ENDPOINT_STREAM_UPDATE_PARAMS;
+        }
+        if info.is_some() {
+            change_mask |= pw_sys::PW_CLIENT_ENDPOINT_STREAM_UPDATE_INFO;
+        }
+
+        self.call_stream_update(stream_id, change_mask, params.unwrap_or_default(), info)
+    }
+
+    /// Remove the stream `stream_id` of the endpoint.
+    pub fn stream_destroyed(&self, stream_id: u32) -> Result<AsyncSeq, Error> {
+        self.call_stream_update(
+            stream_id,
+            pw_sys::PW_CLIENT_ENDPOINT_STREAM_UPDATE_DESTROYED,
+            &[],
+            None,
+        )
+    }
+
+    fn call_stream_update(
+        &self,
+        stream_id: u32,
+        change_mask: u32,
+        params: &[&Pod],
+        info: Option<&ClientEndpointStreamInfo>,
+    ) -> Result<AsyncSeq, Error> {
+        let mut params: Vec<*const spa_sys::spa_pod> = params
+            .iter()
+            .map(|param| param.as_raw_ptr().cast_const())
+            .collect();
+        let info = info.map(|info| info.to_raw(stream_id));
+
+        let res = unsafe {
+            spa_interface_call_method!(
+                self.proxy.as_ptr(),
+                pw_sys::pw_client_endpoint_methods,
+                stream_update,
+                stream_id,
+                change_mask,
+                params.len() as u32,
+                params.as_mut_ptr(),
+                info.as_ref().map_or(ptr::null(), |info| info as *const _)
+            )
+        };
+
+        let res = SpaResult::from_c(res).into_async_result()?;
+        Ok(res)
+    }
+}
+
+impl ProxyT for ClientEndpoint {
+    fn type_() -> ObjectType {
+        ObjectType::ClientEndpoint
+    }
+
+    fn upcast(self) -> Proxy {
+        self.proxy
+    }
+
+    fn upcast_ref(&self) -> &Proxy {
+        &self.proxy
+    }
+
+    unsafe fn from_proxy_unchecked(proxy: Proxy) -> Self
+    where
+        Self: Sized,
+    {
+        Self { proxy }
+    }
+}
+
+#[derive(Default)]
+struct ListenerLocalCallbacks {
+    #[allow(clippy::type_complexity)]
+    set_session_id: Option<Box<dyn Fn(u32) -> Result<(), Errno>>>,
+    #[allow(clippy::type_complexity)]
+    set_param: Option<Box<dyn Fn(ParamType, u32, Option<&Pod>) -> Result<(), Errno>>>,
+    #[allow(clippy::type_complexity)]
+    stream_set_param: Option<Box<dyn Fn(u32, ParamType, u32, Option<&Pod>) -> Result<(), Errno>>>,
+    #[allow(clippy::type_complexity)]
+    create_link: Option<Box<dyn Fn(&DictRef) -> Result<(), Errno>>>,
+}
+
+pub struct ClientEndpointListenerLocalBuilder<'a> {
+    endpoint: &'a ClientEndpoint,
+    cbs: ListenerLocalCallbacks,
+}
+
+pub struct ClientEndpointListener {
+    // Need to stay allocated while the listener is registered
+    #[allow(dead_code)]
+    events: Pin<Box<pw_sys::pw_client_endpoint_events>>,
+    listener: Pin<Box<spa_sys::spa_hook>>,
+    #[allow(dead_code)]
+    data: Box<ListenerLocalCallbacks>,
+}
+
+impl Listener for ClientEndpointListener {}
+
+impl Drop for ClientEndpointListener {
+    fn drop(&mut self) {
+        spa::utils::hook::remove(*self.listener);
+    }
+}
+
+impl<'a> ClientEndpointListenerLocalBuilder<'a> {
+    /// The endpoint was associated with the session `session_id`,
+    /// which must be set in the `session_id` field of the info of the endpoint.
+    #[must_use]
+    pub fn set_session_id<F>(mut self, set_session_id: F) -> Self
+    where
+        F: Fn(u32) -> Result<(), Errno> + 'static,
+    {
+        self.cbs.set_session_id = Some(Box::new(set_session_id));
+        self
+    }
+
+    /// A client called [`Endpoint::set_param`](`super::Endpoint::set_param`) on the endpoint.
+    #[must_use]
+    pub fn set_param<F>(mut self, set_param: F) -> Self
+    where
+        F: Fn(ParamType, u32, Option<&Pod>) -> Result<(), Errno> + 'static,
+    {
+        self.cbs.set_param = Some(Box::new(set_param));
+        self
+    }
+
+    /// A client set a param on a stream of the endpoint, passing the id of the stream,
+    /// the id, flags and value of the param.
+    #[must_use]
+    pub fn stream_set_param<F>(mut self, stream_set_param: F) -> Self
+    where
+        F: Fn(u32, ParamType, u32, Option<&Pod>) -> Result<(), Errno> + 'static,
+    {
+        self.cbs.stream_set_param = Some(Box::new(stream_set_param));
+        self
+    }
+
+    /// A client called [`Endpoint::create_link`](`super::Endpoint::create_link`) on the endpoint.
+    #[must_use]
+    pub fn create_link<F>(mut self, create_link: F) -> Self
+    where
+        F: Fn(&DictRef) -> Result<(), Errno> + 'static,
+    {
+        self.cbs.create_link = Some(Box::new(create_link));
+        self
+    }
+
+    #[must_use]
+    pub fn register(self) -> ClientEndpointListener {
+        unsafe extern "C" fn client_endpoint_events_set_session_id(
+            data: *mut c_void,
+            session_id: u32,
+        ) -> c_int {
+            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
+            to_c(callbacks.set_session_id.as_ref().unwrap()(session_id))
+        }
+
+        unsafe extern "C" fn client_endpoint_events_set_param(
+            data: *mut c_void,
+            id: u32,
+            flags: u32,
+            param: *const spa_sys::spa_pod,
+        ) -> c_int {
+            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
+            let param = (!param.is_null()).then(|| Pod::from_raw(param));
+            to_c(callbacks.set_param.as_ref().unwrap()(
+                ParamType::from_raw(id),
+                flags,
+                param,
+            ))
+        }
+
+        unsafe extern "C" fn client_endpoint_events_stream_set_param(
+            data: *mut c_void,
+            stream_id: u32,
+            id: u32,
+            flags: u32,
+            param: *const spa_sys::spa_pod,
+        ) -> c_int {
+            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
+            let param = (!param.is_null()).then(|| Pod::from_raw(param));
+            to_c(callbacks.stream_set_param.as_ref().unwrap()(
+                stream_id,
+                ParamType::from_raw(id),
+                flags,
+                param,
+            ))
+        }
+
+        unsafe extern "C" fn client_endpoint_events_create_link(
+            data: *mut c_void,
+            props: *const spa_sys::spa_dict,
+        ) -> c_int {
+            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
+            let props = ptr::NonNull::new(props.cast_mut())
+                .expect("props is NULL")
+                .cast::<DictRef>()
+                .as_ref();
+            to_c(callbacks.create_link.as_ref().unwrap()(props))
+        }
+
+        let e = unsafe {
+            let mut e: Pin<Box<pw_sys::pw_client_endpoint_events>> = Box::pin(mem::zeroed());
+            e.version = pw_sys::PW_VERSION_CLIENT_ENDPOINT_EVENTS;
+
+            if self.cbs.set_session_id.is_some() {
+                e.set_session_id = Some(client_endpoint_events_set_session_id);
+            }
+            if self.cbs.set_param.is_some() {
+                e.set_param = Some(client_endpoint_events_set_param);
+            }
+            if self.cbs.stream_set_param.is_some() {
+                e.stream_set_param = Some(client_endpoint_events_stream_set_param);
+            }
+            if self.cbs.create_link.is_some() {
+                e.create_link = Some(client_endpoint_events_create_link);
+            }
+
+            e
+        };
+
+        let (listener, data) = unsafe {
+            let endpoint = &self.endpoint.proxy.as_ptr();
+
+            let data = Box::into_raw(Box::new(self.cbs));
+            let mut listener: Pin<Box<spa_sys::spa_hook>> = Box::pin(mem::zeroed());
+            let listener_ptr: *mut spa_sys::spa_hook = listener.as_mut().get_unchecked_mut();
+
+            spa_interface_call_method!(
+                endpoint,
+                pw_sys::pw_client_endpoint_methods,
+                add_listener,
+                listener_ptr.cast(),
+                e.as_ref().get_ref(),
+                data as *mut _
+            );
+
+            (listener, Box::from_raw(data))
+        };
+
+        ClientEndpointListener {
+            events: e,
+            listener,
+            data,
+        }
+    }
+}
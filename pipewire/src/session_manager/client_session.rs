@@ -0,0 +1,405 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+use std::{
+    ffi::{c_int, c_void, CString},
+    mem,
+    pin::Pin,
+    ptr,
+};
+
+use spa::{
+    param::{ParamInfo, ParamType},
+    pod::Pod,
+    spa_interface_call_method,
+    utils::{
+        dict::DictRef,
+        result::{to_c, AsyncSeq, Errno, SpaResult},
+    },
+};
+
+use super::{EndpointLinkChangeMask, EndpointLinkState, SessionChangeMask};
+use crate::{
+    proxy::{Listener, Proxy, ProxyT},
+    types::ObjectType,
+    Error,
+};
+
+/// A session implemented by this client, usually a session manager.
+///
+/// A client session is created with the `client-session` factory of the session manager module,
+/// see [`ClientEndpoint`](`super::ClientEndpoint`) for an example.
+///
+/// The server exposes it to other clients as a [`Session`](`super::Session`) once its info
+/// is published with [`update()`](`Self::update()`), along with the
+/// [`EndpointLink`](`super::EndpointLink`)s published with [`link_update()`](`Self::link_update()`),
+/// and forwards the requests made on them to the listeners of this proxy.
+#[derive(Debug)]
+pub struct ClientSession {
+    proxy: Proxy,
+}
+
+/// The info of a [`ClientSession`], published with [`ClientSession::update`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ClientSessionInfo<'a> {
+    /// Which of the following fields changed.
+    pub change_mask: SessionChangeMask,
+    pub props: Option<&'a DictRef>,
+    pub params: &'a [ParamInfo],
+}
+
+impl ClientSessionInfo<'_> {
+    fn to_raw(self) -> pw_sys::pw_session_info {
+        pw_sys::pw_session_info {
+            version: pw_sys::PW_VERSION_SESSION_INFO,
+            id: crate::constants::ID_ANY,
+            change_mask: self.change_mask.bits(),
+            props: self
+                .props
+                .map_or(ptr::null_mut(), |props| props.as_raw_ptr()),
+            params: self.params.as_ptr().cast_mut().cast(),
+            n_params: self.params.len() as u32,
+        }
+    }
+}
+
+/// The info of a link of a [`ClientSession`], published with [`ClientSession::link_update`].
+#[derive(Debug, Clone, Copy)]
+pub struct ClientSessionLinkInfo<'a> {
+    pub output_endpoint_id: u32,
+    pub output_stream_id: u32,
+    pub input_endpoint_id: u32,
+    pub input_stream_id: u32,
+    /// Which of the following fields changed.
+    pub change_mask: EndpointLinkChangeMask,
+    pub state: EndpointLinkState<'a>,
+    pub props: Option<&'a DictRef>,
+    pub params: &'a [ParamInfo],
+}
+
+impl ClientSessionLinkInfo<'_> {
+    fn to_raw(self, link_id: u32, error: Option<&CString>) -> pw_sys::pw_endpoint_link_info {
+        pw_sys::pw_endpoint_link_info {
+            version: pw_sys::PW_VERSION_ENDPOINT_LINK_INFO,
+            id: link_id,
+            session_id: crate::constants::ID_ANY,
+            output_endpoint_id: self.output_endpoint_id,
+            output_stream_id: self.output_stream_id,
+            input_endpoint_id: self.input_endpoint_id,
+            input_stream_id: self.input_stream_id,
+            change_mask: self.change_mask.bits(),
+            state: self.state.as_raw(),
+            error: error.map_or(ptr::null_mut(), |error| error.as_ptr().cast_mut()),
+            props: self
+                .props
+                .map_or(ptr::null_mut(), |props| props.as_raw_ptr()),
+            params: self.params.as_ptr().cast_mut().cast(),
+            n_params: self.params.len() as u32,
+        }
+    }
+}
+
+impl ClientSession {
+    // TODO: add non-local version when we'll bind pw_thread_loop_start()
+    #[must_use]
+    pub fn add_listener_local(&self) -> ClientSessionListenerLocalBuilder {
+        ClientSessionListenerLocalBuilder {
+            session: self,
+            cbs: ListenerLocalCallbacks::default(),
+        }
+    }
+
+    /// Update the session.
+    ///
+    /// If `params` is not [`None`], the params of the session are replaced with `params`.
+    /// If `info` is not [`None`], the fields of the info of the session
+    /// set in its `change_mask` are updated.
+    pub fn update(
+        &self,
+        params: Option<&[&Pod]>,
+        info: Option<&ClientSessionInfo>,
+    ) -> Result<AsyncSeq, Error> {
+        let mut change_mask = 0;
+        if params.is_some() {
+            change_mask |= pw_sys::PW_CLIENT_SESSION_UPDATE_PARAMS;
+        }
+        if info.is_some() {
+            change_mask |= pw_sys::PW_CLIENT_SESSION_UPDATE_INFO;
+        }
+
+        let mut params: Vec<*const spa_sys::spa_pod> = params
+            .unwrap_or_default()
+            .iter()
+            .map(|param| param.as_raw_ptr().cast_const())
+            .collect();
+        let info = info.map(|info| info.to_raw());
+
+        let res = unsafe {
+            spa_interface_call_method!(
+                self.proxy.as_ptr(),
+                pw_sys::pw_client_session_methods,
+                update,
+                change_mask,
+                params.len() as u32,
+                params.as_mut_ptr(),
+                info.as_ref().map_or(ptr::null(), |info| info as *const _)
+            )
+        };
+
+        let res = SpaResult::from_c(res).into_async_result()?;
+        Ok(res)
+    }
+
+    /// Add or update the link `link_id` of the session.
+    ///
+    /// `params` and `info` are handled as in [`update()`](`Self::update()`).
+    pub fn link_update(
+        &self,
+        link_id: u32,
+        params: Option<&[&Pod]>,
+        info: Option<&ClientSessionLinkInfo>,
+    ) -> Result<AsyncSeq, Error> {
+        let mut change_mask = 0;
+        if params.is_some() {
+            change_mask |= pw_sys::PW_CLIENT_SESSION_LINK_UPDATE_PARAMS;
+        }
+        if info.is_some() {
+            change_mask |= pw_sys::PW_CLIENT_SESSION_LINK_UPDATE_INFO;
+        }
+
+        self.call_link_update(link_id, change_mask, params.unwrap_or_default(), info)
+    }
+
+    /// Remove the link `link_id` of the session.
+    pub fn link_destroyed(&self, link_id: u32) -> Result<AsyncSeq, Error> {
+        self.call_link_update(
+            link_id,
+            pw_sys::PW_CLIENT_SESSION_LINK_UPDATE_DESTROYED,
+            &[],
+            None,
+        )
+    }
+
+    fn call_link_update(
+        &self,
+        link_id: u32,
+        change_mask: u32,
+        params: &[&Pod],
+        info: Option<&ClientSessionLinkInfo>,
+    ) -> Result<AsyncSeq, Error> {
+        let mut params: Vec<*const spa_sys::spa_pod> = params
+            .iter()
+            .map(|param| param.as_raw_ptr().cast_const())
+            .collect();
+        let error = match info.map(|info| info.state) {
+            Some(EndpointLinkState::Error(error)) => {
+                Some(CString::new(error).expect("Null byte in link error"))
+            }
+            _ => None,
+        };
+        let info = info.map(|info| info.to_raw(link_id, error.as_ref()));
+
+        let res = unsafe {
+            spa_interface_call_method!(
+                self.proxy.as_ptr(),
+                pw_sys::pw_client_session_methods,
+                link_update,
+                link_id,
+                change_mask,
+                params.len() as u32,
+                params.as_mut_ptr(),
+                info.as_ref().map_or(ptr::null(), |info| info as *const _)
+            )
+        };
+
+        let res = SpaResult::from_c(res).into_async_result()?;
+        Ok(res)
+    }
+}
+
+impl ProxyT for ClientSession {
+    fn type_() -> ObjectType {
+        ObjectType::ClientSession
+    }
+
+    fn upcast(self) -> Proxy {
+        self.proxy
+    }
+
+    fn upcast_ref(&self) -> &Proxy {
+        &self.proxy
+    }
+
+    unsafe fn from_proxy_unchecked(proxy: Proxy) -> Self
+    where
+        Self: Sized,
+    {
+        Self { proxy }
+    }
+}
+
+#[derive(Default)]
+struct ListenerLocalCallbacks {
+    #[allow(clippy::type_complexity)]
+    set_param: Option<Box<dyn Fn(ParamType, u32, Option<&Pod>) -> Result<(), Errno>>>,
+    #[allow(clippy::type_complexity)]
+    link_set_param: Option<Box<dyn Fn(u32, ParamType, u32, Option<&Pod>) -> Result<(), Errno>>>,
+    #[allow(clippy::type_complexity)]
+    link_request_state: Option<Box<dyn Fn(u32, EndpointLinkState) -> Result<(), Errno>>>,
+}
+
+pub struct ClientSessionListenerLocalBuilder<'a> {
+    session: &'a ClientSession,
+    cbs: ListenerLocalCallbacks,
+}
+
+pub struct ClientSessionListener {
+    // Need to stay allocated while the listener is registered
+    #[allow(dead_code)]
+    events: Pin<Box<pw_sys::pw_client_session_events>>,
+    listener: Pin<Box<spa_sys::spa_hook>>,
+    #[allow(dead_code)]
+    data: Box<ListenerLocalCallbacks>,
+}
+
+impl Listener for ClientSessionListener {}
+
+impl Drop for ClientSessionListener {
+    fn drop(&mut self) {
+        spa::utils::hook::remove(*self.listener);
+    }
+}
+
+impl<'a> ClientSessionListenerLocalBuilder<'a> {
+    /// A client called [`Session::set_param`](`super::Session::set_param`) on the session.
+    #[must_use]
+    pub fn set_param<F>(mut self, set_param: F) -> Self
+    where
+        F: Fn(ParamType, u32, Option<&Pod>) -> Result<(), Errno> + 'static,
+    {
+        self.cbs.set_param = Some(Box::new(set_param));
+        self
+    }
+
+    /// A client called [`EndpointLink::set_param`](`super::EndpointLink::set_param`)
+    /// on a link of the session, passing the id of the link, the id, flags and value of the param.
+    #[must_use]
+    pub fn link_set_param<F>(mut self, link_set_param: F) -> Self
+    where
+        F: Fn(u32, ParamType, u32, Option<&Pod>) -> Result<(), Errno> + 'static,
+    {
+        self.cbs.link_set_param = Some(Box::new(link_set_param));
+        self
+    }
+
+    /// A client called [`EndpointLink::request_state`](`super::EndpointLink::request_state`)
+    /// on a link of the session, passing the id of the link and the requested state.
+    #[must_use]
+    pub fn link_request_state<F>(mut self, link_request_state: F) -> Self
+    where
+        F: Fn(u32, EndpointLinkState) -> Result<(), Errno> + 'static,
+    {
+        self.cbs.link_request_state = Some(Box::new(link_request_state));
+        self
+    }
+
+    #[must_use]
+    pub fn register(self) -> ClientSessionListener {
+        unsafe extern "C" fn client_session_events_set_param(
+            data: *mut c_void,
+            id: u32,
+            flags: u32,
+            param: *const spa_sys::spa_pod,
+        ) -> c_int {
+            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
+            let param = (!param.is_null()).then(|| Pod::from_raw(param));
+            to_c(callbacks.set_param.as_ref().unwrap()(
+                ParamType::from_raw(id),
+                flags,
+                param,
+            ))
+        }
+
+        unsafe extern "C" fn client_session_events_link_set_param(
+            data: *mut c_void,
+            link_id: u32,
+            id: u32,
+            flags: u32,
+            param: *const spa_sys::spa_pod,
+        ) -> c_int {
+            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
+            let param = (!param.is_null()).then(|| Pod::from_raw(param));
+            to_c(callbacks.link_set_param.as_ref().unwrap()(
+                link_id,
+                ParamType::from_raw(id),
+                flags,
+                param,
+            ))
+        }
+
+        unsafe extern "C" fn client_session_events_link_request_state(
+            data: *mut c_void,
+            link_id: u32,
+            state: u32,
+        ) -> c_int {
+            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
+            let state = match state as pw_sys::pw_endpoint_link_state {
+                pw_sys::pw_endpoint_link_state_PW_ENDPOINT_LINK_STATE_PREPARING => {
+                    EndpointLinkState::Preparing
+                }
+                pw_sys::pw_endpoint_link_state_PW_ENDPOINT_LINK_STATE_INACTIVE => {
+                    EndpointLinkState::Inactive
+                }
+                pw_sys::pw_endpoint_link_state_PW_ENDPOINT_LINK_STATE_ACTIVE => {
+                    EndpointLinkState::Active
+                }
+                _ => return -(Errno::EINVAL as c_int),
+            };
+            to_c(callbacks.link_request_state.as_ref().unwrap()(
+                link_id, state,
+            ))
+        }
+
+        let e = unsafe {
+            let mut e: Pin<Box<pw_sys::pw_client_session_events>> = Box::pin(mem::zeroed());
+            e.version = pw_sys::PW_VERSION_CLIENT_SESSION_EVENTS;
+
+            if self.cbs.set_param.is_some() {
+                e.set_param = Some(client_session_events_set_param);
+            }
+            if self.cbs.link_set_param.is_some() {
+                e.link_set_param = Some(client_session_events_link_set_param);
+            }
+            if self.cbs.link_request_state.is_some() {
+                e.link_request_state = Some(client_session_events_link_request_state);
+            }
+
+            e
+        };
+
+        let (listener, data) = unsafe {
+            let session = &self.session.proxy.as_ptr();
+
+            let data = Box::into_raw(Box::new(self.cbs));
+            let mut listener: Pin<Box<spa_sys::spa_hook>> = Box::pin(mem::zeroed());
+            let listener_ptr: *mut spa_sys::spa_hook = listener.as_mut().get_unchecked_mut();
+
+            spa_interface_call_method!(
+                session,
+                pw_sys::pw_client_session_methods,
+                add_listener,
+                listener_ptr.cast(),
+                e.as_ref().get_ref(),
+                data as *mut _
+            );
+
+            (listener, Box::from_raw(data))
+        };
+
+        ClientSessionListener {
+            events: e,
+            listener,
+            data,
+        }
+    }
+}
@@ -0,0 +1,399 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+use std::{
+    ffi::{c_void, CStr},
+    fmt, mem,
+    pin::Pin,
+    ptr,
+};
+
+use bitflags::bitflags;
+use spa::{
+    pod::Pod,
+    spa_interface_call_method,
+    utils::{
+        dict::DictRef,
+        result::{AsyncSeq, SpaResult},
+        Direction,
+    },
+};
+
+use crate::{
+    proxy::{Listener, Proxy, ProxyT},
+    types::ObjectType,
+    Error,
+};
+
+/// A proxy to an endpoint, a logical source or sink of media managed by a session manager,
+/// such as a speaker or a microphone.
+#[derive(Debug)]
+pub struct Endpoint {
+    proxy: Proxy,
+}
+
+impl Endpoint {
+    // TODO: add non-local version when we'll bind pw_thread_loop_start()
+    #[must_use]
+    pub fn add_listener_local(&self) -> EndpointListenerLocalBuilder {
+        EndpointListenerLocalBuilder {
+            endpoint: self,
+            cbs: ListenerLocalCallbacks::default(),
+        }
+    }
+
+    /// Subscribe to parameter changes
+    ///
+    /// Automatically emit `param` events for the given ids when they are changed
+    pub fn subscribe_params(&self, ids: &[spa::param::ParamType]) -> Result<AsyncSeq, Error> {
+        let res = unsafe {
+            spa_interface_call_method!(
+                self.proxy.as_ptr(),
+                pw_sys::pw_endpoint_methods,
+                subscribe_params,
+                ids.as_ptr() as *mut _,
+                ids.len().try_into().unwrap()
+            )
+        };
+
+        let res = SpaResult::from_c(res).into_async_result()?;
+        Ok(res)
+    }
+
+    /// Enumerate endpoint parameters
+    ///
+    /// Start enumeration of endpoint parameters. For each param, a
+    /// param event will be emitted.
+    ///
+    /// # Parameters
+    /// `seq`: a sequence number to place in the reply \
+    /// `id`: the parameter id to enum, or [`None`] to allow any id \
+    /// `start`: the start index or 0 for the first param \
+    /// `num`: the maximum number of params to retrieve ([`u32::MAX`] may be used to retrieve all params) \
+    /// `filter`: a param to filter the enumerated params with, or [`None`] to retrieve all params
+    pub fn enum_params(
+        &self,
+        seq: i32,
+        id: Option<spa::param::ParamType>,
+        start: u32,
+        num: u32,
+        filter: Option<&Pod>,
+    ) -> Result<AsyncSeq, Error> {
+        let id = id.map(|id| id.as_raw()).unwrap_or(crate::constants::ID_ANY);
+        let filter = filter.map_or(std::ptr::null(), |filter| filter.as_raw_ptr().cast_const());
+
+        let res = unsafe {
+            spa_interface_call_method!(
+                self.proxy.as_ptr(),
+                pw_sys::pw_endpoint_methods,
+                enum_params,
+                seq,
+                id,
+                start,
+                num,
+                filter
+            )
+        };
+
+        let res = SpaResult::from_c(res).into_async_result()?;
+        Ok(res)
+    }
+
+    /// Set a parameter on the endpoint
+    ///
+    /// Errors reported asynchronously by the remote are emitted through the proxy
+    /// `error` event with the returned [`AsyncSeq`].
+    pub fn set_param(
+        &self,
+        id: spa::param::ParamType,
+        flags: u32,
+        param: &Pod,
+    ) -> Result<AsyncSeq, Error> {
+        let res = unsafe {
+            spa_interface_call_method!(
+                self.proxy.as_ptr(),
+                pw_sys::pw_endpoint_methods,
+                set_param,
+                id.as_raw(),
+                flags,
+                param.as_raw_ptr()
+            )
+        };
+
+        SpaResult::from_c(res)
+            .into_async_result()
+            .map_err(|e| Error::SetParam {
+                id,
+                errno: nix::errno::Errno::from_raw(e.errno() as i32),
+            })
+    }
+
+    /// Ask the session manager to link a stream of this endpoint to another endpoint.
+    ///
+    /// The link is described by `props`, using keys such as `endpoint-link.output.stream`,
+    /// `endpoint-link.input.endpoint` and `endpoint-link.input.stream`.
+    /// Once created, the link appears in the registry as an
+    /// [`EndpointLink`](`super::EndpointLink`).
+    pub fn create_link(&self, props: &DictRef) -> Result<AsyncSeq, Error> {
+        let res = unsafe {
+            spa_interface_call_method!(
+                self.proxy.as_ptr(),
+                pw_sys::pw_endpoint_methods,
+                create_link,
+                props.as_raw_ptr()
+            )
+        };
+
+        let res = SpaResult::from_c(res).into_async_result()?;
+        Ok(res)
+    }
+}
+
+impl ProxyT for Endpoint {
+    fn type_() -> ObjectType {
+        ObjectType::Endpoint
+    }
+
+    fn upcast(self) -> Proxy {
+        self.proxy
+    }
+
+    fn upcast_ref(&self) -> &Proxy {
+        &self.proxy
+    }
+
+    unsafe fn from_proxy_unchecked(proxy: Proxy) -> Self
+    where
+        Self: Sized,
+    {
+        Self { proxy }
+    }
+}
+
+#[derive(Default)]
+struct ListenerLocalCallbacks {
+    #[allow(clippy::type_complexity)]
+    info: Option<Box<dyn Fn(&EndpointInfoRef)>>,
+    #[allow(clippy::type_complexity)]
+    param: Option<Box<dyn Fn(i32, spa::param::ParamType, u32, u32, Option<&Pod>)>>,
+}
+
+pub struct EndpointListenerLocalBuilder<'a> {
+    endpoint: &'a Endpoint,
+    cbs: ListenerLocalCallbacks,
+}
+
+#[repr(transparent)]
+pub struct EndpointInfoRef(pw_sys::pw_endpoint_info);
+
+impl EndpointInfoRef {
+    pub fn as_raw(&self) -> &pw_sys::pw_endpoint_info {
+        &self.0
+    }
+
+    pub fn as_raw_ptr(&self) -> *mut pw_sys::pw_endpoint_info {
+        std::ptr::addr_of!(self.0).cast_mut()
+    }
+
+    pub fn id(&self) -> u32 {
+        self.0.id
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        let name = self.0.name;
+        (!name.is_null()).then(|| unsafe { CStr::from_ptr(name).to_str().unwrap() })
+    }
+
+    /// The media class of the endpoint, such as `Audio/Sink`.
+    pub fn media_class(&self) -> Option<&str> {
+        let media_class = self.0.media_class;
+        (!media_class.is_null()).then(|| unsafe { CStr::from_ptr(media_class).to_str().unwrap() })
+    }
+
+    pub fn direction(&self) -> Direction {
+        Direction::from_raw(self.0.direction)
+    }
+
+    pub fn flags(&self) -> EndpointFlags {
+        EndpointFlags::from_bits_retain(self.0.flags)
+    }
+
+    pub fn change_mask(&self) -> EndpointChangeMask {
+        EndpointChangeMask::from_bits_retain(self.0.change_mask)
+    }
+
+    pub fn n_streams(&self) -> u32 {
+        self.0.n_streams
+    }
+
+    /// The id of the global of the session the endpoint belongs to.
+    pub fn session_id(&self) -> u32 {
+        self.0.session_id
+    }
+
+    pub fn props(&self) -> Option<&DictRef> {
+        let props_ptr: *mut DictRef = self.0.props.cast();
+        ptr::NonNull::new(props_ptr).map(|ptr| unsafe { ptr.as_ref() })
+    }
+
+    /// Get the param infos for the endpoint.
+    pub fn params(&self) -> &[spa::param::ParamInfo] {
+        unsafe {
+            let params_ptr = self.0.params;
+
+            if params_ptr.is_null() {
+                &[]
+            } else {
+                std::slice::from_raw_parts(
+                    params_ptr as *const _,
+                    self.0.n_params.try_into().unwrap(),
+                )
+            }
+        }
+    }
+}
+
+impl fmt::Debug for EndpointInfoRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EndpointInfoRef")
+            .field("id", &self.id())
+            .field("name", &self.name())
+            .field("media-class", &self.media_class())
+            .field("direction", &self.direction())
+            .field("flags", &self.flags())
+            .field("change-mask", &self.change_mask())
+            .field("n-streams", &self.n_streams())
+            .field("session-id", &self.session_id())
+            .field("props", &self.props())
+            .field("params", &self.params())
+            .finish()
+    }
+}
+
+bitflags! {
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub struct EndpointFlags: u32 {
+        /// The endpoint is the master of its session.
+        const PROVIDES_SESSION = pw_sys::PW_ENDPOINT_FLAG_PROVIDES_SESSION;
+    }
+}
+
+bitflags! {
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub struct EndpointChangeMask: u64 {
+        const STREAMS = pw_sys::PW_ENDPOINT_CHANGE_MASK_STREAMS as u64;
+        const SESSION = pw_sys::PW_ENDPOINT_CHANGE_MASK_SESSION as u64;
+        const PROPS = pw_sys::PW_ENDPOINT_CHANGE_MASK_PROPS as u64;
+        const PARAMS = pw_sys::PW_ENDPOINT_CHANGE_MASK_PARAMS as u64;
+    }
+}
+
+pub struct EndpointListener {
+    // Need to stay allocated while the listener is registered
+    #[allow(dead_code)]
+    events: Pin<Box<pw_sys::pw_endpoint_events>>,
+    listener: Pin<Box<spa_sys::spa_hook>>,
+    #[allow(dead_code)]
+    data: Box<ListenerLocalCallbacks>,
+}
+
+impl Listener for EndpointListener {}
+
+impl Drop for EndpointListener {
+    fn drop(&mut self) {
+        spa::utils::hook::remove(*self.listener);
+    }
+}
+
+impl<'a> EndpointListenerLocalBuilder<'a> {
+    #[must_use]
+    pub fn info<F>(mut self, info: F) -> Self
+    where
+        F: Fn(&EndpointInfoRef) + 'static,
+    {
+        self.cbs.info = Some(Box::new(info));
+        self
+    }
+
+    #[must_use]
+    pub fn param<F>(mut self, param: F) -> Self
+    where
+        F: Fn(i32, spa::param::ParamType, u32, u32, Option<&Pod>) + 'static,
+    {
+        self.cbs.param = Some(Box::new(param));
+        self
+    }
+
+    #[must_use]
+    pub fn register(self) -> EndpointListener {
+        unsafe extern "C" fn endpoint_events_info(
+            data: *mut c_void,
+            info: *const pw_sys::pw_endpoint_info,
+        ) {
+            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
+            let info =
+                ptr::NonNull::new(info as *mut pw_sys::pw_endpoint_info).expect("info is NULL");
+            let info = info.cast::<EndpointInfoRef>().as_ref();
+            callbacks.info.as_ref().unwrap()(info);
+        }
+
+        unsafe extern "C" fn endpoint_events_param(
+            data: *mut c_void,
+            seq: i32,
+            id: u32,
+            index: u32,
+            next: u32,
+            param: *const spa_sys::spa_pod,
+        ) {
+            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
+
+            let id = spa::param::ParamType::from_raw(id);
+            let param = if !param.is_null() {
+                unsafe { Some(Pod::from_raw(param)) }
+            } else {
+                None
+            };
+
+            callbacks.param.as_ref().unwrap()(seq, id, index, next, param);
+        }
+
+        let e = unsafe {
+            let mut e: Pin<Box<pw_sys::pw_endpoint_events>> = Box::pin(mem::zeroed());
+            e.version = pw_sys::PW_VERSION_ENDPOINT_EVENTS;
+
+            if self.cbs.info.is_some() {
+                e.info = Some(endpoint_events_info);
+            }
+            if self.cbs.param.is_some() {
+                e.param = Some(endpoint_events_param);
+            }
+
+            e
+        };
+
+        let (listener, data) = unsafe {
+            let endpoint = &self.endpoint.proxy.as_ptr();
+
+            let data = Box::into_raw(Box::new(self.cbs));
+            let mut listener: Pin<Box<spa_sys::spa_hook>> = Box::pin(mem::zeroed());
+            let listener_ptr: *mut spa_sys::spa_hook = listener.as_mut().get_unchecked_mut();
+
+            spa_interface_call_method!(
+                endpoint,
+                pw_sys::pw_endpoint_methods,
+                add_listener,
+                listener_ptr.cast(),
+                e.as_ref().get_ref(),
+                data as *mut _
+            );
+
+            (listener, Box::from_raw(data))
+        };
+
+        EndpointListener {
+            events: e,
+            listener,
+            data,
+        }
+    }
+}
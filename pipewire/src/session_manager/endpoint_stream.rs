@@ -0,0 +1,354 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+use std::{
+    ffi::{c_void, CStr},
+    fmt, mem,
+    pin::Pin,
+    ptr,
+};
+
+use bitflags::bitflags;
+use spa::{
+    pod::Pod,
+    spa_interface_call_method,
+    utils::{
+        dict::DictRef,
+        result::{AsyncSeq, SpaResult},
+    },
+};
+
+use crate::{
+    proxy::{Listener, Proxy, ProxyT},
+    types::ObjectType,
+    Error,
+};
+
+/// A proxy to a stream of an [`Endpoint`](`super::Endpoint`), which can be linked to
+/// a stream of another endpoint.
+#[derive(Debug)]
+pub struct EndpointStream {
+    proxy: Proxy,
+}
+
+impl EndpointStream {
+    // TODO: add non-local version when we'll bind pw_thread_loop_start()
+    #[must_use]
+    pub fn add_listener_local(&self) -> EndpointStreamListenerLocalBuilder {
+        EndpointStreamListenerLocalBuilder {
+            endpoint_stream: self,
+            cbs: ListenerLocalCallbacks::default(),
+        }
+    }
+
+    /// Subscribe to parameter changes
+    ///
+    /// Automatically emit `param` events for the given ids when they are changed
+    pub fn subscribe_params(&self, ids: &[spa::param::ParamType]) -> Result<AsyncSeq, Error> {
+        let res = unsafe {
+            spa_interface_call_method!(
+                self.proxy.as_ptr(),
+                pw_sys::pw_endpoint_stream_methods,
+                subscribe_params,
+                ids.as_ptr() as *mut _,
+                ids.len().try_into().unwrap()
+            )
+        };
+
+        let res = SpaResult::from_c(res).into_async_result()?;
+        Ok(res)
+    }
+
+    /// Enumerate stream parameters
+    ///
+    /// Start enumeration of stream parameters. For each param, a
+    /// param event will be emitted.
+    ///
+    /// # Parameters
+    /// `seq`: a sequence number to place in the reply \
+    /// `id`: the parameter id to enum, or [`None`] to allow any id \
+    /// `start`: the start index or 0 for the first param \
+    /// `num`: the maximum number of params to retrieve ([`u32::MAX`] may be used to retrieve all params) \
+    /// `filter`: a param to filter the enumerated params with, or [`None`] to retrieve all params
+    pub fn enum_params(
+        &self,
+        seq: i32,
+        id: Option<spa::param::ParamType>,
+        start: u32,
+        num: u32,
+        filter: Option<&Pod>,
+    ) -> Result<AsyncSeq, Error> {
+        let id = id.map(|id| id.as_raw()).unwrap_or(crate::constants::ID_ANY);
+        let filter = filter.map_or(std::ptr::null(), |filter| filter.as_raw_ptr().cast_const());
+
+        let res = unsafe {
+            spa_interface_call_method!(
+                self.proxy.as_ptr(),
+                pw_sys::pw_endpoint_stream_methods,
+                enum_params,
+                seq,
+                id,
+                start,
+                num,
+                filter
+            )
+        };
+
+        let res = SpaResult::from_c(res).into_async_result()?;
+        Ok(res)
+    }
+
+    /// Set a parameter on the stream
+    ///
+    /// Errors reported asynchronously by the remote are emitted through the proxy
+    /// `error` event with the returned [`AsyncSeq`].
+    pub fn set_param(
+        &self,
+        id: spa::param::ParamType,
+        flags: u32,
+        param: &Pod,
+    ) -> Result<AsyncSeq, Error> {
+        let res = unsafe {
+            spa_interface_call_method!(
+                self.proxy.as_ptr(),
+                pw_sys::pw_endpoint_stream_methods,
+                set_param,
+                id.as_raw(),
+                flags,
+                param.as_raw_ptr()
+            )
+        };
+
+        SpaResult::from_c(res)
+            .into_async_result()
+            .map_err(|e| Error::SetParam {
+                id,
+                errno: nix::errno::Errno::from_raw(e.errno() as i32),
+            })
+    }
+}
+
+impl ProxyT for EndpointStream {
+    fn type_() -> ObjectType {
+        ObjectType::EndpointStream
+    }
+
+    fn upcast(self) -> Proxy {
+        self.proxy
+    }
+
+    fn upcast_ref(&self) -> &Proxy {
+        &self.proxy
+    }
+
+    unsafe fn from_proxy_unchecked(proxy: Proxy) -> Self
+    where
+        Self: Sized,
+    {
+        Self { proxy }
+    }
+}
+
+#[derive(Default)]
+struct ListenerLocalCallbacks {
+    #[allow(clippy::type_complexity)]
+    info: Option<Box<dyn Fn(&EndpointStreamInfoRef)>>,
+    #[allow(clippy::type_complexity)]
+    param: Option<Box<dyn Fn(i32, spa::param::ParamType, u32, u32, Option<&Pod>)>>,
+}
+
+pub struct EndpointStreamListenerLocalBuilder<'a> {
+    endpoint_stream: &'a EndpointStream,
+    cbs: ListenerLocalCallbacks,
+}
+
+#[repr(transparent)]
+pub struct EndpointStreamInfoRef(pw_sys::pw_endpoint_stream_info);
+
+impl EndpointStreamInfoRef {
+    pub fn as_raw(&self) -> &pw_sys::pw_endpoint_stream_info {
+        &self.0
+    }
+
+    pub fn as_raw_ptr(&self) -> *mut pw_sys::pw_endpoint_stream_info {
+        std::ptr::addr_of!(self.0).cast_mut()
+    }
+
+    pub fn id(&self) -> u32 {
+        self.0.id
+    }
+
+    /// The id of the global of the endpoint the stream belongs to.
+    pub fn endpoint_id(&self) -> u32 {
+        self.0.endpoint_id
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        let name = self.0.name;
+        (!name.is_null()).then(|| unsafe { CStr::from_ptr(name).to_str().unwrap() })
+    }
+
+    pub fn change_mask(&self) -> EndpointStreamChangeMask {
+        EndpointStreamChangeMask::from_bits_retain(self.0.change_mask)
+    }
+
+    /// Information needed to link this stream, such as the supported formats.
+    pub fn link_params(&self) -> Option<&Pod> {
+        let link_params = self.0.link_params;
+        (!link_params.is_null()).then(|| unsafe { Pod::from_raw(link_params) })
+    }
+
+    pub fn props(&self) -> Option<&DictRef> {
+        let props_ptr: *mut DictRef = self.0.props.cast();
+        ptr::NonNull::new(props_ptr).map(|ptr| unsafe { ptr.as_ref() })
+    }
+
+    /// Get the param infos for the stream.
+    pub fn params(&self) -> &[spa::param::ParamInfo] {
+        unsafe {
+            let params_ptr = self.0.params;
+
+            if params_ptr.is_null() {
+                &[]
+            } else {
+                std::slice::from_raw_parts(
+                    params_ptr as *const _,
+                    self.0.n_params.try_into().unwrap(),
+                )
+            }
+        }
+    }
+}
+
+impl fmt::Debug for EndpointStreamInfoRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EndpointStreamInfoRef")
+            .field("id", &self.id())
+            .field("endpoint-id", &self.endpoint_id())
+            .field("name", &self.name())
+            .field("change-mask", &self.change_mask())
+            // TODO: .field("link-params", &self.link_params())
+            .field("props", &self.props())
+            .field("params", &self.params())
+            .finish()
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+    pub struct EndpointStreamChangeMask: u64 {
+        const LINK_PARAMS = pw_sys::PW_ENDPOINT_STREAM_CHANGE_MASK_LINK_PARAMS as u64;
+        const PROPS = pw_sys::PW_ENDPOINT_STREAM_CHANGE_MASK_PROPS as u64;
+        const PARAMS = pw_sys::PW_ENDPOINT_STREAM_CHANGE_MASK_PARAMS as u64;
+    }
+}
+
+pub struct EndpointStreamListener {
+    // Need to stay allocated while the listener is registered
+    #[allow(dead_code)]
+    events: Pin<Box<pw_sys::pw_endpoint_stream_events>>,
+    listener: Pin<Box<spa_sys::spa_hook>>,
+    #[allow(dead_code)]
+    data: Box<ListenerLocalCallbacks>,
+}
+
+impl Listener for EndpointStreamListener {}
+
+impl Drop for EndpointStreamListener {
+    fn drop(&mut self) {
+        spa::utils::hook::remove(*self.listener);
+    }
+}
+
+impl<'a> EndpointStreamListenerLocalBuilder<'a> {
+    #[must_use]
+    pub fn info<F>(mut self, info: F) -> Self
+    where
+        F: Fn(&EndpointStreamInfoRef) + 'static,
+    {
+        self.cbs.info = Some(Box::new(info));
+        self
+    }
+
+    #[must_use]
+    pub fn param<F>(mut self, param: F) -> Self
+    where
+        F: Fn(i32, spa::param::ParamType, u32, u32, Option<&Pod>) + 'static,
+    {
+        self.cbs.param = Some(Box::new(param));
+        self
+    }
+
+    #[must_use]
+    pub fn register(self) -> EndpointStreamListener {
+        unsafe extern "C" fn endpoint_stream_events_info(
+            data: *mut c_void,
+            info: *const pw_sys::pw_endpoint_stream_info,
+        ) {
+            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
+            let info = ptr::NonNull::new(info as *mut pw_sys::pw_endpoint_stream_info)
+                .expect("info is NULL");
+            let info = info.cast::<EndpointStreamInfoRef>().as_ref();
+            callbacks.info.as_ref().unwrap()(info);
+        }
+
+        unsafe extern "C" fn endpoint_stream_events_param(
+            data: *mut c_void,
+            seq: i32,
+            id: u32,
+            index: u32,
+            next: u32,
+            param: *const spa_sys::spa_pod,
+        ) {
+            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
+
+            let id = spa::param::ParamType::from_raw(id);
+            let param = if !param.is_null() {
+                unsafe { Some(Pod::from_raw(param)) }
+            } else {
+                None
+            };
+
+            callbacks.param.as_ref().unwrap()(seq, id, index, next, param);
+        }
+
+        let e = unsafe {
+            let mut e: Pin<Box<pw_sys::pw_endpoint_stream_events>> = Box::pin(mem::zeroed());
+            e.version = pw_sys::PW_VERSION_ENDPOINT_STREAM_EVENTS;
+
+            if self.cbs.info.is_some() {
+                e.info = Some(endpoint_stream_events_info);
+            }
+            if self.cbs.param.is_some() {
+                e.param = Some(endpoint_stream_events_param);
+            }
+
+            e
+        };
+
+        let (listener, data) = unsafe {
+            let endpoint_stream = &self.endpoint_stream.proxy.as_ptr();
+
+            let data = Box::into_raw(Box::new(self.cbs));
+            let mut listener: Pin<Box<spa_sys::spa_hook>> = Box::pin(mem::zeroed());
+            let listener_ptr: *mut spa_sys::spa_hook = listener.as_mut().get_unchecked_mut();
+
+            spa_interface_call_method!(
+                endpoint_stream,
+                pw_sys::pw_endpoint_stream_methods,
+                add_listener,
+                listener_ptr.cast(),
+                e.as_ref().get_ref(),
+                data as *mut _
+            );
+
+            (listener, Box::from_raw(data))
+        };
+
+        EndpointStreamListener {
+            events: e,
+            listener,
+            data,
+        }
+    }
+}
@@ -0,0 +1,28 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Session manager interfaces.
+//!
+//! A session manager can expose a higher level view of the graph as [`Session`]s of
+//! [`Endpoint`]s, such as speakers or microphones, each with one or more [`EndpointStream`]s,
+//! which are linked together with [`EndpointLink`]s.
+//! Those are bound from the registry like any other global.
+//!
+//! Clients can implement endpoints and sessions themselves with [`ClientEndpoint`] and
+//! [`ClientSession`].
+//!
+//! These interfaces require the `libpipewire-module-session-manager` module
+//! to be loaded by both the server and the client.
+
+mod client_endpoint;
+pub use client_endpoint::*;
+mod client_session;
+pub use client_session::*;
+mod endpoint;
+pub use endpoint::*;
+mod endpoint_link;
+pub use endpoint_link::*;
+mod endpoint_stream;
+pub use endpoint_stream::*;
+mod session;
+pub use session::*;
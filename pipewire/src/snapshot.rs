@@ -0,0 +1,853 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Owned snapshots of the info of the proxies.
+//!
+//! The `*InfoRef` types passed to the `info` listeners borrow the data of the event,
+//! and the `*Info` types wrap data allocated by PipeWire.
+//! The snapshots of this module copy the info into plain Rust types, which are
+//! [`Clone`] and [`Send`], and can be serialized with [serde](https://serde.rs)
+//! when the `serde` feature is enabled.
+//!
+//! Like the `pw_*_info_update()` functions, [`update()`](NodeInfoSnapshot::update) only applies
+//! the fields whose bit is set in the change mask of the update, so a snapshot can be kept up to
+//! date from the events, which only carry the changed fields.
+
+use std::collections::BTreeMap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use spa::{
+    param::ParamInfo,
+    pod::Pod,
+    utils::{dict::DictRef, Direction},
+};
+
+use crate::{
+    client::{Client, ClientChangeMask, ClientInfoRef},
+    device::{Device, DeviceChangeMask, DeviceInfoRef},
+    factory::{Factory, FactoryChangeMask, FactoryInfoRef},
+    link::{Link, LinkChangeMask, LinkInfoRef, LinkState},
+    module::{Module, ModuleChangeMask, ModuleInfoRef},
+    node::{Node, NodeChangeMask, NodeInfoRef, NodeState},
+    port::{Port, PortChangeMask, PortInfoRef},
+    proxy::{Listener, ProxyT},
+    types::ObjectType,
+    utils,
+};
+
+/// The properties of a snapshot, sorted by key.
+pub type Props = BTreeMap<String, String>;
+
+fn props(dict: Option<&DictRef>) -> Props {
+    dict.map(|dict| {
+        dict.iter()
+            .map(|(key, value)| (key.to_owned(), value.to_owned()))
+            .collect()
+    })
+    .unwrap_or_default()
+}
+
+fn aligned_format(format: &Pod) -> Vec<u64> {
+    utils::aligned_pod_bytes(format.as_bytes())
+}
+
+/// Owned state of a node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum NodeStateSnapshot {
+    Error(String),
+    Creating,
+    Suspended,
+    Idle,
+    Running,
+}
+
+impl From<NodeState<'_>> for NodeStateSnapshot {
+    fn from(state: NodeState<'_>) -> Self {
+        match state {
+            NodeState::Error(error) => Self::Error(error.to_owned()),
+            NodeState::Creating => Self::Creating,
+            NodeState::Suspended => Self::Suspended,
+            NodeState::Idle => Self::Idle,
+            NodeState::Running => Self::Running,
+        }
+    }
+}
+
+/// Owned state of a link.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum LinkStateSnapshot {
+    Error(String),
+    Unlinked,
+    Init,
+    Negotiating,
+    Allocating,
+    Paused,
+    Active,
+}
+
+impl From<LinkState<'_>> for LinkStateSnapshot {
+    fn from(state: LinkState<'_>) -> Self {
+        match state {
+            LinkState::Error(error) => Self::Error(error.to_owned()),
+            LinkState::Unlinked => Self::Unlinked,
+            LinkState::Init => Self::Init,
+            LinkState::Negotiating => Self::Negotiating,
+            LinkState::Allocating => Self::Allocating,
+            LinkState::Paused => Self::Paused,
+            LinkState::Active => Self::Active,
+        }
+    }
+}
+
+/// Owned snapshot of a [`NodeInfoRef`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NodeInfoSnapshot {
+    pub id: u32,
+    pub max_input_ports: u32,
+    pub max_output_ports: u32,
+    pub change_mask: NodeChangeMask,
+    pub n_input_ports: u32,
+    pub n_output_ports: u32,
+    pub state: NodeStateSnapshot,
+    pub props: Props,
+    #[cfg_attr(feature = "serde", serde(with = "param_infos"))]
+    pub params: Vec<ParamInfo>,
+}
+
+impl NodeInfoSnapshot {
+    /// Apply the fields of `update` set in its change mask.
+    pub fn update(&mut self, update: &NodeInfoRef) {
+        let change_mask = update.change_mask();
+        self.id = update.id();
+        self.max_input_ports = update.max_input_ports();
+        self.max_output_ports = update.max_output_ports();
+        self.change_mask = change_mask;
+        if change_mask.contains(NodeChangeMask::INPUT_PORTS) {
+            self.n_input_ports = update.n_input_ports();
+        }
+        if change_mask.contains(NodeChangeMask::OUTPUT_PORTS) {
+            self.n_output_ports = update.n_output_ports();
+        }
+        if change_mask.contains(NodeChangeMask::STATE) {
+            self.state = update.state().into();
+        }
+        if change_mask.contains(NodeChangeMask::PROPS) {
+            self.props = props(update.props());
+        }
+        if change_mask.contains(NodeChangeMask::PARAMS) {
+            self.params = update.params().to_vec();
+        }
+    }
+}
+
+impl From<&NodeInfoRef> for NodeInfoSnapshot {
+    fn from(info: &NodeInfoRef) -> Self {
+        Self {
+            id: info.id(),
+            max_input_ports: info.max_input_ports(),
+            max_output_ports: info.max_output_ports(),
+            change_mask: info.change_mask(),
+            n_input_ports: info.n_input_ports(),
+            n_output_ports: info.n_output_ports(),
+            state: info.state().into(),
+            props: props(info.props()),
+            params: info.params().to_vec(),
+        }
+    }
+}
+
+/// Owned snapshot of a [`PortInfoRef`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PortInfoSnapshot {
+    pub id: u32,
+    #[cfg_attr(feature = "serde", serde(with = "direction"))]
+    pub direction: Direction,
+    pub change_mask: PortChangeMask,
+    pub props: Props,
+    #[cfg_attr(feature = "serde", serde(with = "param_infos"))]
+    pub params: Vec<ParamInfo>,
+}
+
+impl PortInfoSnapshot {
+    /// Apply the fields of `update` set in its change mask.
+    pub fn update(&mut self, update: &PortInfoRef) {
+        let change_mask = update.change_mask();
+        self.id = update.id();
+        self.direction = update.direction();
+        self.change_mask = change_mask;
+        if change_mask.contains(PortChangeMask::PROPS) {
+            self.props = props(update.props());
+        }
+        if change_mask.contains(PortChangeMask::PARAMS) {
+            self.params = update.params().to_vec();
+        }
+    }
+}
+
+impl From<&PortInfoRef> for PortInfoSnapshot {
+    fn from(info: &PortInfoRef) -> Self {
+        Self {
+            id: info.id(),
+            direction: info.direction(),
+            change_mask: info.change_mask(),
+            props: props(info.props()),
+            params: info.params().to_vec(),
+        }
+    }
+}
+
+/// Owned snapshot of a [`LinkInfoRef`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LinkInfoSnapshot {
+    pub id: u32,
+    pub output_node_id: u32,
+    pub output_port_id: u32,
+    pub input_node_id: u32,
+    pub input_port_id: u32,
+    pub change_mask: LinkChangeMask,
+    pub state: LinkStateSnapshot,
+    // Stored as `u64` so that the pod is correctly aligned, see `format()`.
+    #[cfg_attr(feature = "serde", serde(with = "pod"))]
+    format: Option<Vec<u64>>,
+    pub props: Props,
+}
+
+impl LinkInfoSnapshot {
+    /// The negotiated format of the link.
+    pub fn format(&self) -> Option<&Pod> {
+        self.format.as_deref().and_then(utils::aligned_pod)
+    }
+
+    /// Apply the fields of `update` set in its change mask.
+    pub fn update(&mut self, update: &LinkInfoRef) {
+        let change_mask = update.change_mask();
+        self.id = update.id();
+        self.output_node_id = update.output_node_id();
+        self.output_port_id = update.output_port_id();
+        self.input_node_id = update.input_node_id();
+        self.input_port_id = update.input_port_id();
+        self.change_mask = change_mask;
+        if change_mask.contains(LinkChangeMask::STATE) {
+            self.state = update.state().into();
+        }
+        if change_mask.contains(LinkChangeMask::FORMAT) {
+            self.format = update.format().map(aligned_format);
+        }
+        if change_mask.contains(LinkChangeMask::PROPS) {
+            self.props = props(update.props());
+        }
+    }
+}
+
+impl From<&LinkInfoRef> for LinkInfoSnapshot {
+    fn from(info: &LinkInfoRef) -> Self {
+        Self {
+            id: info.id(),
+            output_node_id: info.output_node_id(),
+            output_port_id: info.output_port_id(),
+            input_node_id: info.input_node_id(),
+            input_port_id: info.input_port_id(),
+            change_mask: info.change_mask(),
+            state: info.state().into(),
+            format: info.format().map(aligned_format),
+            props: props(info.props()),
+        }
+    }
+}
+
+/// Owned snapshot of a [`DeviceInfoRef`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DeviceInfoSnapshot {
+    pub id: u32,
+    pub change_mask: DeviceChangeMask,
+    pub props: Props,
+    #[cfg_attr(feature = "serde", serde(with = "param_infos"))]
+    pub params: Vec<ParamInfo>,
+}
+
+impl DeviceInfoSnapshot {
+    /// Apply the fields of `update` set in its change mask.
+    pub fn update(&mut self, update: &DeviceInfoRef) {
+        let change_mask = update.change_mask();
+        self.id = update.id();
+        self.change_mask = change_mask;
+        if change_mask.contains(DeviceChangeMask::PROPS) {
+            self.props = props(update.props());
+        }
+        if change_mask.contains(DeviceChangeMask::PARAMS) {
+            self.params = update.params().to_vec();
+        }
+    }
+}
+
+impl From<&DeviceInfoRef> for DeviceInfoSnapshot {
+    fn from(info: &DeviceInfoRef) -> Self {
+        Self {
+            id: info.id(),
+            change_mask: info.change_mask(),
+            props: props(info.props()),
+            params: info.params().to_vec(),
+        }
+    }
+}
+
+/// Owned snapshot of a [`ClientInfoRef`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ClientInfoSnapshot {
+    pub id: u32,
+    pub change_mask: ClientChangeMask,
+    pub props: Props,
+}
+
+impl ClientInfoSnapshot {
+    /// Apply the fields of `update` set in its change mask.
+    pub fn update(&mut self, update: &ClientInfoRef) {
+        let change_mask = update.change_mask();
+        self.id = update.id();
+        self.change_mask = change_mask;
+        if change_mask.contains(ClientChangeMask::PROPS) {
+            self.props = props(update.props());
+        }
+    }
+}
+
+impl From<&ClientInfoRef> for ClientInfoSnapshot {
+    fn from(info: &ClientInfoRef) -> Self {
+        Self {
+            id: info.id(),
+            change_mask: info.change_mask(),
+            props: props(info.props()),
+        }
+    }
+}
+
+/// Owned snapshot of a [`ModuleInfoRef`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ModuleInfoSnapshot {
+    pub id: u32,
+    pub name: String,
+    pub filename: String,
+    pub args: Option<String>,
+    pub change_mask: ModuleChangeMask,
+    pub props: Props,
+}
+
+impl ModuleInfoSnapshot {
+    /// Apply the fields of `update` set in its change mask.
+    pub fn update(&mut self, update: &ModuleInfoRef) {
+        let change_mask = update.change_mask();
+        self.id = update.id();
+        self.name = update.name().to_owned();
+        self.filename = update.filename().to_owned();
+        self.args = update.args().map(str::to_owned);
+        self.change_mask = change_mask;
+        if change_mask.contains(ModuleChangeMask::PROPS) {
+            self.props = props(update.props());
+        }
+    }
+}
+
+impl From<&ModuleInfoRef> for ModuleInfoSnapshot {
+    fn from(info: &ModuleInfoRef) -> Self {
+        Self {
+            id: info.id(),
+            name: info.name().to_owned(),
+            filename: info.filename().to_owned(),
+            args: info.args().map(str::to_owned),
+            change_mask: info.change_mask(),
+            props: props(info.props()),
+        }
+    }
+}
+
+/// Owned snapshot of a [`FactoryInfoRef`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FactoryInfoSnapshot {
+    pub id: u32,
+    pub name: String,
+    #[cfg_attr(feature = "serde", serde(with = "object_type"))]
+    pub type_: ObjectType,
+    pub version: u32,
+    pub change_mask: FactoryChangeMask,
+    pub props: Props,
+}
+
+impl FactoryInfoSnapshot {
+    /// Apply the fields of `update` set in its change mask.
+    pub fn update(&mut self, update: &FactoryInfoRef) {
+        let change_mask = update.change_mask();
+        self.id = update.id();
+        self.name = update.name().to_owned();
+        self.type_ = update.type_();
+        self.version = update.version();
+        self.change_mask = change_mask;
+        if change_mask.contains(FactoryChangeMask::PROPS) {
+            self.props = props(update.props());
+        }
+    }
+}
+
+impl From<&FactoryInfoRef> for FactoryInfoSnapshot {
+    fn from(info: &FactoryInfoRef) -> Self {
+        Self {
+            id: info.id(),
+            name: info.name().to_owned(),
+            type_: info.type_(),
+            version: info.version(),
+            change_mask: info.change_mask(),
+            props: props(info.props()),
+        }
+    }
+}
+
+/// A snapshot kept up to date from the info events of a proxy.
+pub(crate) trait InfoSnapshot: Clone + 'static {
+    type Info: ?Sized;
+
+    fn new(info: &Self::Info) -> Self;
+
+    fn update(&mut self, info: &Self::Info);
+
+    /// The params of `info`, if they are set in its change mask.
+    fn changed_params(info: &Self::Info) -> Option<&[ParamInfo]> {
+        let _ = info;
+        None
+    }
+}
+
+/// A proxy whose info events can be kept in an [`InfoSnapshot`].
+pub(crate) trait InfoProxy: ProxyT + 'static {
+    type Snapshot: InfoSnapshot;
+
+    fn add_info_listener<F>(&self, info: F) -> Box<dyn Listener>
+    where
+        F: Fn(&<Self::Snapshot as InfoSnapshot>::Info) + 'static;
+}
+
+/// Update the snapshot held by `object` with `info`, or replace `object` by a new snapshot
+/// if it does not hold one of the same type.
+///
+/// `wrap` and `get` convert between the snapshot and the type of `object`,
+/// usually an enum of snapshots.
+pub(crate) fn update_object<T, S: InfoSnapshot>(
+    object: &mut Option<T>,
+    info: &S::Info,
+    wrap: fn(S) -> T,
+    get: fn(&mut T) -> Option<&mut S>,
+) {
+    match object.as_mut().and_then(get) {
+        Some(snapshot) => snapshot.update(info),
+        None => *object = Some(wrap(S::new(info))),
+    }
+}
+
+macro_rules! impl_info_proxy {
+    ($proxy:ty, $snapshot:ty, $info:ty $(, $params:expr)?) => {
+        impl InfoSnapshot for $snapshot {
+            type Info = $info;
+
+            fn new(info: &$info) -> Self {
+                Self::from(info)
+            }
+
+            fn update(&mut self, info: &$info) {
+                <$snapshot>::update(self, info)
+            }
+
+            $(
+                fn changed_params(info: &$info) -> Option<&[ParamInfo]> {
+                    info.change_mask().contains($params).then(|| info.params())
+                }
+            )?
+        }
+
+        impl InfoProxy for $proxy {
+            type Snapshot = $snapshot;
+
+            fn add_info_listener<F>(&self, info: F) -> Box<dyn Listener>
+            where
+                F: Fn(&$info) + 'static,
+            {
+                Box::new(self.add_listener_local().info(info).register())
+            }
+        }
+    };
+}
+
+impl_info_proxy!(Node, NodeInfoSnapshot, NodeInfoRef, NodeChangeMask::PARAMS);
+impl_info_proxy!(Port, PortInfoSnapshot, PortInfoRef, PortChangeMask::PARAMS);
+impl_info_proxy!(Link, LinkInfoSnapshot, LinkInfoRef);
+impl_info_proxy!(
+    Device,
+    DeviceInfoSnapshot,
+    DeviceInfoRef,
+    DeviceChangeMask::PARAMS
+);
+impl_info_proxy!(Client, ClientInfoSnapshot, ClientInfoRef);
+impl_info_proxy!(Module, ModuleInfoSnapshot, ModuleInfoRef);
+impl_info_proxy!(Factory, FactoryInfoSnapshot, FactoryInfoRef);
+
+/// Serialize param infos as a list of `{ "id": u32, "flags": u32 }`.
+#[cfg(feature = "serde")]
+mod param_infos {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use spa::param::{ParamInfo, ParamInfoFlags, ParamType};
+
+    #[derive(Serialize, Deserialize)]
+    struct Entry {
+        id: u32,
+        flags: u32,
+    }
+
+    pub fn serialize<S: Serializer>(
+        params: &[ParamInfo],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(params.iter().map(|param| Entry {
+            id: param.id().as_raw(),
+            flags: param.flags().bits(),
+        }))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<ParamInfo>, D::Error> {
+        let entries = Vec::<Entry>::deserialize(deserializer)?;
+        Ok(entries
+            .into_iter()
+            .map(|entry| {
+                ParamInfo::new(
+                    ParamType::from_raw(entry.id),
+                    ParamInfoFlags::from_bits_truncate(entry.flags),
+                )
+            })
+            .collect())
+    }
+}
+
+/// Serialize directions as `"input"` or `"output"`.
+#[cfg(feature = "serde")]
+mod direction {
+    use serde::{de, ser, Deserialize, Deserializer, Serializer};
+    use spa::utils::Direction;
+
+    pub fn serialize<S: Serializer>(
+        direction: &Direction,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match *direction {
+            Direction::Input => serializer.serialize_str("input"),
+            Direction::Output => serializer.serialize_str("output"),
+            other => Err(ser::Error::custom(format_args!(
+                "invalid direction {}",
+                other.as_raw()
+            ))),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Direction, D::Error> {
+        match String::deserialize(deserializer)?.as_str() {
+            "input" => Ok(Direction::Input),
+            "output" => Ok(Direction::Output),
+            other => Err(de::Error::unknown_variant(other, &["input", "output"])),
+        }
+    }
+}
+
+/// Serialize pods as their bytes.
+#[cfg(feature = "serde")]
+mod pod {
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::utils;
+
+    pub fn serialize<S: Serializer>(
+        pod: &Option<Vec<u64>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        pod.as_deref()
+            .and_then(utils::aligned_pod)
+            .map(|pod| pod.as_bytes())
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<u64>>, D::Error> {
+        let Some(bytes) = Option::<Vec<u8>>::deserialize(deserializer)? else {
+            return Ok(None);
+        };
+
+        let buf = utils::aligned_pod_bytes(&bytes);
+        match utils::aligned_pod(&buf) {
+            Some(_) => Ok(Some(buf)),
+            None => Err(de::Error::custom("invalid pod")),
+        }
+    }
+}
+
+/// Serialize object types as their type name, e.g. `"PipeWire:Interface:Node"`.
+#[cfg(feature = "serde")]
+mod object_type {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use crate::types::ObjectType;
+
+    pub fn serialize<S: Serializer>(type_: &ObjectType, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(type_)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<ObjectType, D::Error> {
+        Ok(ObjectType::from_str(&String::deserialize(deserializer)?))
+    }
+}
+
+/// Raw infos, as received in the `info` events, for the tests of the snapshots and
+/// of the types built on them.
+#[cfg(test)]
+pub(crate) mod test_utils {
+    use std::{ffi::CStr, ptr};
+
+    use super::*;
+
+    /// A raw `pw_*_info` struct.
+    pub(crate) trait RawInfo: Copy {
+        type Ref;
+
+        /// The info, as passed to the `info` listeners.
+        fn info(&self) -> &Self::Ref;
+
+        /// A copy of the info only updating the fields set in `change_mask`.
+        ///
+        /// The props are not sent again, as when they did not change.
+        fn partial(&self, change_mask: u64) -> Self;
+    }
+
+    macro_rules! impl_raw_info {
+        ($raw:ty, $info:ty) => {
+            impl RawInfo for $raw {
+                type Ref = $info;
+
+                fn info(&self) -> &$info {
+                    // The info refs are transparent wrappers of the raw structs.
+                    unsafe { &*ptr::addr_of!(*self).cast::<$info>() }
+                }
+
+                fn partial(&self, change_mask: u64) -> Self {
+                    Self {
+                        change_mask,
+                        props: ptr::null_mut(),
+                        ..*self
+                    }
+                }
+            }
+        };
+    }
+
+    impl_raw_info!(pw_sys::pw_node_info, NodeInfoRef);
+    impl_raw_info!(pw_sys::pw_port_info, PortInfoRef);
+    impl_raw_info!(pw_sys::pw_link_info, LinkInfoRef);
+    impl_raw_info!(pw_sys::pw_module_info, ModuleInfoRef);
+
+    /// An idle node with one input port.
+    pub(crate) fn node_info(id: u32, props: &DictRef) -> pw_sys::pw_node_info {
+        pw_sys::pw_node_info {
+            id,
+            max_input_ports: 1,
+            max_output_ports: 0,
+            change_mask: NodeChangeMask::all().bits(),
+            n_input_ports: 1,
+            n_output_ports: 0,
+            state: pw_sys::pw_node_state_PW_NODE_STATE_IDLE,
+            error: ptr::null(),
+            props: props.as_raw_ptr(),
+            params: ptr::null_mut(),
+            n_params: 0,
+        }
+    }
+
+    pub(crate) fn port_info(
+        id: u32,
+        direction: Direction,
+        props: &DictRef,
+    ) -> pw_sys::pw_port_info {
+        pw_sys::pw_port_info {
+            id,
+            direction: direction.as_raw(),
+            change_mask: PortChangeMask::all().bits(),
+            props: props.as_raw_ptr(),
+            params: ptr::null_mut(),
+            n_params: 0,
+        }
+    }
+
+    /// A paused link from the port `output.1` of the node `output.0` to the port `input.1`
+    /// of the node `input.0`, without format.
+    pub(crate) fn link_info(
+        id: u32,
+        output: (u32, u32),
+        input: (u32, u32),
+        props: &DictRef,
+    ) -> pw_sys::pw_link_info {
+        pw_sys::pw_link_info {
+            id,
+            output_node_id: output.0,
+            output_port_id: output.1,
+            input_node_id: input.0,
+            input_port_id: input.1,
+            change_mask: LinkChangeMask::all().bits(),
+            state: pw_sys::pw_link_state_PW_LINK_STATE_PAUSED,
+            error: ptr::null(),
+            format: ptr::null_mut(),
+            props: props.as_raw_ptr(),
+        }
+    }
+
+    /// A module loaded from `filename`, without arguments.
+    pub(crate) fn module_info(
+        id: u32,
+        name: &'static CStr,
+        filename: &'static CStr,
+        props: &DictRef,
+    ) -> pw_sys::pw_module_info {
+        pw_sys::pw_module_info {
+            id,
+            name: name.as_ptr(),
+            filename: filename.as_ptr(),
+            args: ptr::null(),
+            change_mask: ModuleChangeMask::all().bits(),
+            props: props.as_raw_ptr(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr;
+
+    use spa::{
+        param::{ParamInfoFlags, ParamType},
+        pod::{serialize::PodSerializer, Value},
+        static_dict,
+    };
+
+    use super::{
+        test_utils::{link_info, node_info, RawInfo},
+        *,
+    };
+
+    fn assert_clone_send<T: Clone + Send>() {}
+
+    #[test]
+    fn snapshots_are_clone_and_send() {
+        assert_clone_send::<NodeInfoSnapshot>();
+        assert_clone_send::<PortInfoSnapshot>();
+        assert_clone_send::<LinkInfoSnapshot>();
+        assert_clone_send::<DeviceInfoSnapshot>();
+        assert_clone_send::<ClientInfoSnapshot>();
+        assert_clone_send::<ModuleInfoSnapshot>();
+        assert_clone_send::<FactoryInfoSnapshot>();
+    }
+
+    #[test]
+    fn states() {
+        assert_eq!(
+            NodeStateSnapshot::from(NodeState::Error("failed")),
+            NodeStateSnapshot::Error("failed".to_owned())
+        );
+        assert_eq!(
+            LinkStateSnapshot::from(LinkState::Active),
+            LinkStateSnapshot::Active
+        );
+    }
+
+    #[test]
+    fn update_node() {
+        let props = static_dict! { "node.name" => "sink" };
+        let mut params = [ParamInfo::new(ParamType::Props, ParamInfoFlags::READ)];
+        let mut raw = node_info(42, &props);
+        raw.params = params.as_mut_ptr().cast();
+        raw.n_params = params.len() as u32;
+        let mut snapshot = NodeInfoSnapshot::from(raw.info());
+        assert_eq!(
+            snapshot.props.get("node.name").map(String::as_str),
+            Some("sink")
+        );
+        assert_eq!(snapshot.params.len(), 1);
+        assert_eq!(snapshot.state, NodeStateSnapshot::Idle);
+
+        // Only the state is applied, the fields not in the change mask are kept.
+        let mut update = raw.partial(NodeChangeMask::STATE.bits());
+        update.state = pw_sys::pw_node_state_PW_NODE_STATE_RUNNING;
+        update.n_input_ports = 0;
+        update.params = ptr::null_mut();
+        update.n_params = 0;
+        snapshot.update(update.info());
+        assert_eq!(snapshot.change_mask, NodeChangeMask::STATE);
+        assert_eq!(snapshot.state, NodeStateSnapshot::Running);
+        assert_eq!(snapshot.n_input_ports, 1);
+        assert_eq!(
+            snapshot.props.get("node.name").map(String::as_str),
+            Some("sink")
+        );
+        assert_eq!(snapshot.params.len(), 1);
+
+        let clone = snapshot.clone();
+        assert_eq!(clone.props, snapshot.props);
+
+        let props = static_dict! { "node.name" => "source" };
+        update.change_mask = (NodeChangeMask::PROPS | NodeChangeMask::PARAMS).bits();
+        update.props = props.as_raw_ptr();
+        snapshot.update(update.info());
+        assert_eq!(
+            snapshot.props.get("node.name").map(String::as_str),
+            Some("source")
+        );
+        assert!(snapshot.params.is_empty());
+        assert_eq!(snapshot.state, NodeStateSnapshot::Running);
+    }
+
+    #[test]
+    fn update_link() {
+        let (cursor, _) =
+            PodSerializer::serialize(std::io::Cursor::new(Vec::new()), &Value::Int(3)).unwrap();
+        let bytes = cursor.into_inner();
+        let mut format = utils::aligned_pod_bytes(&bytes);
+        let props = static_dict! { "link.passive" => "false" };
+        let mut raw = link_info(42, (1, 2), (3, 4), &props);
+        raw.format = format.as_mut_ptr().cast();
+        let mut snapshot = LinkInfoSnapshot::from(raw.info());
+        assert_eq!(
+            snapshot.format().map(Pod::as_bytes),
+            Pod::from_bytes(&bytes).map(Pod::as_bytes)
+        );
+
+        let mut update = raw.partial(LinkChangeMask::STATE.bits());
+        update.state = pw_sys::pw_link_state_PW_LINK_STATE_ACTIVE;
+        update.format = ptr::null_mut();
+        snapshot.update(update.info());
+        assert_eq!(snapshot.state, LinkStateSnapshot::Active);
+        assert_eq!(
+            snapshot.format().map(Pod::as_bytes),
+            Pod::from_bytes(&bytes).map(Pod::as_bytes)
+        );
+
+        update.change_mask = LinkChangeMask::FORMAT.bits();
+        snapshot.update(update.info());
+        assert!(snapshot.format().is_none());
+        assert_eq!(
+            snapshot.props.get("link.passive").map(String::as_str),
+            Some("false")
+        );
+    }
+}
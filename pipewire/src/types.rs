@@ -29,11 +29,16 @@ macro_rules! object_type {
             }
 
             pub(crate) fn client_version(&self) -> u32 {
+                self.try_client_version().expect("Invalid object type")
+            }
+
+            /// The interface version implemented by this library, or [`None`] for unknown types.
+            pub(crate) fn try_client_version(&self) -> Option<u32> {
                 match self {
                     $(
-                        ObjectType::$x => pw_sys::$version,
+                        ObjectType::$x => Some(pw_sys::$version),
                     )*
-                    ObjectType::Other(_) => panic!("Invalid object type"),
+                    ObjectType::Other(_) => None,
                 }
             }
         }
@@ -65,5 +70,6 @@ object_type![
     (Port, PW_VERSION_PORT),
     (Profiler, PW_VERSION_PROFILER),
     (Registry, PW_VERSION_REGISTRY),
+    (SecurityContext, PW_VERSION_SECURITY_CONTEXT),
     (Session, PW_VERSION_SESSION)
 ];
@@ -1,8 +1,122 @@
 // Copyright The pipewire-rs Contributors.
 // SPDX-License-Identifier: MIT
 
-use std::thread;
+use std::{
+    cell::RefCell,
+    rc::{Rc, Weak},
+    thread,
+};
+
+use spa::{
+    param::ParamType,
+    pod::{serialize::PodSerializer, Object, Pod, Value},
+};
+
+use crate::Error;
 
 pub fn assert_main_thread() {
     assert_eq!(thread::current().name(), Some("main"));
 }
+
+/// Serialize `object` into the bytes of a pod, to be set as the `id` param.
+pub(crate) fn serialize_param(id: ParamType, object: Object) -> Result<Vec<u8>, Error> {
+    let (cursor, _) =
+        PodSerializer::serialize(std::io::Cursor::new(Vec::new()), &Value::Object(object))
+            .map_err(|_| Error::SetParam {
+                id,
+                errno: nix::errno::Errno::EINVAL,
+            })?;
+
+    Ok(cursor.into_inner())
+}
+
+/// Copy the bytes of a pod into a buffer of `u64`, so that the pod is correctly aligned.
+///
+/// The buffer is zero-padded to a multiple of 8 bytes, as expected by [`Pod::from_bytes`].
+pub(crate) fn aligned_pod_bytes(bytes: &[u8]) -> Vec<u64> {
+    let mut buf = vec![0u64; bytes.len().div_ceil(std::mem::size_of::<u64>())];
+    // SAFETY: `buf` is at least `bytes.len()` bytes long.
+    unsafe {
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), buf.as_mut_ptr().cast(), bytes.len());
+    }
+
+    buf
+}
+
+/// The pod stored in a buffer filled by [`aligned_pod_bytes`].
+pub(crate) fn aligned_pod(buf: &[u64]) -> Option<&Pod> {
+    let bytes = unsafe {
+        std::slice::from_raw_parts(buf.as_ptr().cast::<u8>(), std::mem::size_of_val(buf))
+    };
+
+    Pod::from_bytes(bytes)
+}
+
+/// The listeners registered on an object implemented in Rust, such as a
+/// [`Graph`](`crate::graph::Graph`).
+///
+/// Only weak references to the callbacks are kept, the listener is removed when the [`Rc`]
+/// returned by [`register()`](`Self::register`) is dropped.
+pub(crate) struct LocalListeners<C> {
+    listeners: RefCell<Vec<Weak<C>>>,
+}
+
+impl<C> Default for LocalListeners<C> {
+    fn default() -> Self {
+        Self {
+            listeners: RefCell::new(Vec::new()),
+        }
+    }
+}
+
+impl<C> LocalListeners<C> {
+    pub(crate) fn register(&self, cbs: C) -> Rc<C> {
+        let cbs = Rc::new(cbs);
+        self.listeners.borrow_mut().push(Rc::downgrade(&cbs));
+        cbs
+    }
+
+    /// Call `f` with the callbacks of each listener still registered.
+    ///
+    /// The listeners are not borrowed while `f` is called, so that the callbacks can
+    /// register or drop listeners.
+    pub(crate) fn emit(&self, f: impl Fn(&C)) {
+        let listeners: Vec<_> = {
+            let mut listeners = self.listeners.borrow_mut();
+            listeners.retain(|cbs| cbs.strong_count() > 0);
+            listeners.iter().filter_map(Weak::upgrade).collect()
+        };
+
+        for cbs in &listeners {
+            f(cbs);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    #[test]
+    fn local_listeners() {
+        let listeners = LocalListeners::default();
+        let first = listeners.register(1);
+        let second = listeners.register(2);
+
+        let sum = Cell::new(0);
+        listeners.emit(|cbs| sum.set(sum.get() + cbs));
+        assert_eq!(sum.get(), 3);
+
+        drop(first);
+        sum.set(0);
+        listeners.emit(|cbs| {
+            sum.set(sum.get() + cbs);
+            // Registering from a callback does not conflict with the emission.
+            let _ = listeners.register(4);
+        });
+        assert_eq!(sum.get(), 2);
+        drop(second);
+    }
+}
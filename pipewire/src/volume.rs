@@ -0,0 +1,397 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Volume and mute control of nodes.
+//!
+//! Nodes hold their volumes in their `Props` param, as linear factors per channel.
+//! Volume sliders usually show the cubic root of these factors, like PulseAudio does,
+//! see [`VolumeScale`] to convert between the scales.
+//!
+//! The volume of the nodes of a device, such as a sound card, is controlled through
+//! the active `Route` of the device instead, so the change is applied to the hardware
+//! mixer and saved by the session manager.
+//! [`NodeVolume`] takes care of both cases.
+
+use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
+
+use spa::{
+    param::{
+        device::{Route, RouteProps},
+        ParamType,
+    },
+    pod::{deserialize::PodDeserializer, Object, Pod, Value},
+    utils::result::AsyncSeq,
+};
+
+use crate::{
+    device::{Device, DeviceListener},
+    node::{Node, NodeListener},
+    proxy::ProxyT,
+    utils::LocalListeners,
+    Error,
+};
+
+/// The node property holding the index of the device of the profile a node belongs to.
+const PROFILE_DEVICE_KEY: &str = "card.profile.device";
+
+/// A scale of volume values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VolumeScale {
+    /// Linear factors, as used by the `Props` of nodes.
+    Linear,
+    /// Cubic root of the linear factors, as shown by PulseAudio volume sliders.
+    Cubic,
+    /// Decibels, with a linear factor of `0.0` being negative infinity.
+    Decibel,
+}
+
+impl VolumeScale {
+    /// Convert `value` of this scale to a linear factor.
+    pub fn to_linear(&self, value: f32) -> f32 {
+        match self {
+            Self::Linear => value,
+            Self::Cubic => value.max(0.0).powi(3),
+            Self::Decibel => 10f32.powf(value / 20.0),
+        }
+    }
+
+    /// Convert the linear factor `linear` to this scale.
+    pub fn from_linear(&self, linear: f32) -> f32 {
+        match self {
+            Self::Linear => linear,
+            Self::Cubic => linear.max(0.0).cbrt(),
+            Self::Decibel => 20.0 * linear.log10(),
+        }
+    }
+
+    /// Convert `value` of this scale to the scale `to`.
+    pub fn convert(&self, value: f32, to: VolumeScale) -> f32 {
+        to.from_linear(self.to_linear(value))
+    }
+}
+
+/// The volume of a node.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Volume {
+    /// The volume of each channel, as linear factors.
+    pub channel_volumes: Vec<f32>,
+    /// The channel positions of the volumes, as `spa_audio_channel` ids.
+    pub channel_map: Vec<u32>,
+    pub mute: bool,
+}
+
+impl Volume {
+    /// The overall volume, as a linear factor, which is the volume of the loudest channel.
+    pub fn volume(&self) -> f32 {
+        self.channel_volumes.iter().copied().fold(0.0, f32::max)
+    }
+
+    /// The overall volume, in the `scale` scale.
+    pub fn volume_in(&self, scale: VolumeScale) -> f32 {
+        scale.from_linear(self.volume())
+    }
+}
+
+/// Scale the channel volumes so that the loudest channel has the volume `volume`,
+/// keeping the balance between channels.
+fn scale_volumes(volumes: &[f32], volume: f32) -> Vec<f32> {
+    let max = volumes.iter().copied().fold(0.0, f32::max);
+    volumes
+        .iter()
+        .map(|v| if max > 0.0 { v * volume / max } else { volume })
+        .collect()
+}
+
+fn pod_object(pod: &Pod) -> Option<Object> {
+    match PodDeserializer::deserialize_any_from(pod.as_bytes()) {
+        Ok((_, Value::Object(object))) => Some(object),
+        _ => None,
+    }
+}
+
+type ChangedCallback = dyn Fn(&Volume);
+
+#[derive(Default)]
+struct ListenerLocalCallbacks {
+    changed: Option<Box<ChangedCallback>>,
+}
+
+#[derive(Default)]
+struct State {
+    volume: Option<Volume>,
+    profile_device: Option<u32>,
+    /// The active routes of the device, by device index.
+    routes: BTreeMap<u32, Route>,
+}
+
+impl State {
+    fn route(&self) -> Option<&Route> {
+        self.routes.get(&self.profile_device?)
+    }
+}
+
+#[derive(Default)]
+struct Inner {
+    state: RefCell<State>,
+    listeners: LocalListeners<ListenerLocalCallbacks>,
+}
+
+impl Inner {
+    fn node_props(&self, props: &RouteProps) {
+        let volume = {
+            let mut state = self.state.borrow_mut();
+            let Some(channel_volumes) = &props.channel_volumes else {
+                return;
+            };
+            let volume = Volume {
+                channel_volumes: channel_volumes.clone(),
+                channel_map: props.channel_map.clone().unwrap_or_default(),
+                mute: props.mute.unwrap_or_default(),
+            };
+            if state.volume.as_ref() == Some(&volume) {
+                return;
+            }
+            state.volume = Some(volume.clone());
+            volume
+        };
+
+        self.listeners.emit(|cbs| {
+            if let Some(changed) = &cbs.changed {
+                changed(&volume);
+            }
+        });
+    }
+}
+
+/// Reads and sets the volume and mute state of a node.
+///
+/// The volumes are read from the `Props` param of the node.
+/// When the device of the node is given, volume changes are routed through the active route
+/// of the device for the node, falling back to the node `Props` if the device has none.
+pub struct NodeVolume {
+    // Listeners need to be dropped before the proxies they are registered on.
+    _node_listener: NodeListener,
+    _device_listener: Option<DeviceListener>,
+    node: Node,
+    device: Option<Device>,
+    inner: Rc<Inner>,
+}
+
+impl NodeVolume {
+    /// Start tracking the volume of `node`, owned by `device` if it is backed by one,
+    /// the device being the object of the `device.id` property of the node.
+    pub fn new(node: Node, device: Option<Device>) -> Result<Self, Error> {
+        let inner = Rc::new(Inner::default());
+
+        let node_listener = node
+            .add_listener_local()
+            .info({
+                let inner = Rc::downgrade(&inner);
+                move |info| {
+                    let Some(inner) = inner.upgrade() else {
+                        return;
+                    };
+                    if let Some(props) = info.props() {
+                        inner.state.borrow_mut().profile_device = props
+                            .get(PROFILE_DEVICE_KEY)
+                            .and_then(|device| device.parse().ok());
+                    }
+                }
+            })
+            .param({
+                let inner = Rc::downgrade(&inner);
+                move |_seq, id, _index, _next, param| {
+                    let Some(inner) = inner.upgrade() else {
+                        return;
+                    };
+                    if id != ParamType::Props {
+                        return;
+                    }
+                    let props = param
+                        .and_then(pod_object)
+                        .and_then(|object| RouteProps::from_object(&object).ok());
+                    if let Some(props) = props {
+                        inner.node_props(&props);
+                    }
+                }
+            })
+            .register();
+        node.subscribe_params(&[ParamType::Props])?;
+
+        let device_listener = match &device {
+            Some(device) => {
+                let listener = device
+                    .add_listener_local()
+                    .param({
+                        let inner = Rc::downgrade(&inner);
+                        move |_seq, id, index, _next, param| {
+                            let Some(inner) = inner.upgrade() else {
+                                return;
+                            };
+                            if id != ParamType::Route {
+                                return;
+                            }
+
+                            let mut state = inner.state.borrow_mut();
+                            // The routes are enumerated again when they change.
+                            if index == 0 {
+                                state.routes.clear();
+                            }
+                            let route = param
+                                .and_then(pod_object)
+                                .and_then(|object| Route::try_from(&object).ok());
+                            if let Some(route) = route {
+                                if let Some(device) = route.device {
+                                    state.routes.insert(device, route);
+                                }
+                            }
+                        }
+                    })
+                    .register();
+                device.subscribe_params(&[ParamType::Route])?;
+                Some(listener)
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            _node_listener: node_listener,
+            _device_listener: device_listener,
+            node,
+            device,
+            inner,
+        })
+    }
+
+    #[must_use]
+    pub fn add_listener_local(&self) -> NodeVolumeListenerLocalBuilder {
+        NodeVolumeListenerLocalBuilder {
+            volume: self,
+            cbs: ListenerLocalCallbacks::default(),
+        }
+    }
+
+    pub fn node(&self) -> &Node {
+        &self.node
+    }
+
+    pub fn device(&self) -> Option<&Device> {
+        self.device.as_ref()
+    }
+
+    /// The current volume of the node, once its `Props` have been received.
+    pub fn volume(&self) -> Option<Volume> {
+        self.inner.state.borrow().volume.clone()
+    }
+
+    /// Set the volume of each channel, as linear factors.
+    pub fn set_channel_volumes(&self, volumes: &[f32]) -> Result<AsyncSeq, Error> {
+        self.set_props(RouteProps {
+            channel_volumes: Some(volumes.to_vec()),
+            ..Default::default()
+        })
+    }
+
+    /// Set the overall volume as a linear factor, keeping the balance between channels.
+    pub fn set_volume(&self, volume: f32) -> Result<AsyncSeq, Error> {
+        let volumes = self
+            .inner
+            .state
+            .borrow()
+            .volume
+            .as_ref()
+            .map(|current| scale_volumes(&current.channel_volumes, volume))
+            .ok_or_else(|| Error::VolumeUnknown(self.node.upcast_ref().id()))?;
+
+        self.set_channel_volumes(&volumes)
+    }
+
+    /// Set the overall volume in the `scale` scale, keeping the balance between channels.
+    pub fn set_volume_in(&self, scale: VolumeScale, volume: f32) -> Result<AsyncSeq, Error> {
+        self.set_volume(scale.to_linear(volume))
+    }
+
+    pub fn set_mute(&self, mute: bool) -> Result<AsyncSeq, Error> {
+        self.set_props(RouteProps {
+            mute: Some(mute),
+            ..Default::default()
+        })
+    }
+
+    fn set_props(&self, props: RouteProps) -> Result<AsyncSeq, Error> {
+        let state = self.inner.state.borrow();
+        if let (Some(device), Some(route)) = (&self.device, state.route()) {
+            let mut route = Route::new(route.index, route.direction, route.device.unwrap_or(0));
+            route.props = Some(props);
+            route.save = true;
+            return device.set_route(&route);
+        }
+
+        let bytes =
+            crate::utils::serialize_param(ParamType::Props, props.to_object(ParamType::Props))?;
+        let pod = Pod::from_bytes(&bytes).expect("Serialized param is a valid pod");
+
+        self.node.set_param(ParamType::Props, 0, pod)
+    }
+}
+
+pub struct NodeVolumeListenerLocalBuilder<'a> {
+    volume: &'a NodeVolume,
+    cbs: ListenerLocalCallbacks,
+}
+
+/// A listener of the volume changes of a [`NodeVolume`], removed when dropped.
+pub struct NodeVolumeListener {
+    _cbs: Rc<ListenerLocalCallbacks>,
+}
+
+impl<'a> NodeVolumeListenerLocalBuilder<'a> {
+    /// Called with the new volume when the volume or mute state of the node changed.
+    #[must_use]
+    pub fn changed<F>(mut self, changed: F) -> Self
+    where
+        F: Fn(&Volume) + 'static,
+    {
+        self.cbs.changed = Some(Box::new(changed));
+        self
+    }
+
+    #[must_use]
+    pub fn register(self) -> NodeVolumeListener {
+        NodeVolumeListener {
+            _cbs: self.volume.inner.listeners.register(self.cbs),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scales() {
+        assert_eq!(VolumeScale::Cubic.to_linear(0.5), 0.125);
+        assert!((VolumeScale::Cubic.from_linear(0.125) - 0.5).abs() < 1e-6);
+        assert_eq!(VolumeScale::Cubic.from_linear(-1.0), 0.0);
+
+        assert_eq!(VolumeScale::Decibel.from_linear(1.0), 0.0);
+        assert!((VolumeScale::Decibel.from_linear(0.1) + 20.0).abs() < 1e-4);
+        assert_eq!(VolumeScale::Decibel.from_linear(0.0), f32::NEG_INFINITY);
+        assert_eq!(VolumeScale::Decibel.to_linear(f32::NEG_INFINITY), 0.0);
+
+        let db = VolumeScale::Cubic.convert(0.5, VolumeScale::Decibel);
+        assert!((db - 20.0 * 0.125f32.log10()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn master_volume() {
+        let volume = Volume {
+            channel_volumes: vec![0.5, 0.25],
+            ..Default::default()
+        };
+        assert_eq!(volume.volume(), 0.5);
+        assert_eq!(scale_volumes(&volume.channel_volumes, 1.0), vec![1.0, 0.5]);
+        assert_eq!(scale_volumes(&[0.0, 0.0], 0.5), vec![0.5, 0.5]);
+        assert_eq!(Volume::default().volume(), 0.0);
+    }
+}